@@ -0,0 +1,344 @@
+use std::cmp;
+use std::time::{Duration, Instant};
+
+use imageview::{ImageBuffer, Repeat};
+
+/// Frames with a shorter delay than this get clamped up to it.
+///
+/// Lots of gifs in the wild use a 0 or 10ms delay and expect to be slowed down.
+pub const MIN_FRAME_DELAY_MS: u64 = 20;
+
+/// Something that can tell the time, relative to some fixed point
+pub trait TimeSource {
+    fn now(&self) -> Duration;
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemClock(Instant);
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock(Instant::now())
+    }
+}
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+#[derive(Debug)]
+pub struct Animation<T = SystemClock> {
+    delays: Vec<Duration>,
+    repeat: Repeat,
+    frame: usize,
+    plays: u32,
+    playing: bool,
+    deadline: Duration,  // when the current frame should be replaced, while playing
+    remaining: Duration, // how much of the current frame is left, while paused
+    clock: T,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self::new(vec![], Repeat::Finite(1), SystemClock::default())
+    }
+}
+
+impl<T: TimeSource> Animation<T> {
+    pub fn new(delays: Vec<Duration>, repeat: Repeat, clock: T) -> Self {
+        let min = Duration::from_millis(MIN_FRAME_DELAY_MS);
+        let delays = delays
+            .into_iter()
+            .map(|d| cmp::max(d, min))
+            .collect::<Vec<_>>();
+
+        let now = clock.now();
+        let first = delays.first().cloned().unwrap_or_default();
+        Self {
+            playing: delays.len() > 1,
+            delays,
+            repeat,
+            frame: 0,
+            plays: 0,
+            deadline: now + first,
+            remaining: first,
+            clock,
+        }
+    }
+
+    pub fn for_image(image: &ImageBuffer, clock: T) -> Self {
+        let delays = (0..image.frames())
+            .map(|n| image.frame(n).delay())
+            .collect();
+        Self::new(delays, image.repeat(), clock)
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn frames(&self) -> usize {
+        self.delays.len()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// how many times it has played all the way through
+    pub fn plays(&self) -> u32 {
+        self.plays
+    }
+
+    pub fn play(&mut self) {
+        if self.playing || self.frames() < 2 {
+            return;
+        }
+
+        // restart from the top if we've played through all of the loops
+        if self.is_finished() {
+            self.plays = 0;
+            self.frame = 0;
+            self.remaining = self.delays[0];
+        }
+
+        trace!("resuming animation at frame {}", self.frame);
+        self.deadline = self.clock.now() + self.remaining;
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        if !self.playing {
+            return;
+        }
+
+        trace!("pausing animation at frame {}", self.frame);
+        self.remaining = self.time_to_next().unwrap_or_default();
+        self.playing = false;
+    }
+
+    /// returns whether the animation is now playing
+    pub fn toggle(&mut self) -> bool {
+        if self.playing {
+            self.pause()
+        } else {
+            self.play()
+        }
+        self.playing
+    }
+
+    /// pauses the animation and moves to the next frame, wrapping around
+    pub fn step_forward(&mut self) -> usize {
+        if self.frames() == 0 {
+            return 0;
+        }
+
+        self.pause();
+        let next = if self.frame + 1 == self.frames() {
+            0
+        } else {
+            self.frame + 1
+        };
+        self.seek(next)
+    }
+
+    /// pauses the animation and moves to the previous frame, wrapping around
+    pub fn step_back(&mut self) -> usize {
+        if self.frames() == 0 {
+            return 0;
+        }
+
+        self.pause();
+        let prev = if self.frame == 0 {
+            self.frames() - 1
+        } else {
+            self.frame - 1
+        };
+        self.seek(prev)
+    }
+
+    /// jumps to a specific frame. the frame gets its full delay
+    pub fn seek(&mut self, frame: usize) -> usize {
+        if self.frames() == 0 {
+            return 0;
+        }
+
+        self.frame = cmp::min(frame, self.frames() - 1);
+        self.remaining = self.delays[self.frame];
+        self.deadline = self.clock.now() + self.remaining;
+        self.frame
+    }
+
+    /// advances past every frame whose time is up. returns whether the frame changed
+    pub fn tick(&mut self) -> bool {
+        if !self.playing {
+            return false;
+        }
+
+        let now = self.clock.now();
+        let total = self.delays.iter().fold(Duration::default(), |a, d| a + *d);
+        if now > self.deadline + total {
+            // we've fallen more than a whole loop behind (e.g. the window was being dragged),
+            // so don't bother trying to catch up
+            self.deadline = now;
+        }
+
+        let start = self.frame;
+        while self.playing && now >= self.deadline {
+            if self.frame + 1 < self.frames() {
+                self.frame += 1;
+            } else {
+                self.plays += 1;
+                if self.is_finished() {
+                    debug!("animation finished after {} plays", self.plays);
+                    self.playing = false;
+                    self.remaining = Duration::default();
+                    break;
+                }
+                self.frame = 0;
+            }
+            self.deadline += self.delays[self.frame];
+        }
+
+        self.frame != start
+    }
+
+    /// how long until the next frame is due, if playing
+    pub fn time_to_next(&self) -> Option<Duration> {
+        if !self.playing {
+            return None;
+        }
+
+        let now = self.clock.now();
+        Some(if self.deadline > now {
+            self.deadline - now
+        } else {
+            Duration::default()
+        })
+    }
+
+    fn is_finished(&self) -> bool {
+        match self.repeat {
+            Repeat::Infinite => false,
+            Repeat::Finite(n) => self.plays >= n,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct FakeClock(Rc<Cell<Duration>>);
+
+    impl FakeClock {
+        fn advance(&self, ms: u64) {
+            self.0.set(self.0.get() + Duration::from_millis(ms))
+        }
+    }
+
+    impl TimeSource for FakeClock {
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+    }
+
+    fn animation(delays: &[u64], repeat: Repeat) -> (Animation<FakeClock>, FakeClock) {
+        let clock = FakeClock::default();
+        let delays = delays.iter().map(|&d| Duration::from_millis(d)).collect();
+        (Animation::new(delays, repeat, clock.clone()), clock)
+    }
+
+    #[test]
+    fn advances_on_delay() {
+        let (mut anim, clock) = animation(&[100, 50, 200], Repeat::Infinite);
+        assert!(anim.is_playing());
+
+        clock.advance(99);
+        assert!(!anim.tick());
+        assert_eq!(anim.frame(), 0);
+
+        clock.advance(1);
+        assert!(anim.tick());
+        assert_eq!(anim.frame(), 1);
+
+        // skipping over a frame
+        clock.advance(250);
+        assert!(anim.tick());
+        assert_eq!(anim.frame(), 0);
+        assert_eq!(anim.time_to_next(), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn pause_keeps_remaining_time() {
+        let (mut anim, clock) = animation(&[100, 100], Repeat::Infinite);
+        clock.advance(60);
+        assert!(!anim.toggle());
+
+        clock.advance(1000);
+        assert!(!anim.tick());
+        assert_eq!(anim.frame(), 0);
+
+        assert!(anim.toggle());
+        assert_eq!(anim.time_to_next(), Some(Duration::from_millis(40)));
+        clock.advance(40);
+        assert!(anim.tick());
+        assert_eq!(anim.frame(), 1);
+    }
+
+    #[test]
+    fn stepping() {
+        let (mut anim, _clock) = animation(&[100, 100, 100], Repeat::Infinite);
+        assert_eq!(anim.step_back(), 2);
+        assert!(!anim.is_playing());
+        assert_eq!(anim.step_forward(), 0);
+        assert_eq!(anim.step_forward(), 1);
+        assert_eq!(anim.seek(10), 2);
+    }
+
+    #[test]
+    fn finite_loops() {
+        let (mut anim, clock) = animation(&[100, 100], Repeat::Finite(2));
+        clock.advance(300);
+        anim.tick();
+        assert!(anim.is_playing());
+        assert_eq!(anim.frame(), 1);
+
+        clock.advance(100);
+        anim.tick();
+        assert!(!anim.is_playing());
+        assert_eq!(anim.frame(), 1);
+
+        // playing again starts over
+        anim.play();
+        assert_eq!(anim.frame(), 0);
+        assert!(anim.is_playing());
+    }
+
+    #[test]
+    fn clamps_short_delays() {
+        let (mut anim, clock) = animation(&[0, 10], Repeat::Infinite);
+        clock.advance(MIN_FRAME_DELAY_MS - 1);
+        assert!(!anim.tick());
+        clock.advance(1);
+        assert!(anim.tick());
+    }
+
+    #[test]
+    fn still_images_dont_play() {
+        let (mut anim, clock) = animation(&[0], Repeat::Infinite);
+        assert!(!anim.is_playing());
+        assert!(!anim.toggle());
+        clock.advance(1000);
+        assert!(!anim.tick());
+
+        let (mut anim, _) = animation(&[], Repeat::Infinite);
+        assert_eq!(anim.step_forward(), 0);
+        assert_eq!(anim.time_to_next(), None);
+    }
+}
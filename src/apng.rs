@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use image::{self, ImageError, ImageFormat, ImageResult};
+
+use compose::{Animated, Blend, Dispose, Part};
+use imageview::Repeat;
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// the animation in the png `data`. `None` if it's just a png
+///
+/// The png decoder doesn't know about the animation chunks, so every frame is put back
+/// together into a png of its own, with the chunks from the start of the file
+pub fn decode(data: &[u8]) -> ImageResult<Option<Animated>> {
+    if !data.starts_with(SIGNATURE) {
+        return Err(format_error("not a png"));
+    }
+    let chunks = chunks(&data[SIGNATURE.len()..])?;
+
+    let repeat = match chunks.iter().find(|&&(ty, _)| ty == b"acTL") {
+        Some(&(_, actl)) if actl.len() >= 8 => match be32(&actl[4..]) {
+            0 => Repeat::Infinite,
+            n => Repeat::Finite(n),
+        },
+        Some(_) => return Err(format_error("short acTL chunk")),
+        None => return Ok(None),
+    };
+    let ihdr = match chunks.first() {
+        Some(&(ty, ihdr)) if ty == b"IHDR" && ihdr.len() == 13 => ihdr,
+        _ => return Err(format_error("no IHDR chunk")),
+    };
+
+    // what every frame needs, like the palette
+    let shared = chunks
+        .iter()
+        .take_while(|&&(ty, _)| ty != b"IDAT")
+        .filter(|&&(ty, _)| ty != b"IHDR" && ty != b"acTL" && ty != b"fcTL")
+        .collect::<Vec<_>>();
+
+    let mut parts = vec![];
+    let mut control: Option<Control> = None;
+    let mut frame = vec![];
+    for &(ty, data) in &chunks {
+        match ty {
+            b"fcTL" => {
+                if let Some(control) = control.take() {
+                    parts.push(part(ihdr, &shared, control, &frame)?);
+                    frame.clear();
+                }
+                control = Some(Control::parse(data)?);
+            }
+            // without a fcTL before it, the default image isn't part of the animation
+            b"IDAT" if control.is_some() => frame.extend_from_slice(data),
+            b"fdAT" if data.len() >= 4 => frame.extend_from_slice(&data[4..]),
+            _ => {}
+        }
+    }
+    if let Some(control) = control {
+        parts.push(part(ihdr, &shared, control, &frame)?);
+    }
+
+    // there's nothing before the first frame to go back to
+    if let Some(first) = parts.first_mut() {
+        if first.dispose == Dispose::Previous {
+            first.dispose = Dispose::Background;
+        }
+    }
+
+    Ok(Some(Animated {
+        width: be32(ihdr),
+        height: be32(&ihdr[4..]),
+        repeat,
+        parts,
+    }))
+}
+
+// a fcTL chunk
+#[derive(Debug)]
+struct Control {
+    width: u32,
+    height: u32,
+    left: u32,
+    top: u32,
+    delay: Duration,
+    dispose: Dispose,
+    blend: Blend,
+}
+
+impl Control {
+    fn parse(data: &[u8]) -> ImageResult<Self> {
+        if data.len() < 26 {
+            return Err(format_error("short fcTL chunk"));
+        }
+
+        // a fraction of a second. no denominator means hundredths
+        let num = u64::from(be16(&data[20..]));
+        let den = match be16(&data[22..]) {
+            0 => 100,
+            n => u64::from(n),
+        };
+        Ok(Self {
+            width: be32(&data[4..]),
+            height: be32(&data[8..]),
+            left: be32(&data[12..]),
+            top: be32(&data[16..]),
+            delay: Duration::from_millis(num * 1000 / den),
+            dispose: match data[24] {
+                1 => Dispose::Background,
+                2 => Dispose::Previous,
+                _ => Dispose::Keep,
+            },
+            blend: match data[25] {
+                1 => Blend::Over,
+                _ => Blend::Source,
+            },
+        })
+    }
+}
+
+// decodes a frame, as a png of its own
+fn part(
+    ihdr: &[u8],
+    shared: &[&(&[u8], &[u8])],
+    control: Control,
+    idat: &[u8],
+) -> ImageResult<Part> {
+    let mut header = ihdr.to_vec();
+    header[..4].copy_from_slice(&control.width.to_be_bytes());
+    header[4..8].copy_from_slice(&control.height.to_be_bytes());
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    for &&(ty, data) in shared {
+        write_chunk(&mut png, ty, data);
+    }
+    write_chunk(&mut png, b"IDAT", idat);
+    write_chunk(&mut png, b"IEND", &[]);
+
+    let buffer = image::load_from_memory_with_format(&png, ImageFormat::PNG)?.to_rgba();
+    Ok(Part {
+        buffer,
+        left: control.left,
+        top: control.top,
+        delay: control.delay,
+        blend: control.blend,
+        dispose: control.dispose,
+    })
+}
+
+// (type, data), up to IEND
+fn chunks(mut data: &[u8]) -> ImageResult<Vec<(&[u8], &[u8])>> {
+    let mut chunks = vec![];
+    while !data.is_empty() {
+        let len = if data.len() >= 12 {
+            be32(data) as usize
+        } else {
+            0
+        };
+        if data.len() < 12 || data.len() - 12 < len {
+            return Err(format_error("truncated chunk"));
+        }
+        let ty = &data[4..8];
+        if ty == b"IEND" {
+            break;
+        }
+        chunks.push((ty, &data[8..8 + len]));
+        data = &data[12 + len..];
+    }
+    Ok(chunks)
+}
+
+fn write_chunk(out: &mut Vec<u8>, ty: &[u8], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(ty);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn be32(data: &[u8]) -> u32 {
+    (u32::from(data[0]) << 24)
+        | (u32::from(data[1]) << 16)
+        | (u32::from(data[2]) << 8)
+        | u32::from(data[3])
+}
+
+fn be16(data: &[u8]) -> u16 {
+    (u16::from(data[0]) << 8) | u16::from(data[1])
+}
+
+fn format_error(msg: &str) -> ImageError {
+    ImageError::FormatError(format!("apng: {}", msg))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn frames() {
+        let data = fs::read("testdata/animated/test.apng").unwrap();
+        let animated = decode(&data).unwrap().unwrap();
+        assert_eq!((animated.width, animated.height), (8, 6));
+        assert_eq!(animated.repeat, Repeat::Finite(2));
+
+        let parts = animated
+            .parts
+            .iter()
+            .map(|p| (p.left, p.top, p.buffer.dimensions(), p.delay.subsec_millis()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parts,
+            vec![
+                (0, 0, (8, 6), 200),
+                (2, 2, (4, 3), 300),
+                (0, 0, (2, 2), 100)
+            ]
+        );
+        let ops = animated
+            .parts
+            .iter()
+            .map(|p| (p.blend, p.dispose))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                (Blend::Source, Dispose::Keep),
+                (Blend::Over, Dispose::Background),
+                (Blend::Source, Dispose::Previous),
+            ]
+        );
+
+        // a png without the animation chunks is just a png
+        let still = fs::read("testdata/test.png").unwrap();
+        assert!(decode(&still).unwrap().is_none());
+        assert!(decode(&data[..60]).is_err());
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use sort::SortKey;
+
+/// What `pict --help` prints
+pub const USAGE: &str = "usage: pict [options] [file or directory]...
+
+options:
+    -i, --index <n>         start on the nth image in the list, counting from 1
+        --sort <key>        sort the list by `name`, `size`, `modified`, `extension`,
+                            `dimensions` or `taken`
+        --reverse           sort the list backwards
+        --shuffle           go through the list in a random order
+        --seed <n>          shuffle the same way every time, implies --shuffle
+    -r, --recursive         look for images in the directories below too
+        --depth <n>         only go n directories down, implies --recursive
+        --ignore <glob>     leave out files and directories matching this, can be repeated
+        --slideshow <secs>  move to the next image every so many seconds
+    -f, --fullscreen        start in fullscreen
+    -c, --config <path>     use this config file instead of pict.toml
+        --tui               show the images in the terminal
+    -h, --help              print this message";
+
+/// The command line
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// the files and directories to open
+    pub paths: Vec<PathBuf>,
+    /// where to start in the list, counting from 0
+    pub index: Option<usize>,
+    /// instead of the one in the config
+    pub sort: Option<SortKey>,
+    pub reverse: bool,
+    pub shuffle: bool,
+    /// instead of the one in the config
+    pub seed: Option<u64>,
+    /// these add to the scan options in the config
+    pub recursive: bool,
+    pub depth: Option<usize>,
+    pub ignore: Vec<String>,
+    pub slideshow: Option<Duration>,
+    pub fullscreen: bool,
+    pub config: Option<PathBuf>,
+    pub tui: bool,
+}
+
+/// Why the command line couldn't be used
+#[derive(Debug, PartialEq)]
+pub enum ArgsError {
+    /// `--help` was asked for. this isn't really an error
+    Help,
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue { flag: String, value: String },
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgsError::Help => write!(f, "{}", USAGE),
+            ArgsError::UnknownFlag(flag) => write!(f, "unknown option: {}", flag),
+            ArgsError::MissingValue(flag) => write!(f, "{} needs a value", flag),
+            ArgsError::InvalidValue { flag, value } => {
+                write!(f, "invalid value for {}: {:?}", flag, value)
+            }
+        }
+    }
+}
+
+impl Args {
+    /// parses the arguments, without the program name
+    pub fn parse<I>(args: I) -> Result<Self, ArgsError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut this = Self::default();
+        let mut args = args.into_iter();
+        let mut flags = true;
+
+        while let Some(arg) = args.next() {
+            if !flags || arg == "-" || !arg.starts_with('-') {
+                this.paths.push(arg.into());
+                continue;
+            }
+            if arg == "--" {
+                flags = false;
+                continue;
+            }
+
+            // `--flag=value` and `--flag value` are the same
+            let (flag, inline) = match arg.find('=') {
+                Some(n) if arg.starts_with("--") => (arg[..n].to_string(), Some(arg[n + 1..].to_string())),
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| ArgsError::MissingValue(flag.clone()))
+            };
+            let invalid = |value: String| ArgsError::InvalidValue {
+                flag: flag.clone(),
+                value,
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "-i" | "--index" => {
+                    let n = value()?;
+                    match n.parse::<usize>() {
+                        Ok(index) if index > 0 => this.index = Some(index - 1),
+                        _ => return Err(invalid(n)),
+                    }
+                }
+                "--sort" => {
+                    let key = value()?;
+                    this.sort = Some(key.parse().map_err(|_| invalid(key))?);
+                }
+                "--seed" => {
+                    let n = value()?;
+                    this.shuffle = true;
+                    this.seed = Some(n.parse().map_err(|_| invalid(n))?);
+                }
+                "--depth" => {
+                    let n = value()?;
+                    this.depth = Some(n.parse().map_err(|_| invalid(n))?);
+                }
+                "--ignore" => this.ignore.push(value()?),
+                "--slideshow" => {
+                    let secs = value()?;
+                    match secs.parse::<f64>() {
+                        Ok(s) if s > 0.0 && s.is_finite() => {
+                            let ms = (s * 1000.0).round() as u64;
+                            this.slideshow = Some(Duration::from_millis(ms));
+                        }
+                        _ => return Err(invalid(secs)),
+                    }
+                }
+                "-c" | "--config" => this.config = Some(value()?.into()),
+                "-f" | "--fullscreen" if inline.is_none() => this.fullscreen = true,
+                "--reverse" if inline.is_none() => this.reverse = true,
+                "--shuffle" if inline.is_none() => this.shuffle = true,
+                "-r" | "--recursive" if inline.is_none() => this.recursive = true,
+                "--tui" if inline.is_none() => this.tui = true,
+                _ => return Err(ArgsError::UnknownFlag(flag.clone())),
+            }
+        }
+
+        Ok(this)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, ArgsError> {
+        Args::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse(""), Ok(Args::default()));
+
+        let args = parse("-i 3 dir --sort=size --reverse --slideshow 2.5 -f -c other.toml --tui")
+            .unwrap();
+        assert_eq!(
+            args,
+            Args {
+                paths: vec!["dir".into()],
+                index: Some(2),
+                sort: Some(SortKey::Size),
+                reverse: true,
+                shuffle: false,
+                seed: None,
+                recursive: false,
+                depth: None,
+                ignore: vec![],
+                slideshow: Some(Duration::from_millis(2500)),
+                fullscreen: true,
+                config: Some("other.toml".into()),
+                tui: true,
+            }
+        );
+
+        let args = parse("-r --depth 2 --ignore *.tmp --ignore=.git").unwrap();
+        assert!(args.recursive);
+        assert_eq!(args.depth, Some(2));
+        assert_eq!(args.ignore, vec!["*.tmp".to_string(), ".git".into()]);
+
+        let args = parse("--seed=42").unwrap();
+        assert!(args.shuffle);
+        assert_eq!(args.seed, Some(42));
+        assert!(parse("--shuffle").unwrap().shuffle);
+
+        let args = parse("a.png -- -b.png").unwrap();
+        assert_eq!(args.paths, vec![PathBuf::from("a.png"), "-b.png".into()]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("--help"), Err(ArgsError::Help));
+        assert_eq!(parse("-x"), Err(ArgsError::UnknownFlag("-x".into())));
+        assert_eq!(
+            parse("--fullscreen=yes"),
+            Err(ArgsError::UnknownFlag("--fullscreen".into()))
+        );
+        assert_eq!(parse("--index"), Err(ArgsError::MissingValue("--index".into())));
+        assert_eq!(
+            parse("--index 0"),
+            Err(ArgsError::InvalidValue {
+                flag: "--index".into(),
+                value: "0".into()
+            })
+        );
+        assert!(parse("--sort date").is_err());
+        assert!(parse("--slideshow -1").is_err());
+        assert!(parse("--seed -1").is_err());
+        assert_eq!(
+            parse("--sort").unwrap_err().to_string(),
+            "--sort needs a value"
+        );
+    }
+}
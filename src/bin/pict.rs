@@ -6,6 +6,73 @@ use pict::*;
 fn main() {
     env_logger::init();
 
-    let app = App::new();
+    let args = match Args::parse(::std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(ArgsError::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("pict: {}\n\n{}", err, USAGE);
+            ::std::process::exit(2);
+        }
+    };
+
+    if let Some(ref path) = args.config {
+        Config::set_path(path);
+    }
+    if let Some(err) = Config::load_error() {
+        let path = Config::path();
+        eprintln!(
+            "pict: cannot read {}, using the defaults without saving over it: {}",
+            path.display(),
+            err
+        );
+    }
+
+    if args.tui {
+        return tui(&args);
+    }
+    gui(&args);
+}
+
+#[cfg(windows)]
+fn gui(args: &Args) {
+    let app = App::new(args);
     app.run();
 }
+
+#[cfg(target_os = "linux")]
+fn gui(args: &Args) {
+    match App::new(args) {
+        Some(mut app) => app.run(),
+        None => {
+            eprintln!("cannot open a window. is there an X server (or XWayland) running?");
+            eprintln!("`pict --tui` shows images in the terminal instead");
+            ::std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn gui(_args: &Args) {
+    eprintln!("pict doesn't have a window for this platform yet. try `pict --tui`");
+    ::std::process::exit(1);
+}
+
+#[cfg(unix)]
+fn tui(args: &Args) {
+    match Tui::new() {
+        Some(mut tui) => tui.run(args),
+        None => {
+            eprintln!("--tui needs to be run in a terminal");
+            ::std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn tui(_args: &Args) {
+    eprintln!("--tui isn't supported on this platform");
+    ::std::process::exit(1);
+}
@@ -0,0 +1,202 @@
+use std::cmp;
+use std::time::Duration;
+
+use image::{Rgba, RgbaImage};
+
+use imageview::Repeat;
+
+/// How a part is drawn onto the canvas
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Blend {
+    /// it replaces what's under it, see-through pixels and all
+    Source,
+    /// it's drawn over what's there
+    Over,
+}
+
+/// What happens to the area of a part, before the next one is drawn
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Dispose {
+    /// it stays
+    Keep,
+    /// it's cleared to transparent
+    Background,
+    /// it goes back to how it was before the part was drawn
+    Previous,
+}
+
+/// A frame as it's stored in an animation, a rectangle that gets drawn onto the canvas
+#[derive(Debug)]
+pub struct Part {
+    pub buffer: RgbaImage,
+    pub left: u32,
+    pub top: u32,
+    pub delay: Duration,
+    pub blend: Blend,
+    pub dispose: Dispose,
+}
+
+/// An animation as it's stored in the file
+#[derive(Debug)]
+pub struct Animated {
+    pub width: u32,
+    pub height: u32,
+    pub repeat: Repeat,
+    pub parts: Vec<Part>,
+}
+
+/// Draws the parts of an animation one after another, into whole frames
+#[derive(Debug)]
+pub struct Canvas {
+    buffer: RgbaImage,
+    last: Option<Last>,
+}
+
+// what the part that was drawn last leaves behind
+#[derive(Debug)]
+struct Last {
+    dispose: Dispose,
+    rect: (u32, u32, u32, u32), // left, top, width, height
+    saved: Option<RgbaImage>,   // the canvas before it, for `Dispose::Previous`
+}
+
+impl Canvas {
+    /// starts out transparent
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            buffer: RgbaImage::new(width, height),
+            last: None,
+        }
+    }
+
+    /// draws `part`, after getting rid of the last one the way it asked for. returns the
+    /// whole canvas, as it's shown. anything outside of the canvas is cut off
+    pub fn draw(&mut self, part: &Part) -> RgbaImage {
+        self.dispose();
+
+        let saved = match part.dispose {
+            Dispose::Previous => Some(self.buffer.clone()),
+            _ => None,
+        };
+        let (width, height) = self.buffer.dimensions();
+        for (x, y, &pixel) in part.buffer.enumerate_pixels() {
+            let (x, y) = (part.left.saturating_add(x), part.top.saturating_add(y));
+            if x >= width || y >= height {
+                continue;
+            }
+            let pixel = match part.blend {
+                Blend::Source => pixel,
+                Blend::Over => over(*self.buffer.get_pixel(x, y), pixel),
+            };
+            self.buffer.put_pixel(x, y, pixel);
+        }
+
+        self.last = Some(Last {
+            dispose: part.dispose,
+            rect: (part.left, part.top, part.buffer.width(), part.buffer.height()),
+            saved,
+        });
+        self.buffer.clone()
+    }
+
+    fn dispose(&mut self) {
+        let last = match self.last.take() {
+            Some(last) => last,
+            None => return,
+        };
+        match (last.dispose, last.saved) {
+            (Dispose::Keep, _) => {}
+            (Dispose::Previous, Some(saved)) => self.buffer = saved,
+            (_, _) => {
+                let (left, top, w, h) = last.rect;
+                let (width, height) = self.buffer.dimensions();
+                for y in top..cmp::min(top.saturating_add(h), height) {
+                    for x in left..cmp::min(left.saturating_add(w), width) {
+                        self.buffer.put_pixel(x, y, Rgba { data: [0; 4] });
+                    }
+                }
+            }
+        }
+    }
+}
+
+// `src` over `dst`, neither of them premultiplied
+fn over(dst: Rgba<u8>, src: Rgba<u8>) -> Rgba<u8> {
+    let sa = u32::from(src.data[3]);
+    match sa {
+        255 => return src,
+        0 => return dst,
+        _ => {}
+    }
+
+    let da = u32::from(dst.data[3]) * (255 - sa) / 255;
+    let alpha = sa + da;
+    let mut out = [0; 4];
+    for (i, out) in out.iter_mut().take(3).enumerate() {
+        let c = u32::from(src.data[i]) * sa + u32::from(dst.data[i]) * da;
+        *out = (c / alpha) as u8;
+    }
+    out[3] = alpha as u8;
+    Rgba { data: out }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn part(color: [u8; 4], rect: (u32, u32, u32, u32), blend: Blend, dispose: Dispose) -> Part {
+        let (left, top, w, h) = rect;
+        Part {
+            buffer: RgbaImage::from_pixel(w, h, Rgba { data: color }),
+            left,
+            top,
+            delay: Duration::default(),
+            blend,
+            dispose,
+        }
+    }
+
+    fn at(image: &RgbaImage, x: u32, y: u32) -> [u8; 4] {
+        image.get_pixel(x, y).data
+    }
+
+    #[test]
+    fn blending() {
+        let red = [255, 0, 0, 255];
+        let clear = [0, 0, 255, 0];
+        let half = [0, 0, 255, 128];
+
+        let mut canvas = Canvas::new(4, 4);
+        canvas.draw(&part(red, (0, 0, 4, 4), Blend::Source, Dispose::Keep));
+        let frame = canvas.draw(&part(clear, (0, 0, 2, 2), Blend::Over, Dispose::Keep));
+        assert_eq!(at(&frame, 0, 0), red);
+        let frame = canvas.draw(&part(half, (1, 1, 2, 2), Blend::Over, Dispose::Keep));
+        assert_eq!(at(&frame, 1, 1), [127, 0, 128, 255]);
+        let frame = canvas.draw(&part(clear, (0, 0, 2, 2), Blend::Source, Dispose::Keep));
+        assert_eq!(at(&frame, 0, 0), clear);
+        assert_eq!(at(&frame, 3, 3), red);
+
+        // over nothing stays see-through
+        assert_eq!(over(Rgba { data: [0; 4] }, Rgba { data: half }).data, half);
+    }
+
+    #[test]
+    fn disposing() {
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let blue = [0, 0, 255, 255];
+
+        let mut canvas = Canvas::new(4, 4);
+        canvas.draw(&part(red, (0, 0, 4, 4), Blend::Source, Dispose::Keep));
+        canvas.draw(&part(green, (0, 0, 2, 2), Blend::Over, Dispose::Background));
+        let frame = canvas.draw(&part(blue, (2, 2, 4, 4), Blend::Over, Dispose::Previous));
+        assert_eq!(at(&frame, 0, 0), [0; 4]);
+        assert_eq!(at(&frame, 1, 2), red);
+        assert_eq!(at(&frame, 3, 3), blue);
+
+        let frame = canvas.draw(&part(green, (3, 0, 1, 1), Blend::Over, Dispose::Keep));
+        assert_eq!(at(&frame, 3, 3), red);
+        assert_eq!(at(&frame, 3, 0), green);
+        assert_eq!(at(&frame, 0, 0), [0; 4]);
+    }
+}
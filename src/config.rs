@@ -1,11 +1,22 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::io::Write;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 
 use toml;
 
+use exif::ExifOptions;
+use scan::ScanOptions;
+use shuffle::ShuffleOptions;
+use slideshow::SlideshowOptions;
+use sort::SortOrder;
+
 const CONFIG_FILE: &str = "pict.toml";
 
 lazy_static! {
+    static ref CONFIG_PATH: RwLock<PathBuf> = RwLock::new(CONFIG_FILE.into());
+    static ref LOAD_ERROR: RwLock<Option<String>> = RwLock::new(None);
     static ref CONFIG: Config = Config::load();
 }
 
@@ -14,6 +25,22 @@ pub struct Config {
     pub position: Position,
     pub size: Size,
     pub filelist: FileList,
+    #[serde(default)]
+    pub slideshow: SlideshowOptions,
+    #[serde(default)]
+    pub sort: SortOrder,
+    #[serde(default)]
+    pub scan: ScanOptions,
+    #[serde(default)]
+    pub shuffle: ShuffleOptions,
+    /// chords to action names, on top of the default bindings
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+    /// formats turned on or off by name, like `tga = false`. they're all on otherwise
+    #[serde(default)]
+    pub formats: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub exif: ExifOptions,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,37 +64,115 @@ impl Config {
     pub fn get<'a>() -> &'a Self {
         ::lazy_static::initialize(&CONFIG);
         debug!("got cached config");
-        &*CONFIG
+        &CONFIG
     }
 
-    pub fn load() -> Self {
-        fn try_load_config() -> Option<Config> {
-            let s = fs::read_to_string(CONFIG_FILE).ok()?;
-            toml::from_str(&s).ok()
-        }
+    /// loads and saves the config at `path` instead of `pict.toml`. this has to be done
+    /// before the config is first used
+    pub fn set_path<P: Into<PathBuf>>(path: P) {
+        let path = path.into();
+        debug!("using config file: {:?}", path);
+        *CONFIG_PATH.write().unwrap() = path;
+    }
+
+    pub fn path() -> PathBuf {
+        CONFIG_PATH.read().unwrap().clone()
+    }
 
-        let conf = try_load_config()
-            .or_else(|| {
+    pub fn load() -> Self {
+        let path = Self::path();
+        let conf = match read(&path) {
+            Ok(Some(conf)) => conf,
+            Ok(None) => {
                 info!("creating default config");
-                Some(Self {
-                    position: Position { x: 0, y: 0 },
-                    size: Size { w: 400, h: 200 },
-                    filelist: FileList::default(),
-                })
-            })
-            .expect("to get config");
+                Self::defaults()
+            }
+            Err(err) => {
+                error!("cannot read the config {:?}: {}", path, err);
+                *LOAD_ERROR.write().unwrap() = Some(err);
+                Self::defaults()
+            }
+        };
 
         debug!("loaded config: {:?}", conf);
         conf
     }
 
+    /// why the config file couldn't be used, if it couldn't. the defaults are used
+    /// instead, and the file isn't saved over
+    pub fn load_error() -> Option<String> {
+        ::lazy_static::initialize(&CONFIG);
+        LOAD_ERROR.read().unwrap().clone()
+    }
+
+    fn defaults() -> Self {
+        Self {
+            position: Position { x: 0, y: 0 },
+            size: Size { w: 400, h: 200 },
+            filelist: FileList::default(),
+            slideshow: SlideshowOptions::default(),
+            sort: SortOrder::default(),
+            scan: ScanOptions::default(),
+            shuffle: ShuffleOptions::default(),
+            keys: BTreeMap::new(),
+            formats: BTreeMap::new(),
+            exif: ExifOptions::default(),
+        }
+    }
+
     // App takes ownership, so we can't use drop.
     pub fn save(self) {
+        if LOAD_ERROR.read().unwrap().is_some() {
+            warn!("not saving over {:?}, it couldn't be read", Self::path());
+            return;
+        }
         debug!("saving config: {:?}", self);
 
         let s = toml::to_string_pretty(&self).expect("to serialize config");
-        fs::File::create(CONFIG_FILE)
+        fs::File::create(Self::path())
             .and_then(|mut f| writeln!(f, "{}", &s))
             .expect("to write config");
     }
 }
+
+// the config at `path`. `None` if there isn't one yet
+fn read(path: &Path) -> Result<Option<Config>, String> {
+    let s = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.to_string()),
+    };
+    toml::from_str(&s).map(Some).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use testutil::TempDir;
+
+    #[test]
+    fn reading() {
+        let dir = TempDir::new("config");
+        let path = dir.join("pict.toml");
+        assert!(read(&path).unwrap().is_none());
+
+        let conf = Config::defaults();
+        fs::write(&path, toml::to_string_pretty(&conf).unwrap()).unwrap();
+        let read_back = read(&path).unwrap().unwrap();
+        assert_eq!(read_back.size.w, conf.size.w);
+        assert!(read_back.keys.is_empty());
+
+        // the wrong type anywhere is an error, rather than the defaults
+        let base = "[position]\nx = 0\ny = 0\n[size]\nw = 1\nh = 2\n\
+                    [filelist]\nsnap = false\n";
+        for &(extra, ok) in &[
+            ("", true),
+            ("[keys]\nx = \"next\"\n", true),
+            ("[keys]\nx = 1\n", false),
+            ("[formats]\ntga = \"no\"\n", false),
+        ] {
+            fs::write(&path, format!("{}{}", base, extra)).unwrap();
+            assert_eq!(read(&path).is_ok(), ok, "{}", extra);
+        }
+    }
+}
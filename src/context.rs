@@ -1,10 +1,28 @@
-use common::*;
+use std::cmp;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
+use config::Config;
+use entry::Entry;
+use filter::Filter;
+use shuffle::Shuffle;
+use sort::SortOrder;
+
+/// The state shared between the viewer and the frontend.
+///
+/// The list holds everything that was opened, but only the entries that pass the filter
+/// are shown. The index, the length and the entries handed out are all of the shown ones.
+/// When shuffled, next and previous go through the shuffled order instead
 #[derive(Debug)]
 pub struct Context {
-    list: Vec<String>,
+    list: Vec<Entry>,
+    shown: Vec<usize>, // positions in `list`
+    filter: Filter,
     index: usize,
     snap: bool,
+    sort: SortOrder,
+    seed: Option<u64>,
+    shuffle: Option<Shuffle>,
     frame: usize,
 }
 
@@ -20,14 +38,19 @@ impl Context {
 
         Self {
             list: vec![],
+            shown: vec![],
+            filter: Filter::default(),
             index: 0,
             frame: 0,
             snap: conf.filelist.snap,
+            sort: conf.sort,
+            seed: conf.shuffle.seed,
+            shuffle: None,
         }
     }
 
     pub fn get_len(&self) -> usize {
-        let len = self.list.len();
+        let len = self.shown.len();
         trace!("getting len: {}", len);
         len
     }
@@ -42,6 +65,7 @@ impl Context {
         self.index = index
     }
 
+    #[cfg(test)]
     pub fn get_frame_index(&self) -> usize {
         trace!("getting frame: {}", self.index);
         self.frame
@@ -61,23 +85,389 @@ impl Context {
         self.snap = snap
     }
 
+    pub fn get_sort(&self) -> SortOrder {
+        self.sort
+    }
+
+    pub fn set_sort(&mut self, sort: SortOrder) {
+        trace!("setting sort: {}", sort);
+        self.sort = sort
+    }
+
+    /// sorts the list by the current order. the index stays on the same entry
+    pub fn sort_list(&mut self) {
+        let current = self.current_path();
+        self.sort.sort(&mut self.list);
+        self.refilter(current);
+    }
+
+    /// used the next time shuffling is turned on
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        trace!("setting seed: {:?}", seed);
+        self.seed = seed
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle.is_some()
+    }
+
+    /// turns shuffling on, starting a new order from the current entry, or off
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        trace!("setting shuffle: {}", shuffle);
+        self.shuffle = if shuffle {
+            let mut shuffle = Shuffle::new(self.seed);
+            shuffle.set_shown(self.get_list_iter().map(Entry::path));
+            Some(shuffle)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get_filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// only shows what passes `filter`. returns whether the current entry is still shown,
+    /// otherwise the index goes back to the first one
+    pub fn set_filter(&mut self, filter: Filter) -> bool {
+        trace!("setting filter: {:?}", filter);
+        let current = self.current_path();
+        self.filter = filter;
+        self.refilter(current)
+    }
+
+    /// clears the list and the filter
     pub fn clear_list(&mut self) {
         trace!("clearing list");
         self.list.clear();
         self.list.shrink_to_fit();
+        self.shown.clear();
+        self.filter = Filter::default();
+        if let Some(ref mut shuffle) = self.shuffle {
+            shuffle.clear();
+        }
     }
 
-    pub fn extend_list(&mut self, el: &[(String, usize)]) {
+    /// swaps in a newer listing, keeping the filter, and what's been read of the files
+    /// that didn't change. returns `None` if nothing changed, otherwise whether the current
+    /// entry is still there. if it isn't, the index stays put so the next one takes its place
+    pub fn replace_list(&mut self, list: Vec<Entry>) -> Option<bool> {
+        let same = self.list.len() == list.len()
+            && self.list.iter().zip(&list).all(|(old, new)| old.is_unchanged(new));
+        if same {
+            return None;
+        }
+
+        let (current, index) = (self.current_path(), self.index);
+        let mut old = self
+            .list
+            .drain(..)
+            .map(|entry| (entry.path().to_path_buf(), entry))
+            .collect::<HashMap<_, _>>();
+        self.list = list
+            .into_iter()
+            .map(|new| match old.remove(new.path()) {
+                Some(ref old) if old.is_unchanged(&new) => old.clone(),
+                _ => new,
+            })
+            .collect();
+
+        let kept = self.refilter(current);
+        if !kept {
+            self.set_index(cmp::min(index, self.shown.len().saturating_sub(1)));
+        }
+        Some(kept)
+    }
+
+    pub fn extend_list(&mut self, el: &[Entry]) {
         trace!("extending list");
-        let v = el.iter().map(|(s, _)| s.to_owned()).collect::<Vec<_>>();
-        for el in &v {
-            trace!("{}", el);
+        for el in el {
+            trace!("{:?}", el.path());
+        }
+
+        let current = self.current_path();
+        self.list.extend_from_slice(el);
+        self.refilter(current);
+    }
+
+    /// the shown entries
+    pub fn get_list_iter(&self) -> impl Iterator<Item = &Entry> {
+        self.shown.iter().map(move |&n| &self.list[n])
+    }
+
+    pub fn get_entry(&self, index: usize) -> Option<&Entry> {
+        self.shown.get(index).map(|&n| &self.list[n])
+    }
+
+    /// whether the current entry is the last one, either in the list or in the shuffled order
+    pub fn is_at_end(&self) -> bool {
+        match self.shuffle {
+            Some(ref shuffle) => shuffle.is_at_end(),
+            None => self.index + 1 >= self.get_len(),
+        }
+    }
+
+    /// the entry at the current index
+    pub fn current(&self) -> Option<&Entry> {
+        self.get_entry(self.index)
+    }
+
+    fn current_path(&self) -> Option<PathBuf> {
+        self.current().map(|entry| entry.path().to_path_buf())
+    }
+
+    // moves to `path`, if it's shown
+    fn select_path(&mut self, path: &Path) -> Option<usize> {
+        let index = self.get_list_iter().position(|entry| entry.path() == path)?;
+        self.set_index(index);
+        Some(index)
+    }
+
+    // works out what's shown, keeping the index on `current` if it's still there
+    fn refilter(&mut self, current: Option<PathBuf>) -> bool {
+        let (list, filter) = (&self.list, &self.filter);
+        self.shown = (0..list.len())
+            .filter(|&n| filter.is_empty() || filter.matches(&list[n]))
+            .collect();
+
+        let index = current.and_then(|path| {
+            self.shown
+                .iter()
+                .position(|&n| self.list[n].path() == path)
+        });
+        debug!("showing {} of {} files", self.shown.len(), self.list.len());
+        if let Some(ref mut shuffle) = self.shuffle {
+            let list = &self.list;
+            shuffle.set_shown(self.shown.iter().map(|&n| list[n].path()));
+        }
+        self.set_index(index.unwrap_or(0));
+        index.is_some()
+    }
+
+    /// moves to the next index, wrapping around. returns `None` if the list is empty
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<usize> {
+        let len = self.get_len();
+        if len == 0 {
+            debug!("can't move to next index. list empty");
+            return None;
+        }
+
+        if let Some(ref mut shuffle) = self.shuffle {
+            let list = &self.list;
+            let current = self.shown.get(self.index).map(|&n| list[n].path());
+            let next = shuffle.next(current);
+            debug!("moving to next shuffled file: {:?}", next);
+            return next.and_then(|path| self.select_path(&path));
+        }
+
+        let next = if self.index + 1 >= len {
+            0
+        } else {
+            self.index + 1
+        };
+        debug!("moving to next index: {}", next);
+        self.set_index(next);
+        Some(next)
+    }
+
+    /// moves to the previous index, wrapping around. returns `None` if the list is empty
+    pub fn previous(&mut self) -> Option<usize> {
+        let len = self.get_len();
+        if len == 0 {
+            debug!("can't move to previous index. list empty");
+            return None;
+        }
+
+        if let Some(ref mut shuffle) = self.shuffle {
+            let prev = shuffle.previous();
+            debug!("moving to previous shuffled file: {:?}", prev);
+            // at the start of the order, it stays put
+            return match prev {
+                Some(path) => self.select_path(&path),
+                None => Some(cmp::min(self.index, len - 1)),
+            };
         }
 
-        self.list.extend_from_slice(&v)
+        let prev = if self.index == 0 || self.index >= len {
+            len - 1
+        } else {
+            self.index - 1
+        };
+        debug!("moving to previous index: {}", prev);
+        self.set_index(prev);
+        Some(prev)
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sort::SortKey;
+    use std::path::Path;
+
+    fn context(n: usize) -> Context {
+        let mut ctx = Context::new();
+        let list = (0..n)
+            .map(|i| Entry::new(format!("{}.png", i)))
+            .collect::<Vec<_>>();
+        ctx.extend_list(&list);
+        ctx
+    }
+
+    #[test]
+    fn navigation_wraps() {
+        let mut ctx = context(3);
+        assert_eq!(ctx.next(), Some(1));
+        assert_eq!(ctx.next(), Some(2));
+        assert_eq!(ctx.next(), Some(0));
+        assert_eq!(ctx.previous(), Some(2));
+        assert_eq!(ctx.previous(), Some(1));
+        assert_eq!(ctx.get_index(), 1);
+    }
+
+    #[test]
+    fn navigation_empty() {
+        let mut ctx = context(0);
+        assert_eq!(ctx.next(), None);
+        assert_eq!(ctx.previous(), None);
+        ctx.set_shuffle(true);
+        assert_eq!(ctx.next(), None);
+        assert_eq!(ctx.previous(), None);
+        assert_eq!(ctx.get_index(), 0);
+    }
+
+    #[test]
+    fn shuffled() {
+        let mut ctx = context(5);
+        ctx.set_seed(Some(3));
+        ctx.set_shuffle(true);
+        ctx.set_index(2);
+
+        let mut seen = (0..4).map(|_| ctx.next().unwrap()).collect::<Vec<_>>();
+        assert_eq!(ctx.get_index(), seen[3]);
+        assert_eq!(ctx.previous(), Some(seen[2]));
+        assert_eq!(ctx.next(), Some(seen[3]));
+        seen.sort();
+        assert_eq!(seen, vec![0, 1, 3, 4]);
+
+        // it doesn't go back further than where it started
+        for _ in 0..4 {
+            ctx.previous();
+        }
+        assert_eq!(ctx.get_index(), 2);
+        assert_eq!(ctx.previous(), Some(2));
+
+        ctx.set_shuffle(false);
+        assert_eq!(ctx.next(), Some(3));
+    }
+
+    #[test]
+    fn list() {
+        let mut ctx = context(2);
+        assert_eq!(ctx.get_len(), 2);
+        assert_eq!(
+            ctx.get_list_iter().map(Entry::name).collect::<Vec<_>>(),
+            vec!["0.png", "1.png"]
+        );
+        ctx.set_index(1);
+        assert_eq!(ctx.current().unwrap().path(), Path::new("1.png"));
+        assert!(ctx.get_entry(2).is_none());
+
+        ctx.clear_list();
+        assert_eq!(ctx.get_len(), 0);
+    }
+
+    #[test]
+    fn sort_keeps_selection() {
+        let mut ctx = Context::new();
+        let list = ["b10.png", "b9.png", "a.png"]
+            .iter()
+            .map(|name| Entry::new(*name))
+            .collect::<Vec<_>>();
+        ctx.extend_list(&list);
+        ctx.set_index(1);
+
+        ctx.set_sort(SortOrder::new(SortKey::Name, false));
+        ctx.sort_list();
+        assert_eq!(
+            ctx.get_list_iter().map(Entry::name).collect::<Vec<_>>(),
+            vec!["a.png", "b9.png", "b10.png"]
+        );
+        assert_eq!(ctx.get_index(), 1);
+
+        ctx.set_sort(SortOrder::new(SortKey::Name, true));
+        ctx.sort_list();
+        assert_eq!(ctx.get_index(), 1);
+        assert_eq!(ctx.current().unwrap().name(), "b9.png");
+
+        ctx.set_index(0);
+        ctx.sort_list();
+        assert_eq!(ctx.current().unwrap().name(), "b10.png");
+    }
+
+    #[test]
+    fn filtered() {
+        let mut ctx = context(12);
+        ctx.set_index(10);
+        assert!(ctx.set_filter(Filter::parse("1").unwrap()));
+        let names = |ctx: &Context| ctx.get_list_iter().map(Entry::name).collect::<Vec<_>>();
+        assert_eq!(names(&ctx), vec!["1.png", "10.png", "11.png"]);
+        assert_eq!(ctx.get_len(), 3);
+        assert_eq!(ctx.get_index(), 1);
+
+        // navigation only goes through what's shown
+        assert_eq!(ctx.next(), Some(2));
+        assert_eq!(ctx.next(), Some(0));
+        assert_eq!(ctx.current().unwrap().name(), "1.png");
+        ctx.set_shuffle(true);
+        for _ in 0..20 {
+            ctx.next();
+            assert!(ctx.current().unwrap().name().starts_with('1'));
+        }
+        ctx.set_shuffle(false);
+
+        // clearing it goes back to the same file
+        ctx.set_index(2);
+        assert!(ctx.set_filter(Filter::default()));
+        assert_eq!(ctx.get_len(), 12);
+        assert_eq!(ctx.current().unwrap().name(), "11.png");
+
+        // the current file was filtered out
+        assert!(!ctx.set_filter(Filter::parse("*5*").unwrap()));
+        assert_eq!(names(&ctx), vec!["5.png"]);
+        assert_eq!(ctx.get_index(), 0);
+
+        ctx.clear_list();
+        assert!(ctx.get_filter().is_empty());
+    }
+
+    #[test]
+    fn replaced() {
+        let mut ctx = context(4);
+        ctx.set_index(2);
+        ctx.current().unwrap().set_dimensions(1, 2);
+
+        let list = |names: &[usize]| {
+            names
+                .iter()
+                .map(|i| Entry::new(format!("{}.png", i)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ctx.replace_list(list(&[0, 1, 2, 3])), None);
+
+        // something new in front, the same file stays selected and isn't read again
+        assert_eq!(ctx.replace_list(list(&[4, 0, 1, 2, 3])), Some(true));
+        assert_eq!(ctx.get_index(), 3);
+        assert_eq!(ctx.current().unwrap().dimensions(), Some((1, 2)));
 
-    pub fn get_list_iter(&self) -> impl Iterator<Item = &String> {
-        self.list.iter()
+        // the current one is gone, so the next one takes its place
+        assert_eq!(ctx.replace_list(list(&[4, 0, 1, 3])), Some(false));
+        assert_eq!(ctx.current().unwrap().name(), "3.png");
+        assert_eq!(ctx.replace_list(list(&[4, 0])), Some(false));
+        assert_eq!(ctx.current().unwrap().name(), "0.png");
+        assert_eq!(ctx.replace_list(vec![]), Some(false));
+        assert_eq!(ctx.get_index(), 0);
     }
 }
@@ -0,0 +1,296 @@
+use std::cell::Cell;
+use std::fs;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use image::{self, ImageDecoder, ImageFormat};
+
+use exif::{self, Exif, Orientation};
+use format;
+use webp;
+
+/// A file in the list.
+///
+/// The metadata comes from the directory listing. The format, dimensions and EXIF date
+/// need the file to be read, so that only happens when they're first asked for. The
+/// format is sniffed from the start of the file, so the name doesn't have to be right
+#[derive(Debug, Clone)]
+pub struct Entry {
+    path: PathBuf,
+    relative: Option<PathBuf>, // to the directory that was scanned, when it's nested
+    size: u64,
+    modified: Option<SystemTime>,
+    orient: bool, // whether the dimensions are the way the EXIF data turns it
+
+    probed: Cell<bool>,
+    format: Cell<Option<Option<ImageFormat>>>, // outer `None` is not read yet
+    dimensions: Cell<Option<(u32, u32)>>,
+    taken: Cell<Option<Option<u64>>>, // outer `None` is not read yet
+}
+
+impl Entry {
+    /// looks up the metadata for `path`. a file that can't be read is empty
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        match fs::metadata(&path) {
+            Ok(md) => Self::with_metadata(path, &md),
+            Err(err) => {
+                debug!("cannot get the metadata for {:?}: {}", path, err);
+                Self::from_parts(path, 0, None)
+            }
+        }
+    }
+
+    pub fn with_metadata<P: Into<PathBuf>>(path: P, md: &fs::Metadata) -> Self {
+        Self::from_parts(path.into(), md.len(), md.modified().ok())
+    }
+
+    fn from_parts(path: PathBuf, size: u64, modified: Option<SystemTime>) -> Self {
+        Self {
+            path,
+            relative: None,
+            size,
+            modified,
+            orient: true,
+            probed: Cell::new(false),
+            format: Cell::new(None),
+            dimensions: Cell::new(None),
+            taken: Cell::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// the file name, for showing in the list
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// the path below the directory that was scanned, or just the file name
+    pub fn display_name(&self) -> String {
+        match self.relative {
+            Some(ref relative) => relative.to_string_lossy().into_owned(),
+            None => self.name(),
+        }
+    }
+
+    pub fn set_relative<P: Into<PathBuf>>(&mut self, relative: P) {
+        self.relative = Some(relative.into())
+    }
+
+    /// whether the EXIF orientation turns the dimensions, as it does the image. only
+    /// matters before they're read
+    pub fn set_orient(&mut self, orient: bool) {
+        self.orient = orient
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// whether `other` is the same file, and it hasn't changed since this was listed
+    pub fn is_unchanged(&self, other: &Entry) -> bool {
+        self.path == other.path && self.size == other.size && self.modified == other.modified
+    }
+
+    /// what the file says it is, going by its first few bytes. `None` if it isn't an
+    /// image we can show
+    pub fn format(&self) -> Option<ImageFormat> {
+        if let Some(format) = self.format.get() {
+            return format;
+        }
+
+        let format = format::detect(&self.path);
+        trace!("sniffed {:?}: {:?}", self.path, format);
+        self.format.set(Some(format));
+        format
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.format().is_some()
+    }
+
+    /// (width, height), from the header of the image
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        if self.dimensions.get().is_none() {
+            self.probe();
+        }
+        self.dimensions.get()
+    }
+
+    /// when the picture was taken as `YYYYMMDDHHMMSS`, if the EXIF data says so
+    pub fn taken(&self) -> Option<u64> {
+        if let Some(taken) = self.taken.get() {
+            return taken;
+        }
+
+        let taken = self.exif().and_then(|exif| exif.taken_key());
+        trace!("{:?} was taken: {:?}", self.path, taken);
+        self.taken.set(Some(taken));
+        taken
+    }
+
+    /// for when the image was loaded anyway
+    pub fn set_dimensions(&self, width: u32, height: u32) {
+        self.dimensions.set(Some((width, height)))
+    }
+
+    fn exif(&self) -> Option<Exif> {
+        read_exif(&self.path, self.format()?)
+    }
+
+    // reads the dimensions, once
+    fn probe(&self) {
+        if self.probed.replace(true) {
+            return;
+        }
+
+        let dimensions = self
+            .format()
+            .and_then(|format| probe(&self.path, format, self.orient));
+        trace!("probed {:?}: {:?}", self.path, dimensions);
+        if self.dimensions.get().is_none() {
+            self.dimensions.set(dimensions);
+        }
+    }
+}
+
+// the way it's shown, turned if `orient` and the EXIF data says so
+fn probe(path: &Path, format: ImageFormat, orient: bool) -> Option<(u32, u32)> {
+    let (width, height) = read_size(BufReader::new(fs::File::open(path).ok()?), format)?;
+    let turned = orient
+        && read_exif(path, format)
+            .and_then(|exif| exif.orientation)
+            .is_some_and(Orientation::swaps_dimensions);
+    if turned {
+        Some((height, width))
+    } else {
+        Some((width, height))
+    }
+}
+
+// the exif data, for the formats that have it
+fn read_exif(path: &Path, format: ImageFormat) -> Option<Exif> {
+    match format {
+        ImageFormat::JPEG => {
+            let file = fs::File::open(path).ok()?;
+            exif::read_jpeg(BufReader::new(file))
+        }
+        _ => None,
+    }
+}
+
+// only the header gets read, none of them are decoded
+fn read_size<R: BufRead + Seek>(mut rd: R, format: ImageFormat) -> Option<(u32, u32)> {
+    let dimensions = match format {
+        ImageFormat::WEBP => {
+            // the webp decoder can't read the animated ones, but their header has the size
+            let mut header = [0; 30];
+            let size = rd.read_exact(&mut header).ok().and_then(|_| webp::canvas_size(&header));
+            if size.is_some() {
+                return size;
+            }
+            rd.seek(SeekFrom::Start(0)).ok()?;
+            image::webp::WebpDecoder::new(rd).dimensions()
+        }
+        ImageFormat::PNG => image::png::PNGDecoder::new(rd).dimensions(),
+        ImageFormat::JPEG => image::jpeg::JPEGDecoder::new(rd).dimensions(),
+        ImageFormat::GIF => image::gif::Decoder::new(rd).dimensions(),
+        ImageFormat::BMP => image::bmp::BMPDecoder::new(rd).dimensions(),
+        ImageFormat::TGA => image::tga::TGADecoder::new(rd).dimensions(),
+        ImageFormat::ICO => image::ico::ICODecoder::new(rd).and_then(|mut d| d.dimensions()),
+        ImageFormat::TIFF => image::tiff::TIFFDecoder::new(rd).and_then(|mut d| d.dimensions()),
+        ImageFormat::PNM => image::pnm::PNMDecoder::new(rd).and_then(|mut d| d.dimensions()),
+        ImageFormat::HDR => image::hdr::HDRAdapter::new(rd).and_then(|mut d| d.dimensions()),
+    };
+    dimensions.ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use testutil::{turned_jpeg, TempDir};
+
+    #[test]
+    fn metadata() {
+        let entry = Entry::new("testdata/test.png");
+        assert_eq!(entry.name(), "test.png");
+        assert_eq!(entry.display_name(), "test.png");
+        assert_eq!(entry.size(), fs::metadata("testdata/test.png").unwrap().len());
+        assert!(entry.modified().is_some());
+
+        let missing = Entry::new("testdata/missing.png");
+        assert_eq!(missing.size(), 0);
+        assert_eq!(missing.format(), None);
+        assert_eq!(missing.dimensions(), None);
+    }
+
+    #[test]
+    fn lazy_probe() {
+        let expected = [
+            ("test.png", ImageFormat::PNG, (8, 6)),
+            ("test.jpg", ImageFormat::JPEG, (8, 6)),
+            ("test.gif", ImageFormat::GIF, (8, 6)),
+            ("animated/test.apng", ImageFormat::PNG, (8, 6)),
+            ("animated/test.webp", ImageFormat::WEBP, (16, 16)),
+        ];
+        for &(file, format, dimensions) in &expected {
+            let entry = Entry::new(format!("testdata/{}", file));
+            assert!(!entry.probed.get());
+            assert_eq!(entry.format(), Some(format));
+            // the format doesn't need the header
+            assert!(!entry.probed.get());
+            assert_eq!(entry.dimensions(), Some(dimensions));
+            assert_eq!(entry.taken(), None);
+        }
+
+        // what's known already isn't read again
+        let entry = Entry::new("testdata/test.png");
+        entry.set_dimensions(1, 2);
+        assert_eq!(entry.dimensions(), Some((1, 2)));
+        assert!(!entry.probed.get());
+    }
+
+    #[test]
+    fn headers_only() {
+        // just the headers, there's nothing after them to decode
+        let mut bmp = b"BM\0\0\0\0\0\0\0\0\x36\0\0\0\x28\0\0\0".to_vec();
+        bmp.extend_from_slice(&[10, 0, 0, 0, 4, 0, 0, 0, 1, 0, 24, 0]);
+        bmp.extend_from_slice(&[0; 24]);
+        let tga = [0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 3, 0, 24, 0];
+
+        let cases: [(&[u8], ImageFormat, (u32, u32)); 3] = [
+            (&bmp, ImageFormat::BMP, (10, 4)),
+            (&tga, ImageFormat::TGA, (7, 3)),
+            (b"P6\n5 2\n255\n", ImageFormat::PNM, (5, 2)),
+        ];
+        for &(data, format, dimensions) in &cases {
+            assert_eq!(read_size(Cursor::new(data), format), Some(dimensions));
+            assert!(image::load_from_memory_with_format(data, format).is_err());
+        }
+    }
+
+    #[test]
+    fn turned_dimensions() {
+        let dir = TempDir::new("entry");
+        let path = dir.join("turned.jpg");
+        fs::write(&path, turned_jpeg()).unwrap();
+        let entry = Entry::new(&path);
+        // it's the same size before it's shown, as it's going to be after
+        assert_eq!(entry.dimensions(), Some((6, 8)));
+        let mut entry = Entry::new(&path);
+        entry.set_orient(false);
+        assert_eq!(entry.dimensions(), Some((8, 6)));
+    }
+}
@@ -1,89 +1,200 @@
-use common::*;
-
-#[derive(Debug, PartialEq)]
-pub struct Event {
-    pub event: EventType,
-    pub hwnd: ::window::HWND,
-}
-
 #[derive(Debug, PartialEq)]
 pub enum EventType {
     CloseRequest,                                      // done
     Quit,                                              // done
     MouseMove { x: i32, y: i32 },                      // done
     MouseDown { button: MouseButton, x: i32, y: i32 }, // done
+    MouseUp { button: MouseButton, x: i32, y: i32 },   // done
     MouseWheel { delta: i16, x: i32, y: i32 },         // done
     HScroll { wp: usize, lp: isize },                  // done
-    KeyDown { key: Key },                              // done
+    KeyDown {
+        key: Key,
+        modifiers: Modifiers,
+        repeat: bool, // the key was held down
+    }, // done
+    KeyUp { key: Key, modifiers: Modifiers },          // done
+    Char { c: char },                                  // done
     Moved { x: i32, y: i32 },                          // done
     Moving { x: i32, y: i32 },                         // done
     Resizing { width: i32, height: i32 },              // ?
     Resize { width: i32, height: i32 },                // ?
     DropFile { file: String },                         // done
+    DropFiles { files: Vec<String> },                  // done
     Notify { lp: isize },                              // done | actually an LPARAM
+    Timer { id: usize },                               // done
+    Paint,                                             // done
 
     CtrlColorStatic { wp: usize, lp: isize }, // ?
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MouseButton {
     Left,
     Middle,
     Right,
-    // X1, X2,
+    /// the side buttons, usually back and forward
+    X1,
+    X2,
 }
 
-impl From<usize> for MouseButton {
-    fn from(button: usize) -> MouseButton {
-        match button {
-            winuser::MK_LBUTTON => MouseButton::Left,
-            winuser::MK_MBUTTON => MouseButton::Middle,
-            winuser::MK_RBUTTON => MouseButton::Right,
-            _ => {
-                error!("uh oh: {}", button);
-                unreachable!()
-            }
-        }
-    }
+/// The modifier keys that are held down
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Key {
-    Space,
-    Up,
-    Down,
-    Left,
-    Right,
-    Key1,
-    Key2,
-    Key3,
-    Key4,
     A,
+    B,
+    C,
     D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
     K,
     L,
+    M,
+    N,
+    O,
+    P,
+    Q,
     R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Space,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+    Minus,
+    /// the `=` key, which is `+` with shift
+    Plus,
+    Comma,
+    Period,
+    Slash,
+    /// whatever the platform called it
     Other(i32),
 }
 
-impl From<i32> for Key {
-    fn from(key: i32) -> Key {
-        match key {
-            winuser::VK_SPACE => Key::Space,
-            winuser::VK_UP => Key::Up,
-            winuser::VK_DOWN => Key::Down,
-            winuser::VK_LEFT => Key::Left,
-            winuser::VK_RIGHT => Key::Right,
-            0x31 => Key::Key1,
-            0x32 => Key::Key2,
-            0x33 => Key::Key3,
-            0x34 => Key::Key4,
-            0x41 => Key::A,
-            0x44 => Key::D,
-            0x4B => Key::K,
-            0x4C => Key::L,
-            0x52 => Key::R,
-            _ => Key::Other(key),
-        }
+impl Key {
+    pub const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+
+    pub const DIGITS: [Key; 10] = [
+        Key::Key0,
+        Key::Key1,
+        Key::Key2,
+        Key::Key3,
+        Key::Key4,
+        Key::Key5,
+        Key::Key6,
+        Key::Key7,
+        Key::Key8,
+        Key::Key9,
+    ];
+
+    pub const FUNCTION: [Key; 12] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+    ];
+
+    /// the key that types `c`, ignoring case and shift
+    pub fn from_char(c: char) -> Option<Key> {
+        let key = match c.to_ascii_lowercase() {
+            c @ 'a'..='z' => Self::LETTERS[c as usize - 'a' as usize],
+            c @ '0'..='9' => Self::DIGITS[c as usize - '0' as usize],
+            ' ' => Key::Space,
+            '\r' | '\n' => Key::Enter,
+            '\x1b' => Key::Escape,
+            '\t' => Key::Tab,
+            '\x08' | '\x7f' => Key::Backspace,
+            '-' => Key::Minus,
+            '=' | '+' => Key::Plus,
+            ',' => Key::Comma,
+            '.' => Key::Period,
+            '/' => Key::Slash,
+            _ => return None,
+        };
+        Some(key)
     }
 }
@@ -0,0 +1,275 @@
+use std::io::Read;
+
+// how much of a jpeg we look through for the exif segment
+const MAX_HEADER: u64 = 256 * 1024;
+
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// The parts of the EXIF data that we use
+#[derive(Debug, Default, PartialEq)]
+pub struct Exif {
+    /// when the picture was taken, as `YYYY:MM:DD HH:MM:SS`
+    pub taken: Option<String>,
+    /// how the camera was held
+    pub orientation: Option<Orientation>,
+}
+
+/// What has to be done to an image to show it the right way up
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Orientation {
+    Normal,
+    FlipHorizontal,
+    Rotate180,
+    FlipVertical,
+    /// flipped over the line from the top left to the bottom right
+    Transpose,
+    /// a quarter turn clockwise
+    Rotate90,
+    /// flipped over the line from the top right to the bottom left
+    Transverse,
+    /// a quarter turn anticlockwise
+    Rotate270,
+}
+
+impl Orientation {
+    /// from the value of the orientation tag
+    pub fn from_tag(tag: u16) -> Option<Self> {
+        use self::Orientation::*;
+        let orientation = match tag {
+            1 => Normal,
+            2 => FlipHorizontal,
+            3 => Rotate180,
+            4 => FlipVertical,
+            5 => Transpose,
+            6 => Rotate90,
+            7 => Transverse,
+            8 => Rotate270,
+            _ => return None,
+        };
+        Some(orientation)
+    }
+
+    /// whether the width and height trade places
+    pub fn swaps_dimensions(self) -> bool {
+        use self::Orientation::*;
+        matches!(self, Transpose | Rotate90 | Transverse | Rotate270)
+    }
+}
+
+/// What the EXIF data is used for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExifOptions {
+    /// turn and flip images the way the camera says they were held
+    pub orientation: bool,
+}
+
+impl Default for ExifOptions {
+    fn default() -> Self {
+        Self { orientation: true }
+    }
+}
+
+impl Exif {
+    /// when the picture was taken as `YYYYMMDDHHMMSS`, so that it can be compared
+    pub fn taken_key(&self) -> Option<u64> {
+        let digits = self
+            .taken
+            .as_ref()?
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>();
+        if digits.len() != 14 {
+            return None;
+        }
+        digits.parse().ok()
+    }
+}
+
+/// finds the exif data in a jpeg
+pub fn read_jpeg<R: Read>(rd: R) -> Option<Exif> {
+    let mut data = vec![];
+    rd.take(MAX_HEADER).read_to_end(&mut data).ok()?;
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    // walk the segments until the image data starts
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        let len = usize::from(u16::from(data[pos + 2]) << 8 | u16::from(data[pos + 3]));
+        if marker == 0xDA || len < 2 {
+            return None; // start of scan, there's nothing after this
+        }
+
+        let segment = data.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return parse(&segment[6..]);
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+/// reads the TIFF structure that exif is stored as
+pub fn parse(tiff: &[u8]) -> Option<Exif> {
+    let tiff = Tiff::new(tiff)?;
+    let ifd0 = tiff.u32(4)? as usize;
+
+    let original = tiff
+        .find(ifd0, TAG_EXIF_IFD)
+        .and_then(|(_, _, value)| tiff.find(value as usize, TAG_DATE_TIME_ORIGINAL))
+        .and_then(|entry| tiff.ascii(entry));
+    let taken = original.or_else(|| tiff.find(ifd0, TAG_DATE_TIME).and_then(|e| tiff.ascii(e)));
+    let orientation = tiff
+        .find(ifd0, TAG_ORIENTATION)
+        .and_then(|entry| tiff.short(entry))
+        .and_then(Orientation::from_tag);
+
+    Some(Exif { taken, orientation })
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(0..4)? {
+            b"II*\0" => false,
+            b"MM\0*" => true,
+            _ => return None,
+        };
+        Some(Self { data, big_endian })
+    }
+
+    fn u16(&self, pos: usize) -> Option<u16> {
+        let b = self.data.get(pos..pos + 2)?;
+        let (hi, lo) = if self.big_endian { (b[0], b[1]) } else { (b[1], b[0]) };
+        Some(u16::from(hi) << 8 | u16::from(lo))
+    }
+
+    fn u32(&self, pos: usize) -> Option<u32> {
+        let (a, b) = (u32::from(self.u16(pos)?), u32::from(self.u16(pos + 2)?));
+        Some(if self.big_endian { a << 16 | b } else { b << 16 | a })
+    }
+
+    /// the (type, count, value or offset) of `tag` in the directory at `ifd`
+    fn find(&self, ifd: usize, tag: u16) -> Option<(u16, u32, u32)> {
+        let count = usize::from(self.u16(ifd)?);
+        (0..count)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|&entry| self.u16(entry) == Some(tag))
+            .and_then(|entry| {
+                Some((
+                    self.u16(entry + 2)?,
+                    self.u32(entry + 4)?,
+                    self.u32(entry + 8)?,
+                ))
+            })
+    }
+
+    fn short(&self, (kind, count, value): (u16, u32, u32)) -> Option<u16> {
+        const SHORT: u16 = 3;
+        if kind != SHORT || count != 1 {
+            return None;
+        }
+        // it's inline, at the start of the value
+        Some(if self.big_endian {
+            (value >> 16) as u16
+        } else {
+            value as u16
+        })
+    }
+
+    fn ascii(&self, (kind, count, offset): (u16, u32, u32)) -> Option<String> {
+        const ASCII: u16 = 2;
+        if kind != ASCII || count <= 4 {
+            return None; // dates never fit inline
+        }
+        let start = offset as usize;
+        let bytes = self.data.get(start..start + count as usize)?;
+        let s = String::from_utf8_lossy(bytes);
+        Some(s.trim_end_matches('\0').trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // a little endian tiff with the orientation, and an exif ifd holding DateTimeOriginal
+    fn tiff() -> Vec<u8> {
+        let mut data = b"II*\0".to_vec();
+        data.extend(&[8, 0, 0, 0]);
+        // ifd0 at 8: a quarter turn, and the exif ifd at 38
+        data.extend(&[2, 0]);
+        data.extend(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        data.extend(&[0x69, 0x87, 4, 0, 1, 0, 0, 0, 38, 0, 0, 0]);
+        data.extend(&[0, 0, 0, 0]);
+        // the exif ifd at 38: the date is at 56
+        data.extend(&[1, 0]);
+        data.extend(&[0x03, 0x90, 2, 0, 20, 0, 0, 0, 56, 0, 0, 0]);
+        data.extend(&[0, 0, 0, 0]);
+        data.extend(b"2018:07:21 13:45:09\0");
+        data
+    }
+
+    #[test]
+    fn parse_tiff() {
+        let exif = parse(&tiff()).unwrap();
+        assert_eq!(exif.taken, Some("2018:07:21 13:45:09".to_string()));
+        assert_eq!(exif.taken_key(), Some(20180721134509));
+        assert_eq!(exif.orientation, Some(Orientation::Rotate90));
+
+        assert_eq!(parse(b"nope"), None);
+        assert_eq!(parse(b"MM\0*\0\0\0\x08\0\0"), Some(Exif::default()));
+    }
+
+    #[test]
+    fn orientation() {
+        let mut data = b"MM\0*\0\0\0\x08\0\x01".to_vec();
+        data.extend(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 8, 0, 0, 0, 0, 0, 0]);
+        let exif = parse(&data).unwrap();
+        assert_eq!(exif.orientation, Some(Orientation::Rotate270));
+        assert!(Orientation::Rotate270.swaps_dimensions());
+
+        // out of range, or not a short
+        data[19] = 9;
+        assert_eq!(parse(&data).unwrap().orientation, None);
+        data[13] = 4;
+        data[19] = 8;
+        assert_eq!(parse(&data).unwrap().orientation, None);
+
+        assert_eq!(Orientation::from_tag(0), None);
+        assert!(!Orientation::from_tag(3).unwrap().swaps_dimensions());
+    }
+
+    #[test]
+    fn find_in_jpeg() {
+        let tiff = tiff();
+        let len = tiff.len() + 8;
+        let mut jpeg = vec![0xFF, 0xD8];
+        // something before the exif
+        jpeg.extend(&[0xFF, 0xE0, 0, 4, 0, 0]);
+        jpeg.extend(&[0xFF, 0xE1, (len >> 8) as u8, len as u8]);
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(&tiff);
+        jpeg.extend(&[0xFF, 0xDA, 0, 2]);
+
+        let exif = read_jpeg(&jpeg[..]).unwrap();
+        assert_eq!(exif.taken_key(), Some(20180721134509));
+
+        // the test image doesn't have any
+        let file = ::std::fs::File::open("testdata/test.jpg").unwrap();
+        assert_eq!(read_jpeg(file), None);
+    }
+}
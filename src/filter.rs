@@ -0,0 +1,308 @@
+use std::fmt;
+
+use image::ImageFormat;
+use regex::{self, Regex};
+
+use entry::Entry;
+use format;
+
+/// How a file name is matched
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// anywhere in the name, ignoring case
+    Substring(String),
+    /// the whole name, with `*`, `?` and `[...]`, ignoring case
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Pattern::Substring(s) => name.to_lowercase().contains(&s.to_lowercase()),
+            Pattern::Glob(glob) => glob_match(&glob.to_lowercase(), &name.to_lowercase()),
+            Pattern::Regex(re) => re.is_match(name),
+        }
+    }
+}
+
+/// Which files in the list are shown.
+///
+/// It's parsed from what's typed after `/`. Words are matched against the name in the list,
+/// except for these:
+/// * `re:<regex>` matches with a regex instead
+/// * `type:<ext>` only shows images of that format, e.g. `type:png`
+/// * `min:<w>x<h>` only shows images at least that big
+///
+/// a name with `*`, `?` or `[` in it is a glob, anything else matches part of the name
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub patterns: Vec<Pattern>,
+    pub formats: Vec<ImageFormat>,
+    pub min_dimensions: Option<(u32, u32)>,
+}
+
+/// Why a filter couldn't be used
+#[derive(Debug, PartialEq)]
+pub enum FilterError {
+    Regex(String),
+    UnknownFormat(String),
+    InvalidDimensions(String),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterError::Regex(err) => write!(f, "invalid regex: {}", err),
+            FilterError::UnknownFormat(ext) => write!(f, "unknown format: {}", ext),
+            FilterError::InvalidDimensions(s) => write!(f, "expected <w>x<h>, got: {}", s),
+        }
+    }
+}
+
+impl From<regex::Error> for FilterError {
+    fn from(err: regex::Error) -> Self {
+        FilterError::Regex(err.to_string())
+    }
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Result<Self, FilterError> {
+        let mut filter = Self::default();
+        for word in query.split_whitespace() {
+            if let Some(re) = word.strip_prefix("re:") {
+                filter.patterns.push(Pattern::Regex(Regex::new(re)?));
+            } else if let Some(ext) = word.strip_prefix("type:") {
+                let format = format::from_extension(ext)
+                    .ok_or_else(|| FilterError::UnknownFormat(ext.to_string()))?;
+                filter.formats.push(format);
+            } else if let Some(size) = word.strip_prefix("min:") {
+                let dimensions = parse_dimensions(size)
+                    .ok_or_else(|| FilterError::InvalidDimensions(size.to_string()))?;
+                filter.min_dimensions = Some(dimensions);
+            } else if word.contains(&['*', '?', '['][..]) {
+                filter.patterns.push(Pattern::Glob(word.to_string()));
+            } else {
+                filter.patterns.push(Pattern::Substring(word.to_string()));
+            }
+        }
+        Ok(filter)
+    }
+
+    /// whether this lets everything through
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty() && self.formats.is_empty() && self.min_dimensions.is_none()
+    }
+
+    /// whether `entry` is shown. the file is only read for the format and dimensions
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let name = entry.display_name();
+        if !self.patterns.iter().all(|p| p.matches(&name)) {
+            return false;
+        }
+
+        if !self.formats.is_empty() {
+            match entry.format() {
+                Some(format) if self.formats.contains(&format) => {}
+                _ => return false,
+            }
+        }
+
+        match (self.min_dimensions, self.min_dimensions.and(entry.dimensions())) {
+            (None, _) => true,
+            (Some((w, h)), Some((width, height))) => width >= w && height >= h,
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// matches all of `name` against `glob`, where `*` is any run of characters, `?` is any
+/// one character and `[abc]`, `[a-z]` or `[!abc]` is one of (or none of) a set
+pub fn glob_match(glob: &str, name: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    // where to go back to when the last `*` has to take another character
+    let mut star: Option<(usize, usize)> = None;
+    let (mut g, mut n) = (0, 0);
+    while n < name.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, n));
+                g += 1;
+                continue;
+            }
+            Some('?') => {
+                g += 1;
+                n += 1;
+                continue;
+            }
+            Some('[') => {
+                if let Some((matched, len)) = match_class(&glob[g..], name[n]) {
+                    if matched {
+                        g += len;
+                        n += 1;
+                        continue;
+                    }
+                } else if name[n] == '[' {
+                    // no closing bracket, so it's just a bracket
+                    g += 1;
+                    n += 1;
+                    continue;
+                }
+            }
+            Some(&c) if c == name[n] => {
+                g += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        match star {
+            Some((sg, sn)) => {
+                star = Some((sg, sn + 1));
+                g = sg + 1;
+                n = sn + 1;
+            }
+            None => return false,
+        }
+    }
+
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+// whether `c` is in the class at the start of `glob`, and how long the class is
+fn match_class(glob: &[char], c: char) -> Option<(bool, usize)> {
+    let negated = glob.get(1) == Some(&'!');
+    let start = if negated { 2 } else { 1 };
+    // a `]` right at the start is part of the set
+    let end = glob.get(start + 1..)?.iter().position(|&c| c == ']')? + start + 1;
+    let set = &glob[start..end];
+
+    let mut found = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            found |= set[i] <= c && c <= set[i + 2];
+            i += 3;
+        } else {
+            found |= set[i] == c;
+            i += 1;
+        }
+    }
+    Some((found != negated, end + 1))
+}
+
+fn parse_dimensions(s: &str) -> Option<(u32, u32)> {
+    let mut parts = s.splitn(2, &['x', 'X'][..]);
+    let w = parts.next()?.parse().ok()?;
+    let h = parts.next()?.parse().ok()?;
+    Some((w, h))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::{EventType, Key};
+    use headless::Headless;
+    use testutil::{drop, key};
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("*.png", "a.png"));
+        assert!(glob_match("*.png", ".png"));
+        assert!(!glob_match("*.png", "a.png.jpg"));
+        assert!(glob_match("img?.*", "img1.jpg"));
+        assert!(!glob_match("img?.*", "img10.jpg"));
+        assert!(glob_match("*1*", "img10.jpg"));
+        assert!(glob_match("[a-c]*", "b.png"));
+        assert!(!glob_match("[!a-c]*", "b.png"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("a[b", "a[b"));
+        assert!(glob_match("a[", "a["));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn filtering() {
+        let png = Entry::new("testdata/test.png");
+        let gif = Entry::new("testdata/test.gif");
+
+        let filter = Filter::parse("").unwrap();
+        assert!(filter.is_empty());
+        assert!(filter.matches(&png));
+
+        assert!(Filter::parse("PNG").unwrap().matches(&png));
+        assert!(!Filter::parse("png").unwrap().matches(&gif));
+        assert!(Filter::parse("t*.gif").unwrap().matches(&gif));
+        assert!(!Filter::parse("re:^T").unwrap().matches(&gif));
+        assert!(Filter::parse("re:(?i)^T").unwrap().matches(&gif));
+        // every word has to match
+        assert!(!Filter::parse("test gif").unwrap().matches(&png));
+
+        // these read the file
+        assert!(Filter::parse("type:gif").unwrap().matches(&gif));
+        assert!(!Filter::parse("type:jpg type:gif").unwrap().matches(&png));
+        assert!(Filter::parse("min:8x6").unwrap().matches(&png));
+        assert!(!Filter::parse("min:9x1").unwrap().matches(&png));
+
+        assert!(Filter::parse("re:(").is_err());
+        assert_eq!(
+            Filter::parse("type:doc").unwrap_err(),
+            FilterError::UnknownFormat("doc".into())
+        );
+        assert_eq!(
+            Filter::parse("min:big").unwrap_err().to_string(),
+            "expected <w>x<h>, got: big"
+        );
+    }
+
+    #[test]
+    fn type_to_filter() {
+        let mut app = Headless::new(80, 60);
+        let typed = |text: &str| {
+            text.chars()
+                .map(|c| EventType::Char { c })
+                .collect::<Vec<_>>()
+        };
+
+        app.handle(&drop("test.jpg"));
+        app.run(&typed("/pn"));
+        assert!(app.viewer().is_typing());
+        assert_eq!(app.title(), Some("testdata /pn"));
+        assert_eq!(app.files().len(), 1);
+        // the jpg was filtered out, so the png is shown instead
+        assert_eq!(app.selected(), Some(0));
+        assert_eq!(app.context().current().unwrap().name(), "test.png");
+
+        // keys are typed rather than used while typing
+        app.handle(&key(Key::D));
+        app.run(&typed("\x08\x08\x08*.?if\r"));
+        assert!(!app.viewer().is_typing());
+        assert_eq!(app.files()[0].0, "test.gif");
+        assert_eq!(app.viewer().image().unwrap().frames(), 3);
+        app.handle(&key(Key::D));
+        assert_eq!(app.selected(), Some(0));
+
+        // a regex that isn't finished keeps the last filter, which was empty
+        app.run(&typed("/\x08\x08\x08\x08\x08"));
+        assert_eq!(app.title(), Some("testdata /"));
+        assert_eq!(app.files().len(), 3);
+        app.run(&typed("re:(jpg"));
+        assert_eq!(app.files().len(), 3);
+        app.run(&typed("|png)"));
+        assert_eq!(app.files().len(), 2);
+        app.run(&typed("\r"));
+        assert_eq!(app.context().current().unwrap().name(), "test.jpg");
+
+        // escape clears it, and the same file stays selected
+        app.handle(&EventType::Char { c: '\x1b' });
+        assert_eq!(app.viewer().filter_query(), None);
+        assert_eq!(app.title(), Some("testdata"));
+        assert_eq!(app.files().len(), 3);
+        assert_eq!(app.selected(), Some(1));
+        assert_eq!(app.context().current().unwrap().name(), "test.jpg");
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use image::ImageFormat;
+
+/// how much of the start of a file is looked at, the same amount `ImageBuffer` reads
+pub const MAGIC_LEN: usize = 17;
+
+/// How the frames of a format are decoded
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Decoder {
+    /// `image::load`, which gives a single frame
+    Still,
+    /// the `gif` crate, frame by frame
+    Gif,
+    /// `image::load` for a still, or frame by frame from the animation chunks
+    Png,
+    /// the same, for the animated kind
+    WebP,
+}
+
+/// What we know about a format
+#[derive(Debug)]
+pub struct FormatInfo {
+    pub format: ImageFormat,
+    /// what it's called in the config
+    pub name: &'static str,
+    /// the first one is the usual one
+    pub extensions: &'static [&'static str],
+    pub decoder: Decoder,
+    /// whether it starts with bytes that say what it is. the others go by their extension
+    pub magic: bool,
+    /// whether a file can have more than one frame
+    pub animated: bool,
+    /// whether it can be see-through
+    pub alpha: bool,
+    /// whether it has more range than can be shown, which gets squashed down
+    pub hdr: bool,
+}
+
+/// Everything that can be shown.
+///
+/// DDS isn't here, `image` 0.19 only has the DXT codecs and not the DDS container. WebP
+/// is only the lossy kind, and only its brightness, as that's all `image` decodes. Its
+/// alpha is only there when it's stored uncompressed
+pub static FORMATS: [FormatInfo; 10] = [
+    FormatInfo {
+        format: ImageFormat::PNG,
+        name: "png",
+        extensions: &["png", "apng"],
+        decoder: Decoder::Png,
+        magic: true,
+        animated: true,
+        alpha: true,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::JPEG,
+        name: "jpeg",
+        extensions: &["jpg", "jpeg", "jpe", "jfif"],
+        decoder: Decoder::Still,
+        magic: true,
+        animated: false,
+        alpha: false,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::GIF,
+        name: "gif",
+        extensions: &["gif"],
+        decoder: Decoder::Gif,
+        magic: true,
+        animated: true,
+        alpha: true,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::WEBP,
+        name: "webp",
+        extensions: &["webp"],
+        decoder: Decoder::WebP,
+        magic: true,
+        animated: true,
+        alpha: true,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::BMP,
+        name: "bmp",
+        extensions: &["bmp", "dib"],
+        decoder: Decoder::Still,
+        magic: true,
+        animated: false,
+        alpha: true,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::ICO,
+        name: "ico",
+        extensions: &["ico"],
+        decoder: Decoder::Still,
+        magic: true,
+        animated: false,
+        alpha: true,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::TIFF,
+        name: "tiff",
+        extensions: &["tif", "tiff"],
+        decoder: Decoder::Still,
+        magic: true,
+        animated: false,
+        alpha: true,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::TGA,
+        name: "tga",
+        extensions: &["tga"],
+        decoder: Decoder::Still,
+        magic: false,
+        animated: false,
+        alpha: true,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::PNM,
+        name: "pnm",
+        extensions: &["pnm", "pbm", "pgm", "ppm", "pam"],
+        decoder: Decoder::Still,
+        magic: true,
+        animated: false,
+        alpha: true,
+        hdr: false,
+    },
+    FormatInfo {
+        format: ImageFormat::HDR,
+        name: "hdr",
+        extensions: &["hdr", "rgbe"],
+        decoder: Decoder::Still,
+        magic: true,
+        animated: false,
+        alpha: false,
+        hdr: true,
+    },
+];
+
+/// what we know about `format`, if it can be shown
+pub fn info(format: ImageFormat) -> Option<&'static FormatInfo> {
+    FORMATS.iter().find(|info| info.format == format)
+}
+
+pub fn is_supported(format: ImageFormat) -> bool {
+    info(format).is_some()
+}
+
+/// the format called `name` in the config
+pub fn from_name(name: &str) -> Option<ImageFormat> {
+    let name = name.to_lowercase();
+    FORMATS
+        .iter()
+        .find(|info| info.name == name)
+        .map(|info| info.format)
+}
+
+/// the format that usually has `ext` as its extension
+pub fn from_extension(ext: &str) -> Option<ImageFormat> {
+    let ext = ext.to_lowercase();
+    FORMATS
+        .iter()
+        .find(|info| info.extensions.contains(&ext.as_str()))
+        .map(|info| info.format)
+}
+
+/// what `buf`, the start of a file, says it is. `None` if it isn't something we can show.
+///
+/// `image::guess_format` does the same, but it gets WebP and TIFF wrong, and takes any
+/// text that starts with `P1` for a PNM
+pub fn sniff(buf: &[u8]) -> Option<ImageFormat> {
+    let starts = |magic: &[u8]| buf.starts_with(magic);
+    let format = if starts(b"\x89PNG\r\n\x1a\n") {
+        ImageFormat::PNG
+    } else if starts(&[0xFF, 0xD8, 0xFF]) {
+        ImageFormat::JPEG
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        ImageFormat::GIF
+    } else if starts(b"RIFF") && buf.get(8..12) == Some(&b"WEBP"[..]) {
+        ImageFormat::WEBP
+    } else if starts(b"II*\0") || starts(b"MM\0*") {
+        ImageFormat::TIFF
+    } else if starts(b"BM") {
+        ImageFormat::BMP
+    } else if starts(&[0, 0, 1, 0]) {
+        ImageFormat::ICO
+    } else if starts(b"#?RADIANCE") || starts(b"#?RGBE") {
+        ImageFormat::HDR
+    } else if is_pnm(buf) {
+        ImageFormat::PNM
+    } else {
+        return None;
+    };
+    Some(format)
+}
+
+// `P1` to `P7`, and then whitespace
+fn is_pnm(buf: &[u8]) -> bool {
+    match buf {
+        [b'P', n, space, ..] => (b'1'..=b'7').contains(n) && space.is_ascii_whitespace(),
+        _ => false,
+    }
+}
+
+/// what the file at `path` is, going by what's in it rather than its name. formats that
+/// can't be told apart by their first bytes go by the name. `None` if it can't be read,
+/// or it isn't something we can show
+pub fn detect(path: &Path) -> Option<ImageFormat> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            debug!("cannot open {:?}: {}", path, err);
+            return None;
+        }
+    };
+
+    let mut buf = Vec::with_capacity(MAGIC_LEN);
+    if let Err(err) = file.take(MAGIC_LEN as u64).read_to_end(&mut buf) {
+        debug!("cannot read {:?}: {}", path, err);
+        return None;
+    }
+
+    let named = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(from_extension);
+    match sniff(&buf) {
+        Some(format) => {
+            if named.is_some() && named != Some(format) {
+                debug!("{:?} is really {:?}", path, format);
+            }
+            Some(format)
+        }
+        None => named.filter(|&format| info(format).is_some_and(|info| !info.magic)),
+    }
+}
+
+/// Which formats are listed and shown. They all are, unless the config turns them off
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Formats {
+    disabled: Vec<ImageFormat>,
+}
+
+impl Formats {
+    /// from the `[formats]` table in the config, of names to whether they're on. names
+    /// that aren't formats are skipped
+    pub fn from_table(table: &BTreeMap<String, bool>) -> Self {
+        let mut formats = Self::default();
+        for (name, &enabled) in table {
+            match from_name(name) {
+                Some(format) => formats.set_enabled(format, enabled),
+                None => warn!("unknown format in the config: {}", name),
+            }
+        }
+        formats
+    }
+
+    pub fn set_enabled(&mut self, format: ImageFormat, enabled: bool) {
+        self.disabled.retain(|&f| f != format);
+        if !enabled {
+            self.disabled.push(format)
+        }
+    }
+
+    pub fn is_enabled(&self, format: ImageFormat) -> bool {
+        is_supported(format) && !self.disabled.contains(&format)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use testutil::TempDir;
+
+    #[test]
+    fn magic() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n...."), Some(ImageFormat::PNG));
+        assert_eq!(sniff(&[0xff, 0xd8, 0xff, 0xe0]), Some(ImageFormat::JPEG));
+        assert_eq!(sniff(b"GIF87a"), Some(ImageFormat::GIF));
+        assert_eq!(sniff(b"RIFF\x10\0\0\0WEBPVP8 "), Some(ImageFormat::WEBP));
+        assert_eq!(sniff(b"RIFF\x10\0\0\0WAVEfmt "), None);
+        assert_eq!(sniff(b"II*\0\x08\0\0\0"), Some(ImageFormat::TIFF));
+        assert_eq!(sniff(b"BM...."), Some(ImageFormat::BMP));
+        assert_eq!(sniff(b"#?RADIANCE\n"), Some(ImageFormat::HDR));
+        assert_eq!(sniff(b"P6\n8 6\n255\n"), Some(ImageFormat::PNM));
+        // text that happens to start like a pnm header
+        assert_eq!(sniff(b"P12 steps"), None);
+        assert_eq!(sniff(b""), None);
+
+        assert_eq!(from_extension("JFIF"), Some(ImageFormat::JPEG));
+        assert_eq!(from_extension("apng"), Some(ImageFormat::PNG));
+        assert_eq!(from_extension("ppm"), Some(ImageFormat::PNM));
+        assert_eq!(from_extension("dds"), None);
+        assert_eq!(from_extension("txt"), None);
+
+        // every format is somewhere, once
+        for info in &FORMATS {
+            assert_eq!(from_name(info.name), Some(info.format));
+            for ext in info.extensions {
+                assert_eq!(from_extension(ext), Some(info.format));
+            }
+        }
+    }
+
+    #[test]
+    fn by_contents() {
+        let dir = TempDir::new("format");
+        let files = [
+            ("test.png", "png", Some(ImageFormat::PNG)),
+            ("test.png", "really.jpg", Some(ImageFormat::PNG)),
+            ("test.jpg", "photo.jfif", Some(ImageFormat::JPEG)),
+            ("test.gif", "no-extension", Some(ImageFormat::GIF)),
+            // there's nothing to sniff in a tga, it goes by the name
+            ("test.png", "really.tga", Some(ImageFormat::PNG)),
+        ];
+        for &(from, to, format) in &files {
+            fs::copy(Path::new("testdata").join(from), dir.join(to)).unwrap();
+            assert_eq!(detect(&dir.join(to)), format, "{}", to);
+        }
+
+        fs::write(dir.join("short.png"), b"GIF").unwrap();
+        assert_eq!(detect(&dir.join("short.png")), None);
+        fs::write(dir.join("plain.tga"), b"\0\0\x02").unwrap();
+        assert_eq!(detect(&dir.join("plain.tga")), Some(ImageFormat::TGA));
+        assert_eq!(detect(&dir.join("missing.png")), None);
+    }
+
+    #[test]
+    fn enabled() {
+        let mut formats = Formats::default();
+        assert!(FORMATS.iter().all(|info| formats.is_enabled(info.format)));
+
+        let table = [("tga", false), ("HDR", false), ("bmp", true), ("dds", false)]
+            .iter()
+            .map(|&(name, on)| (name.to_string(), on))
+            .collect();
+        let from_config = Formats::from_table(&table);
+        assert!(!from_config.is_enabled(ImageFormat::TGA));
+        assert!(!from_config.is_enabled(ImageFormat::HDR));
+        assert!(from_config.is_enabled(ImageFormat::BMP));
+
+        formats.set_enabled(ImageFormat::GIF, false);
+        formats.set_enabled(ImageFormat::GIF, false);
+        assert!(!formats.is_enabled(ImageFormat::GIF));
+        formats.set_enabled(ImageFormat::GIF, true);
+        assert_eq!(formats, Formats::default());
+    }
+}
@@ -0,0 +1,203 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use animation::{SystemClock, TimeSource};
+use args::Args;
+use context::Context;
+use event::EventType;
+use render::Framebuffer;
+use viewer::{Effect, Viewer};
+
+/// A clock that only moves when it's told to, so scripts are repeatable
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock(Rc<Cell<Duration>>);
+
+impl ManualClock {
+    pub fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + by)
+    }
+}
+
+impl TimeSource for ManualClock {
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+}
+
+/// A frontend without any windows.
+///
+/// Events are fed to the viewer as if they came from a window, the image is drawn into
+/// an in-memory framebuffer, and whatever the frontend would have shown (the file list,
+/// the trackbar, the animation timer) is kept around to be looked at.
+#[derive(Debug)]
+pub struct Headless<T = SystemClock> {
+    viewer: Viewer<T>,
+    framebuffer: Framebuffer,
+
+    title: Option<String>,
+    files: Vec<(String, usize)>,
+    selected: Option<usize>,
+    filelist_visible: bool,
+    trackbar: Option<usize>,
+    trackbar_pos: usize,
+    timer: Option<Duration>,
+}
+
+impl Headless {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self::with_clock(width, height, SystemClock::default())
+    }
+}
+
+impl<T: TimeSource + Clone> Headless<T> {
+    pub fn with_clock(width: i32, height: i32, clock: T) -> Self {
+        let context = Arc::new(Mutex::new(Context::new()));
+        let mut this = Self {
+            viewer: Viewer::with_clock(context, clock),
+            framebuffer: Framebuffer::default(),
+            title: None,
+            files: vec![],
+            selected: None,
+            filelist_visible: false,
+            trackbar: None,
+            trackbar_pos: 0,
+            timer: None,
+        };
+        this.handle(&EventType::Resize { width, height });
+        this
+    }
+
+    /// handles each event in order
+    pub fn run(&mut self, script: &[EventType]) {
+        for ev in script {
+            self.handle(ev);
+        }
+    }
+
+    pub fn handle(&mut self, ev: &EventType) -> Vec<Effect> {
+        trace!("headless event: {:?}", ev);
+        if let EventType::Resize { width, height } = *ev {
+            self.framebuffer.resize(width, height);
+        }
+
+        let effects = self.viewer.handle(ev);
+        for effect in &effects {
+            self.apply(effect)
+        }
+        effects
+    }
+
+    /// opens what's on the command line
+    pub fn start(&mut self, args: &Args) -> Vec<Effect> {
+        let effects = self.viewer.start(args);
+        for effect in &effects {
+            self.apply(effect)
+        }
+        effects
+    }
+
+    pub fn viewer(&self) -> &Viewer<T> {
+        &self.viewer
+    }
+
+    pub fn viewer_mut(&mut self) -> &mut Viewer<T> {
+        &mut self.viewer
+    }
+
+    pub fn context(&self) -> MutexGuard<'_, Context> {
+        self.viewer.context()
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// the title of the file list
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// the rows of the file list
+    pub fn files(&self) -> &[(String, usize)] {
+        &self.files
+    }
+
+    /// the selected row of the file list
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn is_filelist_visible(&self) -> bool {
+        self.filelist_visible
+    }
+
+    /// the trackbar range (`0..=max`), if it's shown
+    pub fn trackbar(&self) -> Option<usize> {
+        self.trackbar
+    }
+
+    pub fn trackbar_pos(&self) -> usize {
+        self.trackbar_pos
+    }
+
+    /// when the animation timer is going to fire, if it's running
+    pub fn timer(&self) -> Option<Duration> {
+        self.timer
+    }
+
+    fn apply(&mut self, effect: &Effect) {
+        match *effect {
+            Effect::Select(index) => self.selected = Some(index),
+            Effect::Populate {
+                ref title,
+                ref files,
+            } => {
+                self.title = Some(title.clone());
+                self.files = files.clone();
+                self.selected = None;
+            }
+            Effect::ToggleFileList => self.filelist_visible = !self.filelist_visible,
+            Effect::AlignFileList => {}
+            Effect::SetFrames(max) => {
+                self.trackbar = max;
+                self.trackbar_pos = 0;
+            }
+            Effect::SetFramePosition(pos) => self.trackbar_pos = pos,
+            Effect::Schedule(next) => self.timer = next,
+            Effect::Redraw => self.viewer.render(&mut self.framebuffer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::Key;
+    use render::BACKGROUND;
+    use testutil::{drop, key};
+
+    #[test]
+    fn drop_still() {
+        let mut app = Headless::new(80, 60);
+        app.run(&[drop("test.png")]);
+
+        assert_eq!(app.title(), Some("testdata"));
+        assert_eq!(app.files().len(), 3);
+        assert_eq!(app.context().get_len(), 3);
+        assert_eq!(app.trackbar(), None);
+        assert_eq!(app.timer(), None);
+
+        // 8x6 fits exactly into 80x60 at 10x
+        let fb = app.framebuffer();
+        assert_eq!(fb.pixel(5, 5), Some(0x00_00_00_80));
+        assert_eq!(fb.pixel(75, 55), app.framebuffer().pixel(79, 59));
+        assert_ne!(fb.pixel(75, 55), fb.pixel(65, 55));
+
+        app.handle(&key(Key::Key2));
+        let fb = app.framebuffer();
+        assert_eq!(fb.pixel(0, 0), Some(BACKGROUND));
+        assert_eq!(fb.pixel(36, 27), Some(0x00_00_00_80));
+    }
+}
@@ -1,17 +1,216 @@
 #![allow(dead_code)]
+use std::fmt;
 use std::fs;
-use std::io::{self, BufReader, Read, Seek, SeekFrom};
-use std::path::{Path, PathBuf};
-use std::{mem, ptr};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
 
-use image;
+use gif;
+use image::{self, ImageFormat};
 
-use common::*;
+use apng;
+use compose::{Animated, Blend, Canvas, Dispose, Part};
+use exif::{self, Orientation};
+use format::{self, Decoder};
+use webp;
 
-pub struct ImageViewport {}
+/// The limits for free zooming
+pub const MIN_SCALE: f64 = 0.05;
+pub const MAX_SCALE: f64 = 32.0;
 
-pub struct ImageView {
-    hwnd: windef::HWND,
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Zoom {
+    /// the whole image fits in the window
+    Fit,
+    /// the image covers the whole window
+    Fill,
+    /// one image pixel per screen pixel
+    Actual,
+    /// a specific scale
+    Fixed(f64),
+}
+
+/// Maps between image coordinates and window (client) coordinates.
+///
+/// `screen = image * scale + offset`
+#[derive(Debug, Clone)]
+pub struct ImageViewport {
+    image: (f64, f64),
+    window: (f64, f64),
+    zoom: Zoom,
+    scale: f64,
+    offset: (f64, f64),
+    pan: Option<(i32, i32)>, // last cursor position while panning
+}
+
+impl Default for ImageViewport {
+    fn default() -> Self {
+        Self {
+            image: (0.0, 0.0),
+            window: (0.0, 0.0),
+            zoom: Zoom::Fit,
+            scale: 1.0,
+            offset: (0.0, 0.0),
+            pan: None,
+        }
+    }
+}
+
+impl ImageViewport {
+    /// a new image was loaded. the zoom mode is kept, and the image is centered
+    pub fn set_image_size(&mut self, w: u32, h: u32) {
+        self.image = (f64::from(w), f64::from(h));
+        self.pan = None;
+        self.offset = (0.0, 0.0);
+        let zoom = self.zoom;
+        self.set_zoom_centered(zoom);
+    }
+
+    /// the window was resized. keeps whatever was in the middle of the window in the middle
+    pub fn set_window_size(&mut self, w: i32, h: i32) {
+        let center = self.to_image(self.window.0 / 2.0, self.window.1 / 2.0);
+        self.window = (f64::from(w.max(0)), f64::from(h.max(0)));
+
+        match self.zoom {
+            Zoom::Fit | Zoom::Fill => {
+                self.scale = self.scale_for(self.zoom);
+                self.center();
+            }
+            Zoom::Actual | Zoom::Fixed(_) => {
+                self.offset = (
+                    self.window.0 / 2.0 - center.0 * self.scale,
+                    self.window.1 / 2.0 - center.1 * self.scale,
+                );
+                self.clamp();
+            }
+        }
+    }
+
+    pub fn zoom(&self) -> Zoom {
+        self.zoom
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// changes the zoom, anchored at the middle of the window
+    pub fn set_zoom(&mut self, zoom: Zoom) {
+        let (x, y) = (self.window.0 / 2.0, self.window.1 / 2.0);
+        self.zoom_to(zoom, (x, y));
+    }
+
+    /// multiplies the scale by `factor`, keeping the image point under `cursor` where it is
+    pub fn zoom_at(&mut self, factor: f64, cursor: (i32, i32)) {
+        let scale = self.scale * factor;
+        let cursor = (f64::from(cursor.0), f64::from(cursor.1));
+        self.zoom_to(Zoom::Fixed(scale), cursor);
+    }
+
+    pub fn begin_pan(&mut self, pos: (i32, i32)) {
+        self.pan = Some(pos);
+    }
+
+    pub fn is_panning(&self) -> bool {
+        self.pan.is_some()
+    }
+
+    /// moves the image along with the cursor. returns whether anything moved
+    pub fn pan_to(&mut self, pos: (i32, i32)) -> bool {
+        let last = match self.pan {
+            Some(last) => last,
+            None => return false,
+        };
+        self.pan = Some(pos);
+
+        let before = self.offset;
+        self.offset.0 += f64::from(pos.0 - last.0);
+        self.offset.1 += f64::from(pos.1 - last.1);
+        self.clamp();
+        before != self.offset
+    }
+
+    pub fn end_pan(&mut self) {
+        self.pan = None;
+    }
+
+    /// image coordinates to window coordinates
+    pub fn to_screen(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * self.scale + self.offset.0, y * self.scale + self.offset.1)
+    }
+
+    /// window coordinates to image coordinates
+    pub fn to_image(&self, x: f64, y: f64) -> (f64, f64) {
+        ((x - self.offset.0) / self.scale, (y - self.offset.1) / self.scale)
+    }
+
+    /// where the image lands in the window, as (x, y, w, h). this can extend past the window
+    pub fn image_rect(&self) -> (f64, f64, f64, f64) {
+        let (x, y) = self.offset;
+        (x, y, self.image.0 * self.scale, self.image.1 * self.scale)
+    }
+
+    fn zoom_to(&mut self, zoom: Zoom, anchor: (f64, f64)) {
+        let point = self.to_image(anchor.0, anchor.1);
+        self.zoom = zoom;
+        self.scale = self.scale_for(zoom);
+        if let Zoom::Fixed(_) = zoom {
+            self.zoom = Zoom::Fixed(self.scale); // keep the clamped scale
+        }
+
+        self.offset = (
+            anchor.0 - point.0 * self.scale,
+            anchor.1 - point.1 * self.scale,
+        );
+        self.clamp();
+    }
+
+    fn set_zoom_centered(&mut self, zoom: Zoom) {
+        self.zoom = zoom;
+        self.scale = self.scale_for(zoom);
+        self.center();
+    }
+
+    fn scale_for(&self, zoom: Zoom) -> f64 {
+        let (w, h) = self.image;
+        let scale = match zoom {
+            _ if w == 0.0 || h == 0.0 => 1.0,
+            Zoom::Fit => (self.window.0 / w).min(self.window.1 / h),
+            Zoom::Fill => (self.window.0 / w).max(self.window.1 / h),
+            Zoom::Actual => 1.0,
+            Zoom::Fixed(scale) => scale.clamp(MIN_SCALE, MAX_SCALE),
+        };
+
+        if scale > 0.0 {
+            scale
+        } else {
+            1.0 // the window is 0 sized. keep things invertible
+        }
+    }
+
+    fn center(&mut self) {
+        self.offset = (
+            (self.window.0 - self.image.0 * self.scale) / 2.0,
+            (self.window.1 - self.image.1 * self.scale) / 2.0,
+        );
+    }
+
+    // an axis that's smaller than the window is centered, otherwise the image edges
+    // aren't allowed to come inside of the window
+    fn clamp(&mut self) {
+        fn clamp_axis(offset: f64, image: f64, window: f64) -> f64 {
+            if image <= window {
+                (window - image) / 2.0
+            } else {
+                offset.min(0.0).max(window - image)
+            }
+        }
+
+        self.offset = (
+            clamp_axis(self.offset.0, self.image.0 * self.scale, self.window.0),
+            clamp_axis(self.offset.1, self.image.1 * self.scale, self.window.1),
+        );
+    }
 }
 
 #[derive(Debug)]
@@ -19,8 +218,9 @@ pub enum ImageError {
     Loading(io::Error),
     Reading(io::Error),
     UnsupportedFormat(image::ImageError),
+    Decoding(gif::DecodingError),
     Other(image::ImageError),
-    NotFinished,
+    Empty,
 }
 
 impl From<image::ImageError> for ImageError {
@@ -29,44 +229,295 @@ impl From<image::ImageError> for ImageError {
     }
 }
 
+impl From<gif::DecodingError> for ImageError {
+    fn from(e: gif::DecodingError) -> Self {
+        ImageError::Decoding(e)
+    }
+}
+
+/// A single decoded frame. `left`/`top` are the offset of the frame on the canvas
+pub struct Frame {
+    buffer: image::RgbaImage,
+    left: u32,
+    top: u32,
+    delay: Duration,
+}
+
+impl Frame {
+    pub fn buffer(&self) -> &image::RgbaImage {
+        &self.buffer
+    }
+
+    pub fn left(&self) -> u32 {
+        self.left
+    }
+
+    pub fn top(&self) -> u32 {
+        self.top
+    }
+
+    /// how long this frame should be shown for. still images have a zero delay
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+/// How many times an animation should be played through
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Repeat {
+    Infinite,
+    Finite(u32),
+}
+
 struct InnerImage {
-    frames: Vec<image::Frame>,
+    frames: Vec<Frame>,
     animated: bool,
+    repeat: Repeat,
+    width: u32,
+    height: u32,
 }
 
-pub struct ImageBuffer<'a> {
-    current: Option<&'a image::Frame>, // this should always be in a valid state. maybe?
+pub struct ImageBuffer {
+    current: usize, // always a valid index into inner.frames
     inner: InnerImage,
 }
 
-impl<'a> ImageBuffer<'a> {
-    // TODO figure out which Path I should convert into
-    pub fn new(path: &str) -> Result<Self, ImageError> {
+impl fmt::Debug for ImageBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImageBuffer")
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .field("frames", &self.frames())
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+impl ImageBuffer {
+    /// turned the right way up, if the EXIF data says how
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
+        Self::open(path, None, true)
+    }
+
+    /// `format` is for when it's known already, from `format::detect`. `orient` turns and
+    /// flips it the way the EXIF data says the camera was held
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        format: Option<ImageFormat>,
+        orient: bool,
+    ) -> Result<Self, ImageError> {
+        let path = path.as_ref();
         let mut rd = BufReader::new(fs::File::open(path).map_err(ImageError::Loading)?);
 
-        let mut buf = [0; 17];
-        rd.read_exact(&mut buf).map_err(ImageError::Reading)?;
-        let _ = rd.seek(SeekFrom::Start(0)); // reset position
+        let format = format.or_else(|| format::detect(path)).ok_or_else(|| {
+            let err = "unsupported image format".to_string();
+            ImageError::UnsupportedFormat(image::ImageError::UnsupportedError(err))
+        })?;
+
+        let orientation = match format {
+            ImageFormat::JPEG if orient => {
+                let exif = exif::read_jpeg(&mut rd);
+                rd.seek(SeekFrom::Start(0)).map_err(ImageError::Reading)?;
+                exif.and_then(|exif| exif.orientation)
+            }
+            _ => None,
+        };
 
-        let format = image::guess_format(&buf).map_err(ImageError::UnsupportedFormat)?;
+        let mut image = Self::decode(rd, format)?;
+        if let Some(orientation) = orientation {
+            debug!("{:?} is {:?}", path, orientation);
+            image.orient(orientation);
+        }
+        Ok(image)
     }
 
-    pub fn width(&self) -> u32 {
-        if let Some(ref img) = self.current {
-            img.buffer().width()
-        } else {
-            0
+    fn decode<R: BufRead + Seek>(rd: R, format: ImageFormat) -> Result<Self, ImageError> {
+        let decoder = format::info(format).map(|info| info.decoder);
+        let inner = match decoder {
+            Some(Decoder::Gif) => Self::decode_gif(rd)?,
+            Some(Decoder::Png) => Self::decode_animated(rd, format, apng::decode)?,
+            Some(Decoder::WebP) => Self::decode_animated(rd, format, webp::decode)?,
+            Some(Decoder::Still) => Self::decode_still(rd, format)?,
+            None => {
+                let err = format!("{:?} can't be shown", format);
+                return Err(ImageError::UnsupportedFormat(
+                    image::ImageError::UnsupportedError(err),
+                ));
+            }
+        };
+
+        if inner.frames.is_empty() {
+            return Err(ImageError::Empty);
         }
+
+        Ok(Self { current: 0, inner })
     }
 
-    pub fn height(&self) -> u32 {
-        if let Some(ref img) = self.current {
-            img.buffer().height()
-        } else {
-            0
+    fn decode_still<R: BufRead + Seek>(
+        rd: R,
+        format: ImageFormat,
+    ) -> Result<InnerImage, ImageError> {
+        let buffer = image::load(rd, format)?.to_rgba();
+        let (width, height) = buffer.dimensions();
+
+        Ok(InnerImage {
+            frames: vec![Frame {
+                buffer,
+                left: 0,
+                top: 0,
+                delay: Duration::from_millis(0),
+            }],
+            animated: false,
+            repeat: Repeat::Finite(1),
+            width,
+            height,
+        })
+    }
+
+    // `animation` finds the frames, when there's more than the still `image` would decode
+    fn decode_animated<R: Read>(
+        mut rd: R,
+        format: ImageFormat,
+        animation: fn(&[u8]) -> image::ImageResult<Option<Animated>>,
+    ) -> Result<InnerImage, ImageError> {
+        let mut data = vec![];
+        rd.read_to_end(&mut data).map_err(ImageError::Reading)?;
+
+        match animation(&data) {
+            Ok(Some(ref animated)) if !animated.parts.is_empty() => {
+                return Ok(Self::compose(animated))
+            }
+            Ok(_) => {}
+            Err(err) => warn!("cannot read the animation, trying it as a still: {}", err),
+        }
+        Self::decode_still(io::Cursor::new(data), format)
+    }
+
+    // every frame is drawn over the ones before it, so each one is the whole canvas
+    fn compose(animated: &Animated) -> InnerImage {
+        let mut canvas = Canvas::new(animated.width, animated.height);
+        let frames = animated
+            .parts
+            .iter()
+            .map(|part| Frame {
+                buffer: canvas.draw(part),
+                left: 0,
+                top: 0,
+                delay: part.delay,
+            })
+            .collect::<Vec<_>>();
+
+        InnerImage {
+            animated: frames.len() > 1,
+            repeat: animated.repeat,
+            frames,
+            width: animated.width,
+            height: animated.height,
+        }
+    }
+
+    fn decode_gif<R: Read>(mut rd: R) -> Result<InnerImage, ImageError> {
+        use gif::SetParameter;
+
+        let mut data = vec![];
+        rd.read_to_end(&mut data).map_err(ImageError::Reading)?;
+
+        let mut decoder = gif::Decoder::new(&data[..]);
+        decoder.set(gif::ColorOutput::RGBA);
+        let mut reader = decoder.read_info()?;
+
+        let mut parts = vec![];
+        while let Some(frame) = reader.read_next_frame()? {
+            let buffer = image::RgbaImage::from_raw(
+                u32::from(frame.width),
+                u32::from(frame.height),
+                frame.buffer.to_vec(),
+            ).ok_or(ImageError::Empty)?;
+
+            parts.push(Part {
+                buffer,
+                left: u32::from(frame.left),
+                top: u32::from(frame.top),
+                // gif delays are in 10ms units
+                delay: Duration::from_millis(u64::from(frame.delay) * 10),
+                // the transparent color is the only thing that isn't opaque
+                blend: Blend::Over,
+                dispose: match frame.dispose {
+                    gif::DisposalMethod::Any | gif::DisposalMethod::Keep => Dispose::Keep,
+                    // browsers clear it rather than use the background color, so do that too
+                    gif::DisposalMethod::Background => Dispose::Background,
+                    gif::DisposalMethod::Previous => Dispose::Previous,
+                },
+            });
+        }
+
+        Ok(Self::compose(&Animated {
+            width: u32::from(reader.width()),
+            height: u32::from(reader.height()),
+            repeat: Self::gif_repeat(&data),
+            parts,
+        }))
+    }
+
+    fn orient(&mut self, orientation: Orientation) {
+        for frame in &mut self.inner.frames {
+            frame.buffer = oriented(&frame.buffer, orientation);
+        }
+        if orientation.swaps_dimensions() {
+            let inner = &mut self.inner;
+            ::std::mem::swap(&mut inner.width, &mut inner.height);
         }
     }
 
+    // the gif decoder doesn't expose the NETSCAPE2.0 looping extension, so look for it
+    // ourselves. it has to come before the first image, in the extensions after the header
+    fn gif_repeat(data: &[u8]) -> Repeat {
+        // no extension, or one that's cut short, means play once
+        Self::find_gif_repeat(data).unwrap_or(Repeat::Finite(1))
+    }
+
+    fn find_gif_repeat(data: &[u8]) -> Option<Repeat> {
+        // the header and the logical screen descriptor, then the global color table
+        let flags = *data.get(10)?;
+        let mut pos = 13;
+        if flags & 0x80 != 0 {
+            pos += 3 << ((flags & 0x07) + 1);
+        }
+
+        // each extension is `0x21`, a label, and then blocks that end with an empty one
+        while *data.get(pos)? == 0x21 {
+            let label = *data.get(pos + 1)?;
+            pos += 2;
+            let start = pos;
+            loop {
+                let len = usize::from(*data.get(pos)?);
+                pos += 1 + len;
+                if len == 0 {
+                    break;
+                }
+            }
+
+            let blocks = &data[start..pos];
+            if label == 0xFF && blocks.starts_with(b"\x0BNETSCAPE2.0\x03\x01") {
+                let n = blocks.get(14..16)?;
+                // the count is how many times to repeat after the first play
+                return Some(match (n[0], n[1]) {
+                    (0, 0) => Repeat::Infinite,
+                    (lo, hi) => Repeat::Finite(u32::from(lo) + (u32::from(hi) << 8) + 1),
+                });
+            }
+        }
+        None
+    }
+
+    pub fn width(&self) -> u32 {
+        self.inner.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.inner.height
+    }
+
     pub fn is_animated(&self) -> bool {
         self.inner.animated || self.inner.frames.len() > 1
     }
@@ -75,47 +526,456 @@ impl<'a> ImageBuffer<'a> {
         self.inner.frames.len()
     }
 
-    // these might need to be mut
-    pub fn next(&self) {
-        unimplemented!()
+    pub fn index(&self) -> usize {
+        self.current
+    }
+
+    pub fn repeat(&self) -> Repeat {
+        self.inner.repeat
+    }
+
+    pub fn current(&self) -> &Frame {
+        &self.inner.frames[self.current]
+    }
+
+    /// panics if `index` is out of bounds
+    pub fn frame(&self, index: usize) -> &Frame {
+        &self.inner.frames[index]
     }
 
-    pub fn previous(&self) {
-        unimplemented!()
+    pub fn seek(&mut self, index: usize) -> &Frame {
+        self.current = ::std::cmp::min(index, self.frames() - 1);
+        self.current()
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> &Frame {
+        self.current = if self.current + 1 == self.frames() {
+            0
+        } else {
+            self.current + 1
+        };
+        self.current()
+    }
+
+    pub fn previous(&mut self) -> &Frame {
+        self.current = if self.current == 0 {
+            self.frames() - 1
+        } else {
+            self.current - 1
+        };
+        self.current()
+    }
+}
+
+fn oriented(buffer: &image::RgbaImage, orientation: Orientation) -> image::RgbaImage {
+    use image::imageops::{flip_horizontal, flip_vertical, rotate180, rotate270, rotate90};
+    match orientation {
+        Orientation::Normal => buffer.clone(),
+        Orientation::FlipHorizontal => flip_horizontal(buffer),
+        Orientation::Rotate180 => rotate180(buffer),
+        Orientation::FlipVertical => flip_vertical(buffer),
+        Orientation::Transpose => flip_horizontal(&rotate90(buffer)),
+        Orientation::Rotate90 => rotate90(buffer),
+        Orientation::Transverse => flip_horizontal(&rotate270(buffer)),
+        Orientation::Rotate270 => rotate270(buffer),
     }
 }
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use testutil::{turned_jpeg, TempDir};
 
     #[test]
     fn try_load_image() {
-        let images = ["test.gif", "test.png", "test.jpg"];
-        for image in &images {
-            match ImageBuffer::new(&image) {
-                Ok(img) => eprintln!(
-                    "{}:{},{} {}",
-                    image,
-                    img.width(),
-                    img.height(),
-                    img.is_animated()
-                ),
-                Err(err) => eprintln!("{}: {:?}", image, err),
+        let images = [
+            ("testdata/test.gif", 3, true),
+            ("testdata/test.png", 1, false),
+            ("testdata/test.jpg", 1, false),
+        ];
+        for &(image, frames, animated) in &images {
+            let img = ImageBuffer::new(image).unwrap_or_else(|err| panic!("{}: {:?}", image, err));
+            assert_eq!((img.width(), img.height()), (8, 6), "{}", image);
+            assert_eq!(img.frames(), frames, "{}", image);
+            assert_eq!(img.is_animated(), animated, "{}", image);
+        }
+    }
+
+    #[test]
+    fn other_formats() {
+        let dir = TempDir::new("formats");
+
+        let rgb = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 9, 9, 9];
+        let rgba = rgb
+            .chunks(3)
+            .flat_map(|rgb| rgb.iter().cloned().chain(Some(255)))
+            .collect::<Vec<u8>>();
+        let files = [
+            ("bmp", &rgb[..], image::RGB(8)),
+            ("ppm", &rgb[..], image::RGB(8)),
+            ("ico", &rgba[..], image::RGBA(8)),
+        ];
+        for &(ext, pixels, color) in &files {
+            let path = dir.join(format!("image.{}", ext));
+            image::save_buffer(&path, pixels, 3, 2, color).unwrap();
+            let img = ImageBuffer::new(&path).unwrap_or_else(|err| panic!("{}: {:?}", ext, err));
+            assert_eq!((img.width(), img.height()), (3, 2), "{}", ext);
+            assert_eq!(img.current().buffer().get_pixel(2, 1).data, [9, 9, 9, 255]);
+        }
+
+        // 2x1, top to bottom, in bgr. there's nothing in a tga that says it's one
+        let mut tga = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 1, 0, 24, 0x20];
+        tga.extend_from_slice(&[0, 0, 255, 255, 0, 0]);
+        let path = dir.join("image.tga");
+        fs::write(&path, &tga).unwrap();
+        let img = ImageBuffer::new(&path).unwrap();
+        assert_eq!((img.width(), img.height()), (2, 1));
+        assert_eq!(img.current().buffer().get_pixel(0, 0).data, [255, 0, 0, 255]);
+
+        fs::rename(&path, dir.join("image")).unwrap();
+        assert!(ImageBuffer::new(dir.join("image")).is_err());
+    }
+
+    #[test]
+    fn step_frames() {
+        let mut img = ImageBuffer::new("testdata/test.gif").unwrap();
+        let delays = (0..img.frames())
+            .map(|_| img.next().delay().subsec_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![200, 300, 100]);
+        assert_eq!(img.index(), 0);
+
+        assert_eq!(img.repeat(), Repeat::Infinite);
+        assert_eq!(img.previous().delay().subsec_millis(), 300);
+        assert_eq!(img.index(), 2);
+        assert_eq!(
+            img.current().buffer().get_pixel(0, 0).data,
+            [0, 0, 255, 255]
+        );
+        assert_eq!(img.seek(10).delay().subsec_millis(), 300);
+    }
+
+    #[test]
+    fn orientations() {
+        // 3x2, with the red being where it started out
+        let buffer = image::RgbaImage::from_fn(3, 2, |x, y| image::Rgba {
+            data: [(y * 3 + x + 1) as u8, 0, 0, 255],
+        });
+        let expected: [(Orientation, &[&[u8]]); 8] = [
+            (Orientation::Normal, &[&[1, 2, 3], &[4, 5, 6]]),
+            (Orientation::FlipHorizontal, &[&[3, 2, 1], &[6, 5, 4]]),
+            (Orientation::Rotate180, &[&[6, 5, 4], &[3, 2, 1]]),
+            (Orientation::FlipVertical, &[&[4, 5, 6], &[1, 2, 3]]),
+            (Orientation::Transpose, &[&[1, 4], &[2, 5], &[3, 6]]),
+            (Orientation::Rotate90, &[&[4, 1], &[5, 2], &[6, 3]]),
+            (Orientation::Transverse, &[&[6, 3], &[5, 2], &[4, 1]]),
+            (Orientation::Rotate270, &[&[3, 6], &[2, 5], &[1, 4]]),
+        ];
+        for &(orientation, rows) in &expected {
+            let out = oriented(&buffer, orientation);
+            let got = (0..out.height())
+                .map(|y| (0..out.width()).map(|x| out.get_pixel(x, y).data[0]).collect())
+                .collect::<Vec<Vec<u8>>>();
+            assert_eq!(got, rows, "{:?}", orientation);
+            assert_eq!(out.width() == 2, orientation.swaps_dimensions());
+        }
+    }
+
+    #[test]
+    fn exif_orientation() {
+        let dir = TempDir::new("exif");
+        let path = dir.join("turned.jpg");
+        fs::write(&path, turned_jpeg()).unwrap();
+        let turned = ImageBuffer::new(&path).unwrap();
+        let plain = ImageBuffer::open(&path, Some(ImageFormat::JPEG), false).unwrap();
+
+        assert_eq!((turned.width(), turned.height()), (6, 8));
+        assert_eq!((plain.width(), plain.height()), (8, 6));
+        let (turned, plain) = (turned.current().buffer(), plain.current().buffer());
+        assert_eq!(turned.dimensions(), (6, 8));
+        for (x, y, pixel) in turned.enumerate_pixels() {
+            assert_eq!(pixel, plain.get_pixel(y, 5 - x), "{},{}", x, y);
+        }
+    }
+
+    // every frame of the gif at `path` against how it should look, a char per pixel:
+    // r, g, b and w for the colors, and . for see-through
+    fn golden(path: &str, delays: &[u32], expected: &[&[&str]]) {
+        let mut img = ImageBuffer::new(path).unwrap_or_else(|err| panic!("{}: {:?}", path, err));
+        assert_eq!((img.width(), img.height()), (6, 4), "{}", path);
+        assert_eq!(img.frames(), expected.len(), "{}", path);
+
+        for (index, rows) in expected.iter().enumerate() {
+            let frame = img.seek(index);
+            assert_eq!((frame.left(), frame.top()), (0, 0));
+            assert_eq!(frame.delay().subsec_millis(), delays[index], "{}", path);
+
+            let want = rows
+                .iter()
+                .map(|row| {
+                    row.chars()
+                        .map(|c| match c {
+                            'r' => [255, 0, 0, 255],
+                            'g' => [0, 255, 0, 255],
+                            'b' => [0, 0, 255, 255],
+                            'w' => [255, 255, 255, 255],
+                            _ => [0, 0, 0, 0],
+                        }).collect::<Vec<_>>()
+                }).collect::<Vec<_>>();
+            // only whether it's see-through matters, not the color it has
+            let got = pixels(frame)
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|p| if p[3] == 0 { [0; 4] } else { p })
+                        .collect::<Vec<_>>()
+                }).collect::<Vec<_>>();
+            assert_eq!(got, want, "{} frame {}", path, index);
+        }
+    }
+
+    #[test]
+    fn gif_disposal() {
+        golden(
+            "testdata/animated/dispose-background.gif",
+            &[100, 200, 300],
+            &[
+                &["rrrr..", "rrrr..", "rrrr..", "rrrr.."],
+                &["rrrr..", "rggr..", "rggr..", "rrrr.."],
+                &["rrrr..", "r..bb.", "r..bb.", "rrrr.."],
+            ],
+        );
+        golden(
+            "testdata/animated/dispose-previous.gif",
+            &[100, 200, 300, 400],
+            &[
+                &["rrrrrr", "rrrrrr", "rrrrrr", "rrrrrr"],
+                &["ggrrrr", "ggrrrr", "rrrrrr", "rrrrrr"],
+                &["ggrrrr", "ggbbbr", "rrbbbr", "rrrrrr"],
+                &["ggrrrr", "ggrrrr", "rrrrrr", "rrrrrw"],
+            ],
+        );
+    }
+
+    #[test]
+    fn gif_transparency() {
+        // the last frame hangs off the bottom right corner
+        golden(
+            "testdata/animated/transparent.gif",
+            &[100, 200, 300],
+            &[
+                &["rrrrrr", "rrrrrr", "rrrrrr", "rrrrrr"],
+                &["rrrrrr", "rrgrgr", "rgrgrr", "rrrrrr"],
+                &["rrrrrr", "rrgrgr", "rgrgbb", "rrrrbb"],
+            ],
+        );
+    }
+
+    // the colors of a whole frame, a row at a time
+    fn pixels(frame: &Frame) -> Vec<Vec<[u8; 4]>> {
+        let buffer = frame.buffer();
+        (0..buffer.height())
+            .map(|y| (0..buffer.width()).map(|x| buffer.get_pixel(x, y).data).collect())
+            .collect()
+    }
+
+    #[test]
+    fn apng_frames() {
+        let mut img = ImageBuffer::new("testdata/animated/test.apng").unwrap();
+        assert!(img.is_animated());
+        assert_eq!((img.width(), img.height(), img.frames()), (8, 6, 3));
+        assert_eq!(img.repeat(), Repeat::Finite(2));
+
+        let (red, green, blue, clear) = (
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [0, 0, 0, 0],
+        );
+        let expected = |frame: usize, x: usize, y: usize| match frame {
+            0 => red,
+            // drawn over, so the see-through corner keeps the red under it
+            1 if (x, y) == (2, 2) => red,
+            1 if (2..6).contains(&x) && (2..5).contains(&y) => green,
+            // the frame before was cleared, and this one replaces its area
+            2 if x < 2 && y < 2 => blue,
+            2 if (2..6).contains(&x) && (2..5).contains(&y) => clear,
+            _ => red,
+        };
+        for index in 0..img.frames() {
+            let frame = img.seek(index);
+            assert_eq!((frame.left(), frame.top()), (0, 0));
+            for (y, row) in pixels(frame).iter().enumerate() {
+                for (x, &pixel) in row.iter().enumerate() {
+                    assert_eq!(pixel, expected(index, x, y), "{} at {},{}", index, x, y);
+                }
             }
         }
+
+        let delays = (0..img.frames())
+            .map(|_| img.next().delay().subsec_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![200, 300, 100]);
+    }
+
+    #[test]
+    fn webp_frames() {
+        let mut img = ImageBuffer::new("testdata/animated/test.webp").unwrap();
+        assert!(img.is_animated());
+        assert_eq!((img.width(), img.height(), img.frames()), (16, 16, 3));
+        assert_eq!(img.repeat(), Repeat::Infinite);
+
+        // only the brightness is decoded, and it's lossy, so it's only about right
+        let gray = |value: u8| {
+            move |pixel: [u8; 4]| {
+                let near = |c: &u8| (i32::from(*c) - i32::from(value)).abs() <= 12;
+                pixel[3] == 255 && pixel[..3].iter().all(near)
+            }
+        };
+        let clear = |pixel: [u8; 4]| pixel[3] == 0;
+        let inside = |x: usize, y: usize, from: usize, to: usize| {
+            (from..to).contains(&x) && (from..to).contains(&y)
+        };
+
+        for index in 0..img.frames() {
+            let frame = pixels(img.seek(index));
+            for (y, row) in frame.iter().enumerate() {
+                for (x, &pixel) in row.iter().enumerate() {
+                    let ok = match index {
+                        0 => gray(200)(pixel),
+                        1 if inside(x, y, 4, 12) => gray(50)(pixel),
+                        // the last frame is see-through on its left half
+                        2 if inside(x, y, 8, 16) && x >= 12 => gray(120)(pixel),
+                        // where the frame before was cleared
+                        2 if inside(x, y, 4, 12) => clear(pixel),
+                        _ => gray(200)(pixel),
+                    };
+                    assert!(ok, "{} at {},{}: {:?}", index, x, y, pixel);
+                }
+            }
+        }
+
+        assert_eq!(img.seek(0).delay().subsec_millis(), 200);
+        assert_eq!(img.previous().delay().subsec_millis(), 100);
+        assert_eq!(img.previous().delay().subsec_millis(), 300);
     }
-}
 
-impl ImageView {
-    pub fn new(parent: windef::HWND) -> Self {
-        use winapi::um::commctrl::*;
-        use winapi::um::winuser::{WS_CHILD, WS_VISIBLE};
-        unsafe {
-            let mut rect = mem::zeroed::<windef::RECT>();
-            winuser::GetClientRect(parent, &mut rect);
+    fn viewport(image: (u32, u32), window: (i32, i32)) -> ImageViewport {
+        let mut viewport = ImageViewport::default();
+        viewport.set_window_size(window.0, window.1);
+        viewport.set_image_size(image.0, image.1);
+        viewport
+    }
+
+    #[test]
+    fn viewport_transforms() {
+        let mut vp = viewport((200, 100), (400, 400));
+        vp.set_zoom(Zoom::Actual);
+        // centered, 1:1
+        assert_eq!(vp.to_screen(0.0, 0.0), (100.0, 150.0));
+        assert_eq!(vp.to_image(100.0, 150.0), (0.0, 0.0));
+        assert_eq!(vp.to_screen(200.0, 100.0), (300.0, 250.0));
+
+        vp.set_zoom(Zoom::Fixed(2.0));
+        assert_eq!(vp.image_rect(), (0.0, 100.0, 400.0, 200.0));
+        assert_eq!(vp.to_screen(50.0, 25.0), (100.0, 150.0));
+        assert_eq!(vp.to_image(100.0, 150.0), (50.0, 25.0));
+
+        // round trips
+        for &(x, y) in &[(0.0, 0.0), (13.5, 77.25), (200.0, 100.0)] {
+            let (sx, sy) = vp.to_screen(x, y);
+            assert_eq!(vp.to_image(sx, sy), (x, y));
         }
+    }
+
+    #[test]
+    fn viewport_modes() {
+        let mut vp = viewport((200, 100), (400, 400));
+        assert_eq!(vp.zoom(), Zoom::Fit);
+        assert_eq!(vp.scale(), 2.0);
+        assert_eq!(vp.image_rect(), (0.0, 100.0, 400.0, 200.0));
+
+        vp.set_zoom(Zoom::Fill);
+        assert_eq!(vp.scale(), 4.0);
+        assert_eq!(vp.image_rect(), (-200.0, 0.0, 800.0, 400.0));
+
+        vp.set_zoom(Zoom::Fixed(1000.0));
+        assert_eq!(vp.zoom(), Zoom::Fixed(MAX_SCALE));
+
+        // fit follows the window around
+        vp.set_zoom(Zoom::Fit);
+        vp.set_window_size(100, 100);
+        assert_eq!(vp.scale(), 0.5);
+        assert_eq!(vp.image_rect(), (0.0, 25.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn viewport_zoom_at_cursor() {
+        let mut vp = viewport((400, 400), (200, 200));
+        vp.set_zoom(Zoom::Actual);
+        let before = vp.to_image(150.0, 50.0);
+
+        vp.zoom_at(2.0, (150, 50));
+        assert_eq!(vp.zoom(), Zoom::Fixed(2.0));
+        assert_eq!(vp.to_image(150.0, 50.0), before);
+        assert_eq!(vp.to_screen(before.0, before.1), (150.0, 50.0));
+    }
+
+    #[test]
+    fn viewport_panning() {
+        let mut vp = viewport((400, 400), (200, 200));
+        vp.set_zoom(Zoom::Actual);
+        assert_eq!(vp.to_screen(0.0, 0.0), (-100.0, -100.0));
+
+        assert!(!vp.pan_to((10, 10)));
+        vp.begin_pan((0, 0));
+        assert!(vp.pan_to((30, -20)));
+        assert_eq!(vp.to_screen(0.0, 0.0), (-70.0, -120.0));
+
+        // can't drag the edges into the window
+        assert!(vp.pan_to((500, 500)));
+        assert_eq!(vp.to_screen(0.0, 0.0), (0.0, 0.0));
+        assert!(!vp.pan_to((600, 600)));
+        vp.pan_to((-1000, -1000));
+        assert_eq!(vp.to_screen(400.0, 400.0), (200.0, 200.0));
+
+        vp.end_pan();
+        assert!(!vp.is_panning());
+
+        // smaller than the window stays centered
+        let mut vp = viewport((100, 50), (200, 200));
+        vp.set_zoom(Zoom::Actual);
+        vp.begin_pan((0, 0));
+        assert!(!vp.pan_to((40, 40)));
+        assert_eq!(vp.to_screen(0.0, 0.0), (50.0, 75.0));
+    }
+
+    #[test]
+    fn gif_repeat() {
+        // 1x1, with a global color table of two colors
+        let header = b"GIF89a\x01\0\x01\0\x80\0\0\0\0\0\xFF\xFF\xFF";
+        let gif = |blocks: &[&[u8]]| {
+            let mut data = header.to_vec();
+            for block in blocks {
+                data.extend_from_slice(block);
+            }
+            ImageBuffer::gif_repeat(&data)
+        };
+        let netscape = b"\x21\xFF\x0BNETSCAPE2.0\x03\x01";
+        let comment = b"\x21\xFE\x02hi\0";
+        let image = b"\x2C\0\0\0\0\x01\0\x01\0\0\x02\x02\x44\x01\0\x3B";
+
+        assert_eq!(gif(&[netscape, &[0, 0, 0], image]), Repeat::Infinite);
+        assert_eq!(gif(&[netscape, &[2, 0, 0], image]), Repeat::Finite(3));
+        assert_eq!(gif(&[comment, netscape, &[0, 1, 0]]), Repeat::Finite(257));
+        assert_eq!(gif(&[image]), Repeat::Finite(1));
+        assert_eq!(ImageBuffer::gif_repeat(b"GIF89a"), Repeat::Finite(1));
 
-        unimplemented!()
+        // cut short
+        assert_eq!(gif(&[netscape, &[0]]), Repeat::Finite(1));
+        // after the image, it's image data rather than an extension
+        assert_eq!(gif(&[image, netscape, &[0, 0, 0]]), Repeat::Finite(1));
     }
 }
 
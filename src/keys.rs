@@ -0,0 +1,519 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use event::{Key, Modifiers, MouseButton};
+
+/// Something a key can be bound to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    Previous,
+    Next,
+    ToggleFileList,
+    /// toggles whether the file list sticks to the window, and moves it there
+    SnapFileList,
+    Shuffle,
+    Slideshow,
+    NextSort,
+    ReverseSort,
+    ZoomFit,
+    ZoomFill,
+    Zoom50,
+    Zoom100,
+    Zoom150,
+    Zoom200,
+    PreviousFrame,
+    NextFrame,
+    PlayPause,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::Previous,
+        Action::Next,
+        Action::ToggleFileList,
+        Action::SnapFileList,
+        Action::Shuffle,
+        Action::Slideshow,
+        Action::NextSort,
+        Action::ReverseSort,
+        Action::ZoomFit,
+        Action::ZoomFill,
+        Action::Zoom50,
+        Action::Zoom100,
+        Action::Zoom150,
+        Action::Zoom200,
+        Action::PreviousFrame,
+        Action::NextFrame,
+        Action::PlayPause,
+    ];
+
+    /// whether holding the key down does it again. toggling back and forth doesn't help
+    pub fn repeats(self) -> bool {
+        matches!(
+            self,
+            Action::Previous | Action::Next | Action::PreviousFrame | Action::NextFrame
+        )
+    }
+}
+
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|action| action.to_string() == s)
+            .cloned()
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Action::Previous => "previous",
+            Action::Next => "next",
+            Action::ToggleFileList => "toggle-filelist",
+            Action::SnapFileList => "snap-filelist",
+            Action::Shuffle => "shuffle",
+            Action::Slideshow => "slideshow",
+            Action::NextSort => "next-sort",
+            Action::ReverseSort => "reverse-sort",
+            Action::ZoomFit => "zoom-fit",
+            Action::ZoomFill => "zoom-fill",
+            Action::Zoom50 => "zoom-50",
+            Action::Zoom100 => "zoom-100",
+            Action::Zoom150 => "zoom-150",
+            Action::Zoom200 => "zoom-200",
+            Action::PreviousFrame => "previous-frame",
+            Action::NextFrame => "next-frame",
+            Action::PlayPause => "play-pause",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// what the keys that aren't letters, digits or function keys are called in the config
+const KEY_NAMES: [(Key, &str); 20] = [
+    (Key::Space, "space"),
+    (Key::Enter, "enter"),
+    (Key::Escape, "escape"),
+    (Key::Tab, "tab"),
+    (Key::Backspace, "backspace"),
+    (Key::Delete, "delete"),
+    (Key::Insert, "insert"),
+    (Key::Home, "home"),
+    (Key::End, "end"),
+    (Key::PageUp, "pageup"),
+    (Key::PageDown, "pagedown"),
+    (Key::Up, "up"),
+    (Key::Down, "down"),
+    (Key::Left, "left"),
+    (Key::Right, "right"),
+    (Key::Minus, "minus"),
+    (Key::Plus, "plus"),
+    (Key::Comma, "comma"),
+    (Key::Period, "period"),
+    (Key::Slash, "slash"),
+];
+
+// the mouse buttons that can be bound. the others are for the window
+const BUTTON_NAMES: [(MouseButton, &str); 2] =
+    [(MouseButton::X1, "mouse-x1"), (MouseButton::X2, "mouse-x2")];
+
+/// What can be bound to an action
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(Key),
+    Button(MouseButton),
+}
+
+impl Input {
+    fn from_name(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii_alphanumeric() {
+                return Key::from_char(c).map(Input::Key);
+            }
+        }
+        if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+            return Key::FUNCTION.get(n.checked_sub(1)?).cloned().map(Input::Key);
+        }
+
+        let key = KEY_NAMES.iter().find(|(_, n)| *n == name);
+        let button = BUTTON_NAMES.iter().find(|(_, n)| *n == name);
+        key.map(|&(key, _)| Input::Key(key))
+            .or_else(|| button.map(|&(button, _)| Input::Button(button)))
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let key = match *self {
+            Input::Key(key) => key,
+            Input::Button(button) => match BUTTON_NAMES.iter().find(|(b, _)| *b == button) {
+                Some((_, name)) => return write!(f, "{}", name),
+                None => return write!(f, "{:?}", button),
+            },
+        };
+
+        if let Some(n) = Key::LETTERS.iter().position(|&k| k == key) {
+            write!(f, "{}", (b'a' + n as u8) as char)
+        } else if let Some(n) = Key::DIGITS.iter().position(|&k| k == key) {
+            write!(f, "{}", n)
+        } else if let Some(n) = Key::FUNCTION.iter().position(|&k| k == key) {
+            write!(f, "f{}", n + 1)
+        } else {
+            match KEY_NAMES.iter().find(|(k, _)| *k == key) {
+                Some((_, name)) => write!(f, "{}", name),
+                None => write!(f, "{:?}", key),
+            }
+        }
+    }
+}
+
+/// A key or a button, and the modifiers held down with it. Written like `ctrl+shift+a`
+/// or `mouse-x1`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub input: Input,
+    pub modifiers: Modifiers,
+}
+
+impl Chord {
+    pub fn new(input: Input, modifiers: Modifiers) -> Self {
+        Self { input, modifiers }
+    }
+}
+
+impl FromStr for Chord {
+    type Err = KeysError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_lowercase();
+        let mut parts = lower.split('+').map(str::trim).collect::<Vec<_>>();
+        let name = parts.pop().unwrap_or_default();
+
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            match part {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                _ => {
+                    return Err(KeysError::UnknownModifier {
+                        chord: s.to_string(),
+                        modifier: part.to_string(),
+                    })
+                }
+            }
+        }
+
+        let input = Input::from_name(name).ok_or_else(|| KeysError::UnknownKey {
+            chord: s.to_string(),
+            key: name.to_string(),
+        })?;
+        Ok(Self::new(input, modifiers))
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Modifiers { ctrl, shift, alt } = self.modifiers;
+        for (held, name) in &[(ctrl, "ctrl"), (shift, "shift"), (alt, "alt")] {
+            if *held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+/// Why the `[keys]` table couldn't be used
+#[derive(Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum KeysError {
+    UnknownKey { chord: String, key: String },
+    UnknownModifier { chord: String, modifier: String },
+    UnknownAction { chord: String, action: String },
+}
+
+impl fmt::Display for KeysError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeysError::UnknownKey { chord, key } => {
+                write!(f, "unknown key {:?} in {:?}", key, chord)
+            }
+            KeysError::UnknownModifier { chord, modifier } => write!(
+                f,
+                "unknown modifier {:?} in {:?}, expected ctrl, shift or alt",
+                modifier, chord
+            ),
+            KeysError::UnknownAction { chord, action } => {
+                write!(f, "unknown action {:?} for {:?}", action, chord)
+            }
+        }
+    }
+}
+
+/// Which key does what.
+///
+/// The `[keys]` table in the config maps chords to the names of actions, on top of the
+/// defaults. `"none"` unbinds a key, e.g.
+/// ```toml
+/// [keys]
+/// "ctrl+d" = "next"
+/// "r" = "none"
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: HashMap<Chord, Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let keys = [
+            (Key::A, Action::Previous),
+            (Key::D, Action::Next),
+            (Key::L, Action::ToggleFileList),
+            (Key::K, Action::SnapFileList),
+            (Key::R, Action::Shuffle),
+            (Key::P, Action::Slideshow),
+            (Key::S, Action::NextSort),
+            (Key::I, Action::ReverseSort),
+            (Key::Key0, Action::ZoomFit),
+            (Key::F, Action::ZoomFill),
+            (Key::Key1, Action::Zoom50),
+            (Key::Key2, Action::Zoom100),
+            (Key::Key3, Action::Zoom150),
+            (Key::Key4, Action::Zoom200),
+            (Key::Left, Action::PreviousFrame),
+            (Key::Right, Action::NextFrame),
+            (Key::Space, Action::PlayPause),
+        ];
+        let buttons = [
+            (MouseButton::X1, Action::Previous),
+            (MouseButton::X2, Action::Next),
+        ];
+
+        let keys = keys.iter().map(|&(key, action)| (Input::Key(key), action));
+        let buttons = buttons
+            .iter()
+            .map(|&(button, action)| (Input::Button(button), action));
+        let bindings = keys
+            .chain(buttons)
+            .map(|(input, action)| (Chord::new(input, Modifiers::default()), action))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl KeyBindings {
+    /// the defaults, with the `[keys]` table from the config on top
+    pub fn from_table(table: &BTreeMap<String, String>) -> Result<Self, KeysError> {
+        let mut this = Self::default();
+        for (chord, action) in table {
+            let parsed = chord.parse::<Chord>()?;
+            if action == "none" {
+                this.bindings.remove(&parsed);
+                continue;
+            }
+            let action = action.parse().map_err(|_| KeysError::UnknownAction {
+                chord: chord.clone(),
+                action: action.clone(),
+            })?;
+            this.bindings.insert(parsed, action);
+        }
+        Ok(this)
+    }
+
+    /// what `input` does with `modifiers` held. something that isn't bound with shift does
+    /// the same as it does without it
+    pub fn action(&self, input: Input, modifiers: Modifiers) -> Option<Action> {
+        if let Some(&action) = self.bindings.get(&Chord::new(input, modifiers)) {
+            return Some(action);
+        }
+        if modifiers.ctrl || modifiers.alt {
+            return None;
+        }
+        self.bindings
+            .get(&Chord::new(input, Modifiers::default()))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::{EventType, Modifiers, MouseButton};
+    use headless::Headless;
+    use testutil::{drop, key};
+    use viewer::Effect;
+
+    fn table(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn chords() {
+        let chord = "Ctrl+Shift+A".parse::<Chord>().unwrap();
+        assert_eq!(chord.input, Input::Key(Key::A));
+        assert!(chord.modifiers.ctrl && chord.modifiers.shift && !chord.modifiers.alt);
+        assert_eq!(chord.to_string(), "ctrl+shift+a");
+        for name in &["space", "7", "f12", "pagedown", "alt+mouse-x2"] {
+            assert_eq!(name.parse::<Chord>().unwrap().to_string(), *name);
+        }
+
+        assert_eq!(
+            "ctrl+f13".parse::<Chord>(),
+            Err(KeysError::UnknownKey {
+                chord: "ctrl+f13".into(),
+                key: "f13".into()
+            })
+        );
+        assert!("f0".parse::<Chord>().is_err());
+        assert!("mouse-left".parse::<Chord>().is_err());
+        assert_eq!(
+            "hyper+a".parse::<Chord>().unwrap_err().to_string(),
+            r#"unknown modifier "hyper" in "hyper+a", expected ctrl, shift or alt"#
+        );
+        assert!("".parse::<Chord>().is_err());
+        assert!("ctrl+".parse::<Chord>().is_err());
+    }
+
+    #[test]
+    fn bindings() {
+        let none = Modifiers::default();
+        let shift = Modifiers {
+            shift: true,
+            ..none
+        };
+        let ctrl = Modifiers { ctrl: true, ..none };
+        let key = Input::Key;
+
+        let keys = KeyBindings::default();
+        assert_eq!(keys.action(key(Key::D), none), Some(Action::Next));
+        assert_eq!(keys.action(key(Key::D), shift), Some(Action::Next));
+        assert_eq!(keys.action(key(Key::D), ctrl), None);
+        assert_eq!(keys.action(key(Key::Other(0)), none), None);
+        let back = Input::Button(MouseButton::X1);
+        assert_eq!(keys.action(back, none), Some(Action::Previous));
+        assert_eq!(keys.action(Input::Button(MouseButton::Left), none), None);
+
+        let keys = KeyBindings::from_table(&table(&[
+            ("ctrl+d", "previous"),
+            ("shift+space", "next-frame"),
+            ("r", "none"),
+        ]))
+        .unwrap();
+        assert_eq!(keys.action(key(Key::D), ctrl), Some(Action::Previous));
+        assert_eq!(keys.action(key(Key::Space), shift), Some(Action::NextFrame));
+        assert_eq!(keys.action(key(Key::Space), none), Some(Action::PlayPause));
+        assert_eq!(keys.action(key(Key::R), none), None);
+
+        let err = KeyBindings::from_table(&table(&[("d", "nxt")])).unwrap_err();
+        assert_eq!(err.to_string(), r#"unknown action "nxt" for "d""#);
+        for action in &Action::ALL {
+            assert_eq!(action.to_string().parse(), Ok(*action));
+        }
+    }
+
+    #[test]
+    fn keys() {
+        let mut app = Headless::new(80, 60);
+        let snap = app.context().get_snap();
+
+        app.handle(&drop("test.png"));
+        let start = app.selected().unwrap();
+        app.run(&[key(Key::D), key(Key::D)]);
+        let index = (start + 2) % 3;
+        assert_eq!(app.selected(), Some(index));
+        app.run(&[key(Key::D), key(Key::A)]);
+        assert_eq!(app.selected(), Some(index));
+        assert_eq!(app.context().get_index(), index);
+
+        app.run(&[key(Key::L), key(Key::K)]);
+        assert!(app.is_filelist_visible());
+        assert_eq!(app.context().get_snap(), !snap);
+        app.handle(&key(Key::L));
+        assert!(!app.is_filelist_visible());
+
+        // shuffling moves on, and can be walked back
+        app.handle(&key(Key::R));
+        assert!(app.context().is_shuffled());
+        let shuffled = app.selected().unwrap();
+        assert_ne!(shuffled, index);
+        app.handle(&key(Key::A));
+        assert_eq!(app.selected(), Some(index));
+        app.handle(&key(Key::D));
+        assert_eq!(app.selected(), Some(shuffled));
+        app.handle(&key(Key::R));
+        assert!(!app.context().is_shuffled());
+        assert_eq!(app.selected(), Some(shuffled));
+        assert!(app.handle(&key(Key::Other(0))).is_empty());
+    }
+
+    #[test]
+    fn rebound_keys() {
+        let mut app = Headless::new(80, 60);
+        let table = [("d", "previous"), ("a", "none"), ("k", "zoom-100")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        app.viewer_mut()
+            .set_key_bindings(KeyBindings::from_table(&table).unwrap());
+
+        app.handle(&drop("test.png"));
+        app.handle(&key(Key::D));
+        assert_eq!(app.selected(), Some(1));
+        assert!(app.handle(&key(Key::A)).is_empty());
+        assert_eq!(app.handle(&key(Key::K)), vec![Effect::Redraw]);
+        assert_eq!(app.viewer().viewport().scale(), 1.0);
+    }
+
+    #[test]
+    fn held_keys_and_buttons() {
+        let mut app = Headless::new(80, 60);
+        app.handle(&drop("test.png"));
+
+        // the side buttons go back and forward
+        let button = |button| EventType::MouseDown { button, x: 0, y: 0 };
+        app.handle(&button(MouseButton::X1));
+        assert_eq!(app.selected(), Some(1));
+        app.handle(&button(MouseButton::X2));
+        assert_eq!(app.selected(), Some(2));
+
+        // holding a key down only repeats moving around
+        let held = |key| EventType::KeyDown {
+            key,
+            modifiers: Modifiers::default(),
+            repeat: true,
+        };
+        app.handle(&held(Key::A));
+        assert_eq!(app.selected(), Some(1));
+        assert!(app.handle(&held(Key::R)).is_empty());
+        assert!(!app.context().is_shuffled());
+
+        // ctrl doesn't fall back to the bare key, and letting go doesn't do anything
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::default()
+        };
+        let event = EventType::KeyDown {
+            key: Key::A,
+            modifiers: ctrl,
+            repeat: false,
+        };
+        assert!(app.handle(&event).is_empty());
+        let event = EventType::KeyUp {
+            key: Key::A,
+            modifiers: Modifiers::default(),
+        };
+        assert!(app.handle(&event).is_empty());
+        assert_eq!(app.selected(), Some(1));
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(windows)]
 extern crate winapi;
 #[macro_use]
 extern crate log;
@@ -7,34 +8,67 @@ extern crate serde_derive;
 extern crate toml;
 #[macro_use]
 extern crate lazy_static;
+#[cfg(windows)]
 #[macro_use]
 extern crate typed_builder;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(target_os = "linux")]
+extern crate x11;
+
+extern crate gif;
 extern crate image;
 extern crate rand;
-
-mod common;
-
-mod error;
-mod util;
+extern crate regex;
 
 pub mod config;
 pub use config::Config;
 
-mod class;
-//mod control;
-mod window;
+mod util;
 
-//mod imageview;
-mod listview;
+mod args;
+mod filter;
+pub mod format;
+mod keys;
+mod scan;
+mod shuffle;
+mod slideshow;
+mod sort;
 
-mod filelist;
-mod mainwindow;
+mod animation;
+mod apng;
+mod compose;
+mod imageview;
+mod scrubber;
+mod webp;
 
-mod app;
 mod context;
+mod entry;
+pub mod exif;
+mod event;
 
-pub use app::App;
+mod render;
+mod viewer;
+mod watch;
 
-mod event;
+#[cfg(test)]
+mod headless;
+#[cfg(test)]
+mod testutil;
+
+#[cfg(windows)]
+pub mod win32;
+#[cfg(windows)]
+pub use win32::App;
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::App;
+
+#[cfg(unix)]
+pub mod tui;
+#[cfg(unix)]
+pub use tui::Tui;
 
-mod trackbar;
+pub use args::{Args, ArgsError, USAGE};
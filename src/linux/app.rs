@@ -0,0 +1,659 @@
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_uchar, c_uint, c_ulong};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{cmp, mem, ptr};
+
+use libc;
+use x11::xlib;
+
+use args::Args;
+use config::{self, Config, Position, Size};
+use context::Context;
+use event::{EventType, MouseButton};
+use linux::dnd::Dnd;
+use linux::event::translate;
+use linux::panel::{Hit, Layout, Panel, Trackbar, ROW_HEIGHT};
+use render::{Framebuffer, BACKGROUND};
+use viewer::{Effect, Viewer, ANIMATION_TIMER, WATCH_INTERVAL, WATCH_TIMER, WHEEL_DELTA};
+
+// the colors of the things we draw ourselves
+const PANEL_BACKGROUND: c_ulong = 0x00_FF_FF_FF;
+const PANEL_TEXT: c_ulong = 0x00_00_00_00;
+const PANEL_SELECTED: c_ulong = 0x00_33_66_CC;
+const PANEL_SELECTED_TEXT: c_ulong = 0x00_FF_FF_FF;
+const TRACKBAR_CHANNEL: c_ulong = 0x00_80_80_80;
+const TRACKBAR_THUMB: c_ulong = 0x00_E0_E0_E0;
+
+// how many rows a single notch of the wheel scrolls the file list by
+const SCROLL_ROWS: i32 = 3;
+
+#[derive(Debug)]
+pub struct App {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    visual: *mut xlib::Visual,
+    depth: c_int,
+    gc: xlib::GC,
+    wm_delete: xlib::Atom,
+    dnd: Dnd,
+
+    context: Arc<Mutex<Context>>,
+    viewer: Viewer,
+    framebuffer: Framebuffer,
+    panel: Panel,
+    trackbar: Trackbar,
+    layout: Layout,
+
+    held: Vec<c_uint>,      // the keycodes that are down
+    timer: Option<Instant>, // when the animation timer fires
+    watch: Instant,         // when the files are checked for changes
+    fullscreen: bool,
+    dirty: bool,
+    quit: bool,
+}
+
+impl App {
+    /// opens a window on the default display, showing what's in `args`. returns `None`
+    /// if there's no X server
+    pub fn new(args: &Args) -> Option<Self> {
+        unsafe {
+            xlib::XInitThreads();
+            let display = xlib::XOpenDisplay(ptr::null());
+            if display.is_null() {
+                error!("cannot open the X display");
+                return None;
+            }
+
+            let screen = xlib::XDefaultScreen(display);
+            let depth = xlib::XDefaultDepth(display, screen);
+            if depth != 24 && depth != 32 {
+                error!("unsupported display depth: {}", depth);
+                xlib::XCloseDisplay(display);
+                return None;
+            }
+
+            let conf = Config::get();
+            let (width, height) = (cmp::max(conf.size.w, 1), cmp::max(conf.size.h, 1));
+
+            let window = xlib::XCreateSimpleWindow(
+                display,
+                xlib::XRootWindow(display, screen),
+                conf.position.x,
+                conf.position.y,
+                width as c_uint,
+                height as c_uint,
+                0,
+                0,
+                c_ulong::from(BACKGROUND),
+            );
+            xlib::XSelectInput(
+                display,
+                window,
+                xlib::ExposureMask
+                    | xlib::KeyPressMask
+                    | xlib::KeyReleaseMask
+                    | xlib::FocusChangeMask
+                    | xlib::ButtonPressMask
+                    | xlib::ButtonReleaseMask
+                    | xlib::PointerMotionMask
+                    | xlib::StructureNotifyMask,
+            );
+
+            // held keys repeat presses without releases in between, so they can be told apart
+            let mut supported = xlib::False;
+            xlib::XkbSetDetectableAutoRepeat(display, xlib::True, &mut supported);
+            if supported == xlib::False {
+                warn!("held keys can't be told apart from presses");
+            }
+
+            let name = CString::new("WM_DELETE_WINDOW").unwrap();
+            let mut wm_delete = xlib::XInternAtom(display, name.as_ptr(), xlib::False);
+            xlib::XSetWMProtocols(display, window, &mut wm_delete, 1);
+
+            let context = Arc::new(Mutex::new(Context::new()));
+            let mut this = Self {
+                display,
+                window,
+                visual: xlib::XDefaultVisual(display, screen),
+                depth,
+                gc: xlib::XCreateGC(display, window, 0, ptr::null_mut()),
+                wm_delete,
+                dnd: Dnd::new(display, window),
+
+                viewer: Viewer::new(Arc::clone(&context)),
+                context,
+                framebuffer: Framebuffer::default(),
+                panel: Panel::default(),
+                trackbar: Trackbar::default(),
+                layout: Layout {
+                    width,
+                    height,
+                    panel: false,
+                    trackbar: false,
+                },
+
+                held: vec![],
+                timer: None,
+                watch: Instant::now() + WATCH_INTERVAL,
+                fullscreen: args.fullscreen,
+                dirty: true,
+                quit: false,
+            };
+            this.set_title("pict");
+            this.relayout();
+            if args.fullscreen {
+                this.set_fullscreen();
+            }
+            let effects = this.viewer.start(args);
+            this.apply(effects);
+
+            xlib::XMapWindow(display, window);
+            xlib::XFlush(display);
+            Some(this)
+        }
+    }
+
+    pub fn run(&mut self) {
+        while !self.quit {
+            self.pump();
+            if !self.quit {
+                self.wait();
+            }
+        }
+        self.save();
+    }
+
+    /// handles everything that's waiting, without blocking
+    pub fn pump(&mut self) {
+        unsafe {
+            while xlib::XPending(self.display) > 0 {
+                let mut ev = mem::zeroed();
+                xlib::XNextEvent(self.display, &mut ev);
+                self.dispatch(&mut ev);
+            }
+        }
+
+        if self.timer.is_some_and(|deadline| deadline <= Instant::now()) {
+            self.timer = None;
+            self.handle(&EventType::Timer {
+                id: ANIMATION_TIMER,
+            });
+        }
+
+        if self.watch <= Instant::now() {
+            self.watch = Instant::now() + WATCH_INTERVAL;
+            self.handle(&EventType::Timer { id: WATCH_TIMER });
+        }
+
+        if self.dirty {
+            self.paint();
+        }
+    }
+
+    pub fn handle(&mut self, ev: &EventType) {
+        let effects = match *ev {
+            EventType::CloseRequest | EventType::Quit => {
+                self.quit = true;
+                vec![]
+            }
+            EventType::Resize { width, height } => {
+                self.layout.width = width;
+                self.layout.height = height;
+                self.relayout();
+                vec![]
+            }
+            EventType::Paint => {
+                self.dirty = true;
+                vec![]
+            }
+            EventType::MouseDown { button, x, y } => self.on_mouse_down(button, (x, y)),
+            EventType::MouseUp { button, x, y } => self.on_mouse_up(button, (x, y)),
+            EventType::MouseMove { x, y } => self.on_mouse_move((x, y)),
+            EventType::MouseWheel { delta, x, y } => self.on_mouse_wheel(delta, (x, y)),
+            _ => self.viewer.handle(ev),
+        };
+        self.apply(effects);
+    }
+
+    fn apply(&mut self, effects: Vec<Effect>) {
+        for effect in effects {
+            match effect {
+                Effect::Select(index) => {
+                    self.panel.select(index, self.layout.height);
+                    self.dirty = true;
+                }
+                Effect::Populate { title, files } => {
+                    self.panel.populate(&title, &files);
+                    self.set_title(&format!("pict - {}", title));
+                    self.dirty = true;
+                }
+                Effect::ToggleFileList => {
+                    debug!("toggling filelist");
+                    self.panel.visible = !self.panel.visible;
+                    self.relayout();
+                }
+                // the panel is always docked to the window
+                Effect::AlignFileList => {}
+                Effect::SetFrames(max) => {
+                    self.trackbar = Trackbar {
+                        max,
+                        ..Trackbar::default()
+                    };
+                    self.layout.trackbar = max.is_some();
+                    self.dirty = true;
+                }
+                Effect::SetFramePosition(pos) => {
+                    self.trackbar.pos = pos;
+                    self.dirty = true;
+                }
+                Effect::Schedule(next) => self.timer = next.map(|delay| Instant::now() + delay),
+                Effect::Redraw => self.dirty = true,
+            }
+        }
+    }
+
+    // the image gets whatever the panel doesn't use
+    fn relayout(&mut self) {
+        self.layout.panel = self.panel.visible;
+        let (_, _, width, height) = self.layout.image_rect();
+        self.framebuffer.resize(width, height);
+
+        let effects = self.viewer.handle(&EventType::Resize { width, height });
+        self.apply(effects);
+        self.dirty = true;
+    }
+
+    // moves window coordinates into the image area
+    fn to_image(&self, pos: (i32, i32)) -> (i32, i32) {
+        let (x, y, _, _) = self.layout.image_rect();
+        (pos.0 - x, pos.1 - y)
+    }
+
+    fn on_mouse_down(&mut self, button: MouseButton, pos: (i32, i32)) -> Vec<Effect> {
+        match self.layout.hit(pos.0, pos.1, &self.panel, &self.trackbar) {
+            Hit::Panel(Some(row)) if button == MouseButton::Left => self.viewer.select(row),
+            Hit::Panel(_) => vec![],
+            Hit::Trackbar(frame) if button == MouseButton::Left => {
+                self.trackbar.dragging = true;
+                self.viewer.scrub_drag(frame)
+            }
+            _ => {
+                let (x, y) = self.to_image(pos);
+                self.viewer
+                    .handle(&EventType::MouseDown { button, x, y })
+            }
+        }
+    }
+
+    fn on_mouse_up(&mut self, button: MouseButton, pos: (i32, i32)) -> Vec<Effect> {
+        let mut effects = vec![];
+        if self.trackbar.dragging && button == MouseButton::Left {
+            self.trackbar.dragging = false;
+            effects = self.viewer.scrub_release();
+        }
+
+        let (x, y) = self.to_image(pos);
+        effects.extend(self.viewer.handle(&EventType::MouseUp { button, x, y }));
+        effects
+    }
+
+    fn on_mouse_move(&mut self, pos: (i32, i32)) -> Vec<Effect> {
+        if self.trackbar.dragging {
+            let max = self.trackbar.max.unwrap_or_default();
+            let frame = self.layout.trackbar_pos(pos.0, max);
+            return self.viewer.scrub_drag(frame);
+        }
+
+        let (x, y) = self.to_image(pos);
+        self.viewer.handle(&EventType::MouseMove { x, y })
+    }
+
+    fn on_mouse_wheel(&mut self, delta: i16, pos: (i32, i32)) -> Vec<Effect> {
+        if let Hit::Panel(_) = self.layout.hit(pos.0, pos.1, &self.panel, &self.trackbar) {
+            let rows = -i32::from(delta) / i32::from(WHEEL_DELTA) * SCROLL_ROWS;
+            self.panel.scroll_by(rows, self.layout.height);
+            self.dirty = true;
+            return vec![];
+        }
+
+        let (x, y) = self.to_image(pos);
+        self.viewer.handle(&EventType::MouseWheel { delta, x, y })
+    }
+
+    unsafe fn dispatch(&mut self, ev: &mut xlib::XEvent) {
+        match ev.get_type() {
+            xlib::ClientMessage => {
+                let msg = ev.client_message;
+                if msg.data.get_long(0) as xlib::Atom == self.wm_delete {
+                    self.handle(&EventType::CloseRequest);
+                } else {
+                    self.dnd.client_message(&msg);
+                }
+            }
+            xlib::SelectionNotify => {
+                let files = self.dnd.selection_notify(&ev.selection);
+                if !files.is_empty() {
+                    self.handle(&EventType::DropFiles { files });
+                }
+            }
+            _ => {
+                let size = (self.layout.width, self.layout.height);
+                for ev in translate(ev, size, &mut self.held) {
+                    self.handle(&ev);
+                }
+            }
+        }
+    }
+
+    // blocks until there's an event, or one of the timers is due
+    fn wait(&self) {
+        let deadline = match self.timer {
+            Some(deadline) => cmp::min(deadline, self.watch),
+            None => self.watch,
+        };
+        let now = Instant::now();
+        let left = if deadline > now {
+            deadline - now
+        } else {
+            Duration::default()
+        };
+        let ms = left.as_secs() * 1000 + u64::from(left.subsec_millis()) + 1;
+        let timeout = cmp::min(ms, c_int::MAX as u64) as c_int;
+
+        let mut fd = libc::pollfd {
+            fd: unsafe { xlib::XConnectionNumber(self.display) },
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut fd, 1, timeout) };
+    }
+
+    fn paint(&mut self) {
+        self.dirty = false;
+        self.viewer.render(&mut self.framebuffer);
+
+        let (x, y, w, h) = self.layout.image_rect();
+        let fits = self.framebuffer.width() as i32 == w && self.framebuffer.height() as i32 == h;
+        unsafe {
+            if fits && w > 0 && h > 0 {
+                let image = xlib::XCreateImage(
+                    self.display,
+                    self.visual,
+                    self.depth as c_uint,
+                    xlib::ZPixmap,
+                    0,
+                    self.framebuffer.pixels().as_ptr() as *mut c_char,
+                    w as c_uint,
+                    h as c_uint,
+                    32,
+                    0,
+                );
+                if !image.is_null() {
+                    // the pixels are native endian 0x00RRGGBB
+                    (*image).byte_order = if cfg!(target_endian = "little") {
+                        xlib::LSBFirst
+                    } else {
+                        xlib::MSBFirst
+                    };
+                    xlib::XPutImage(
+                        self.display,
+                        self.window,
+                        self.gc,
+                        image,
+                        0,
+                        0,
+                        x,
+                        y,
+                        w as c_uint,
+                        h as c_uint,
+                    );
+                    // the pixels belong to the framebuffer, so don't let X free them
+                    (*image).data = ptr::null_mut();
+                    xlib::XDestroyImage(image);
+                }
+            }
+
+            self.paint_panel();
+            self.paint_trackbar();
+            xlib::XFlush(self.display);
+        }
+    }
+
+    unsafe fn paint_panel(&self) {
+        let (x, y, w, h) = self.layout.panel_rect();
+        if w == 0 {
+            return;
+        }
+
+        self.fill(PANEL_BACKGROUND, (x, y, w, h));
+        self.text(PANEL_TEXT, (4, ROW_HEIGHT - 4), &self.panel.title);
+
+        for (row, (index, (name, _))) in self.panel.visible_rows(h).enumerate() {
+            let top = ROW_HEIGHT * (row as i32 + 1);
+            let color = if Some(index) == self.panel.selected {
+                self.fill(PANEL_SELECTED, (x, top, w, ROW_HEIGHT));
+                PANEL_SELECTED_TEXT
+            } else {
+                PANEL_TEXT
+            };
+            self.text(color, (4, top + ROW_HEIGHT - 4), name);
+        }
+    }
+
+    unsafe fn paint_trackbar(&self) {
+        let max = match self.trackbar.max {
+            Some(max) => max,
+            None => return,
+        };
+
+        let (x, y, w, h) = self.layout.trackbar_rect();
+        self.fill(TRACKBAR_CHANNEL, (x, y + h / 2 - 2, w, 4));
+        let thumb = self.layout.thumb_x(self.trackbar.pos, max);
+        self.fill(TRACKBAR_THUMB, (thumb - 4, y + 2, 8, h - 4));
+    }
+
+    unsafe fn fill(&self, color: c_ulong, (x, y, w, h): (i32, i32, i32, i32)) {
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        xlib::XSetForeground(self.display, self.gc, color);
+        xlib::XFillRectangle(
+            self.display,
+            self.window,
+            self.gc,
+            x,
+            y,
+            w as c_uint,
+            h as c_uint,
+        );
+    }
+
+    // the default font only does latin-1, so anything else gets replaced
+    unsafe fn text(&self, color: c_ulong, (x, y): (i32, i32), text: &str) {
+        let text = text
+            .chars()
+            .map(|c| if c.is_ascii() && !c.is_control() { c as u8 } else { b'?' })
+            .collect::<Vec<_>>();
+
+        xlib::XSetForeground(self.display, self.gc, color);
+        xlib::XDrawString(
+            self.display,
+            self.window,
+            self.gc,
+            x,
+            y,
+            text.as_ptr() as *const c_char,
+            text.len() as c_int,
+        );
+    }
+
+    // the window manager does the work, this just asks for it before the window is mapped
+    unsafe fn set_fullscreen(&self) {
+        let state = CString::new("_NET_WM_STATE").unwrap();
+        let fullscreen = CString::new("_NET_WM_STATE_FULLSCREEN").unwrap();
+        let state = xlib::XInternAtom(self.display, state.as_ptr(), xlib::False);
+        let fullscreen = xlib::XInternAtom(self.display, fullscreen.as_ptr(), xlib::False);
+        xlib::XChangeProperty(
+            self.display,
+            self.window,
+            state,
+            xlib::XA_ATOM,
+            32,
+            xlib::PropModeReplace,
+            &fullscreen as *const xlib::Atom as *const c_uchar,
+            1,
+        );
+    }
+
+    fn set_title(&self, title: &str) {
+        debug!("setting title {}", title);
+        let title = CString::new(title.replace('\0', "")).unwrap();
+        unsafe { xlib::XStoreName(self.display, self.window, title.as_ptr()) };
+    }
+
+    fn save(&self) {
+        // a fullscreen window would be saved as the whole screen, so keep what we had
+        let conf = Config::get();
+        let (mut x, mut y) = (conf.position.x, conf.position.y);
+        let (mut w, mut h) = (conf.size.w, conf.size.h);
+        if !self.fullscreen {
+            let mut child = 0;
+            unsafe {
+                xlib::XTranslateCoordinates(
+                    self.display,
+                    self.window,
+                    xlib::XDefaultRootWindow(self.display),
+                    0,
+                    0,
+                    &mut x,
+                    &mut y,
+                    &mut child,
+                );
+            }
+            w = self.layout.width;
+            h = self.layout.height;
+        }
+
+        Config {
+            position: Position { x, y },
+            size: Size { w, h },
+            filelist: config::FileList {
+                snap: self
+                    .context
+                    .lock()
+                    .expect("at unwrap for get snap")
+                    .get_snap(),
+            },
+            slideshow: Config::get().slideshow.clone(),
+            sort: self
+                .context
+                .lock()
+                .expect("at unwrap for get sort")
+                .get_sort(),
+            // only ever changed by hand
+            scan: Config::get().scan.clone(),
+            shuffle: Config::get().shuffle.clone(),
+            keys: Config::get().keys.clone(),
+            formats: Config::get().formats.clone(),
+            exif: Config::get().exif.clone(),
+        }.save();
+    }
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        unsafe {
+            xlib::XFreeGC(self.display, self.gc);
+            xlib::XDestroyWindow(self.display, self.window);
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::{Key, Modifiers};
+    use x11::keysym::XK_l;
+
+    // these need an X server, and are skipped without one. `xvfb-run cargo test` runs them
+    fn app() -> Option<App> {
+        ::std::env::var_os("DISPLAY").and_then(|_| App::new(&Args::default()))
+    }
+
+    unsafe fn press(app: &App, sym: c_uint) {
+        let mut ev: xlib::XEvent = mem::zeroed();
+        ev.key = xlib::XKeyEvent {
+            type_: xlib::KeyPress,
+            serial: 0,
+            send_event: xlib::True,
+            display: app.display,
+            window: app.window,
+            root: xlib::XDefaultRootWindow(app.display),
+            subwindow: 0,
+            time: xlib::CurrentTime,
+            x: 0,
+            y: 0,
+            x_root: 0,
+            y_root: 0,
+            state: 0,
+            keycode: c_uint::from(xlib::XKeysymToKeycode(app.display, c_ulong::from(sym))),
+            same_screen: xlib::True,
+        };
+        xlib::XSendEvent(app.display, app.window, xlib::False, xlib::KeyPressMask, &mut ev);
+        xlib::XSync(app.display, xlib::False);
+    }
+
+    #[test]
+    fn resize_and_keys() {
+        let mut app = match app() {
+            Some(app) => app,
+            None => return,
+        };
+
+        unsafe {
+            xlib::XResizeWindow(app.display, app.window, 300, 200);
+            xlib::XSync(app.display, xlib::False);
+        }
+        app.pump();
+        assert_eq!((app.layout.width, app.layout.height), (300, 200));
+        assert_eq!(app.framebuffer.width(), 300);
+
+        // the file list takes space away from the image
+        unsafe { press(&app, XK_l) };
+        app.pump();
+        assert!(app.panel.visible);
+        assert_eq!(app.framebuffer.width(), 100);
+    }
+
+    #[test]
+    fn drop_and_select() {
+        let mut app = match app() {
+            Some(app) => app,
+            None => return,
+        };
+
+        let file = format!("{}/testdata/test.gif", env!("CARGO_MANIFEST_DIR"));
+        app.handle(&EventType::DropFile { file });
+        app.pump();
+        assert_eq!(app.panel.files.len(), 3);
+        assert_eq!(app.trackbar.max, Some(2));
+        assert!(app.timer.is_some());
+
+        // clicking on a file in the list selects it
+        app.handle(&EventType::KeyDown {
+            key: Key::L,
+            modifiers: Modifiers::default(),
+            repeat: false,
+        });
+        app.handle(&EventType::MouseDown {
+            button: MouseButton::Left,
+            x: 10,
+            y: ROW_HEIGHT * 2 + 1,
+        });
+        assert_eq!(app.context.lock().unwrap().get_index(), 1);
+        assert_eq!(app.panel.selected, Some(1));
+        app.pump();
+    }
+}
@@ -0,0 +1,238 @@
+use std::ffi::CString;
+use std::os::raw::{c_long, c_uchar, c_ulong};
+use std::{mem, ptr, slice};
+
+use x11::xlib;
+
+// the version of the protocol we speak
+const XDND_VERSION: c_long = 5;
+
+/// Receives files that are dropped onto the window, using the XDND protocol.
+///
+/// The source tells us about the drag with client messages, and once it's dropped we
+/// ask for the `text/uri-list` of the selection. The files show up in a `SelectionNotify`
+#[derive(Debug)]
+pub struct Dnd {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    source: Option<xlib::Window>,
+
+    enter: xlib::Atom,
+    position: xlib::Atom,
+    status: xlib::Atom,
+    leave: xlib::Atom,
+    drop: xlib::Atom,
+    finished: xlib::Atom,
+    selection: xlib::Atom,
+    action_copy: xlib::Atom,
+    uri_list: xlib::Atom,
+    property: xlib::Atom,
+}
+
+unsafe fn atom(display: *mut xlib::Display, name: &str) -> xlib::Atom {
+    let name = CString::new(name).unwrap();
+    xlib::XInternAtom(display, name.as_ptr(), xlib::False)
+}
+
+impl Dnd {
+    /// marks `window` as accepting drops
+    pub unsafe fn new(display: *mut xlib::Display, window: xlib::Window) -> Self {
+        let version = XDND_VERSION as c_ulong;
+        xlib::XChangeProperty(
+            display,
+            window,
+            atom(display, "XdndAware"),
+            xlib::XA_ATOM,
+            32,
+            xlib::PropModeReplace,
+            &version as *const c_ulong as *const c_uchar,
+            1,
+        );
+
+        Self {
+            display,
+            window,
+            source: None,
+            enter: atom(display, "XdndEnter"),
+            position: atom(display, "XdndPosition"),
+            status: atom(display, "XdndStatus"),
+            leave: atom(display, "XdndLeave"),
+            drop: atom(display, "XdndDrop"),
+            finished: atom(display, "XdndFinished"),
+            selection: atom(display, "XdndSelection"),
+            action_copy: atom(display, "XdndActionCopy"),
+            uri_list: atom(display, "text/uri-list"),
+            property: atom(display, "PICT_DROP"),
+        }
+    }
+
+    /// returns whether the message was part of a drag and drop
+    pub unsafe fn client_message(&mut self, ev: &xlib::XClientMessageEvent) -> bool {
+        let data = ev.data.as_longs();
+        let source = data[0] as xlib::Window;
+
+        match ev.message_type {
+            t if t == self.enter => {
+                trace!("drag entered from {} (version {})", source, data[1] >> 24);
+                self.source = Some(source);
+            }
+            t if t == self.position => {
+                // accept the drop anywhere in the window
+                let status = [self.window as c_long, 1, 0, 0, self.action_copy as c_long];
+                self.send(source, self.status, status);
+            }
+            t if t == self.leave => {
+                trace!("drag left");
+                self.source = None;
+            }
+            t if t == self.drop => {
+                let time = data[2] as xlib::Time;
+                xlib::XConvertSelection(
+                    self.display,
+                    self.selection,
+                    self.uri_list,
+                    self.property,
+                    self.window,
+                    time,
+                );
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// the dropped data is ready. returns the files that were dropped
+    pub unsafe fn selection_notify(&mut self, ev: &xlib::XSelectionEvent) -> Vec<String> {
+        if ev.selection != self.selection {
+            return vec![];
+        }
+
+        let files = if ev.property == 0 {
+            warn!("the drop source couldn't give us a uri list");
+            vec![]
+        } else {
+            parse_uri_list(&self.read_property())
+        };
+
+        if let Some(source) = self.source.take() {
+            let accepted = !files.is_empty() as c_long;
+            let finished = [self.window as c_long, accepted, self.action_copy as c_long, 0, 0];
+            self.send(source, self.finished, finished);
+        }
+        files
+    }
+
+    unsafe fn read_property(&self) -> String {
+        let mut kind = 0;
+        let mut format = 0;
+        let mut len = 0;
+        let mut remaining = 0;
+        let mut data = ptr::null_mut();
+
+        xlib::XGetWindowProperty(
+            self.display,
+            self.window,
+            self.property,
+            0,
+            c_long::from(i32::MAX),
+            xlib::True,
+            xlib::AnyPropertyType as c_ulong,
+            &mut kind,
+            &mut format,
+            &mut len,
+            &mut remaining,
+            &mut data,
+        );
+        if data.is_null() {
+            return String::new();
+        }
+
+        let list = String::from_utf8_lossy(slice::from_raw_parts(data, len as usize)).into_owned();
+        xlib::XFree(data as *mut _);
+        list
+    }
+
+    unsafe fn send(&self, to: xlib::Window, message_type: xlib::Atom, data: [c_long; 5]) {
+        let mut ev: xlib::XEvent = mem::zeroed();
+        ev.client_message = xlib::XClientMessageEvent {
+            type_: xlib::ClientMessage,
+            serial: 0,
+            send_event: xlib::True,
+            display: self.display,
+            window: to,
+            message_type,
+            format: 32,
+            data: data.into(),
+        };
+        xlib::XSendEvent(self.display, to, xlib::False, xlib::NoEventMask, &mut ev);
+        xlib::XFlush(self.display);
+    }
+}
+
+/// the local files in a `text/uri-list`
+pub fn parse_uri_list(list: &str) -> Vec<String> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let path = line.trim_start_matches("file://");
+            if path.len() == line.len() {
+                debug!("ignoring dropped uri: {}", line);
+                return None;
+            }
+            // skip over the host, if there is one
+            let path = &path[path.find('/')?..];
+            Some(percent_decode(path))
+        }).collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    fn hex(b: u8) -> Option<u8> {
+        (b as char).to_digit(16).map(|d| d as u8)
+    }
+
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let decoded = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            hex(bytes[i + 1]).and_then(|hi| hex(bytes[i + 2]).map(|lo| hi << 4 | lo))
+        } else {
+            None
+        };
+
+        match decoded {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uri_list() {
+        let list = "# a comment\r\nfile:///home/foo/a%20b.png\r\nfile://host/tmp/c.gif\r\nhttp://example.com/d.png\r\n\r\n";
+        assert_eq!(
+            parse_uri_list(list),
+            vec!["/home/foo/a b.png".to_string(), "/tmp/c.gif".to_string()]
+        );
+        assert!(parse_uri_list("").is_empty());
+    }
+
+    #[test]
+    fn percent() {
+        assert_eq!(percent_decode("%E3%81%82.png"), "\u{3042}.png");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+}
@@ -0,0 +1,228 @@
+use std::os::raw::c_uint;
+use std::ptr;
+
+use x11::keysym::*;
+use x11::xlib;
+
+use event::{EventType, Key, Modifiers, MouseButton};
+use viewer::WHEEL_DELTA;
+
+#[allow(non_upper_case_globals)]
+pub fn key_from_keysym(sym: c_uint) -> Key {
+    match sym {
+        XK_a..=XK_z => Key::LETTERS[(sym - XK_a) as usize],
+        XK_A..=XK_Z => Key::LETTERS[(sym - XK_A) as usize],
+        XK_0..=XK_9 => Key::DIGITS[(sym - XK_0) as usize],
+        XK_KP_0..=XK_KP_9 => Key::DIGITS[(sym - XK_KP_0) as usize],
+        XK_F1..=XK_F12 => Key::FUNCTION[(sym - XK_F1) as usize],
+        XK_space => Key::Space,
+        XK_Return | XK_KP_Enter => Key::Enter,
+        XK_Escape => Key::Escape,
+        XK_Tab | XK_ISO_Left_Tab => Key::Tab,
+        XK_BackSpace => Key::Backspace,
+        XK_Delete | XK_KP_Delete => Key::Delete,
+        XK_Insert | XK_KP_Insert => Key::Insert,
+        XK_Home | XK_KP_Home => Key::Home,
+        XK_End | XK_KP_End => Key::End,
+        XK_Page_Up | XK_KP_Page_Up => Key::PageUp,
+        XK_Page_Down | XK_KP_Page_Down => Key::PageDown,
+        XK_Up | XK_KP_Up => Key::Up,
+        XK_Down | XK_KP_Down => Key::Down,
+        XK_Left | XK_KP_Left => Key::Left,
+        XK_Right | XK_KP_Right => Key::Right,
+        XK_minus | XK_KP_Subtract => Key::Minus,
+        XK_equal | XK_plus | XK_KP_Add => Key::Plus,
+        XK_comma => Key::Comma,
+        XK_period | XK_KP_Decimal => Key::Period,
+        XK_slash | XK_KP_Divide => Key::Slash,
+        n => Key::Other(n as i32),
+    }
+}
+
+/// the modifiers in the state of a key event
+pub fn modifiers_from_state(state: c_uint) -> Modifiers {
+    Modifiers {
+        ctrl: state & xlib::ControlMask != 0,
+        shift: state & xlib::ShiftMask != 0,
+        alt: state & xlib::Mod1Mask != 0,
+    }
+}
+
+/// X button numbers. 4 and 5 are the wheel, 6 and 7 are the wheel going sideways, and 8
+/// and 9 are the side buttons
+pub fn button_event(button: c_uint, pressed: bool, x: i32, y: i32) -> Option<EventType> {
+    let button = match button {
+        xlib::Button1 => MouseButton::Left,
+        xlib::Button2 => MouseButton::Middle,
+        xlib::Button3 => MouseButton::Right,
+        8 => MouseButton::X1,
+        9 => MouseButton::X2,
+        xlib::Button4 | xlib::Button5 if pressed => {
+            let delta = if button == xlib::Button4 {
+                WHEEL_DELTA
+            } else {
+                -WHEEL_DELTA
+            };
+            return Some(EventType::MouseWheel { delta, x, y });
+        }
+        _ => return None,
+    };
+
+    Some(if pressed {
+        EventType::MouseDown { button, x, y }
+    } else {
+        EventType::MouseUp { button, x, y }
+    })
+}
+
+/// turns an X event into ours. `size` is the last known size of the window, so that
+/// moves can be told apart from resizes. `held` is the keycodes that are down, so that
+/// repeats can be told apart from presses
+pub fn translate(ev: &mut xlib::XEvent, size: (i32, i32), held: &mut Vec<c_uint>) -> Vec<EventType> {
+    unsafe {
+        match ev.get_type() {
+            xlib::KeyPress => {
+                let sym = xlib::XLookupKeysym(&mut ev.key, 0);
+                let keycode = ev.key.keycode;
+                let repeat = held.contains(&keycode);
+                if !repeat {
+                    held.push(keycode);
+                }
+                let mut events = vec![EventType::KeyDown {
+                    key: key_from_keysym(sym as c_uint),
+                    modifiers: modifiers_from_state(ev.key.state),
+                    repeat,
+                }];
+
+                // what it types, in latin-1
+                let mut buf = [0u8; 8];
+                let len = xlib::XLookupString(
+                    &mut ev.key,
+                    buf.as_mut_ptr() as *mut _,
+                    buf.len() as i32,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                );
+                if len == 1 {
+                    events.push(EventType::Char {
+                        c: char::from(buf[0]),
+                    });
+                }
+                events
+            }
+            xlib::KeyRelease => {
+                let sym = xlib::XLookupKeysym(&mut ev.key, 0);
+                let keycode = ev.key.keycode;
+                held.retain(|&code| code != keycode);
+                vec![EventType::KeyUp {
+                    key: key_from_keysym(sym as c_uint),
+                    modifiers: modifiers_from_state(ev.key.state),
+                }]
+            }
+            // the releases went somewhere else
+            xlib::FocusOut => {
+                held.clear();
+                vec![]
+            }
+            xlib::ButtonPress | xlib::ButtonRelease => {
+                let ev = ev.button;
+                let pressed = ev.type_ == xlib::ButtonPress;
+                button_event(ev.button, pressed, ev.x, ev.y)
+                    .into_iter()
+                    .collect()
+            }
+            xlib::MotionNotify => vec![EventType::MouseMove {
+                x: ev.motion.x,
+                y: ev.motion.y,
+            }],
+            xlib::ConfigureNotify => {
+                let ev = ev.configure;
+                let mut events = vec![EventType::Moved { x: ev.x, y: ev.y }];
+                if (ev.width, ev.height) != size {
+                    events.push(EventType::Resize {
+                        width: ev.width,
+                        height: ev.height,
+                    });
+                }
+                events
+            }
+            // only the last of a run of exposes
+            xlib::Expose if ev.expose.count == 0 => vec![EventType::Paint],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        assert_eq!(key_from_keysym(XK_space), Key::Space);
+        assert_eq!(key_from_keysym(XK_d), Key::D);
+        assert_eq!(key_from_keysym(XK_D), Key::D);
+        assert_eq!(key_from_keysym(XK_4), Key::Key4);
+        assert_eq!(key_from_keysym(XK_KP_4), Key::Key4);
+        assert_eq!(key_from_keysym(XK_z), Key::Z);
+        assert_eq!(key_from_keysym(XK_F12), Key::F12);
+        assert_eq!(key_from_keysym(XK_Escape), Key::Escape);
+        assert_eq!(key_from_keysym(XK_Page_Down), Key::PageDown);
+        assert_eq!(key_from_keysym(XK_equal), Key::Plus);
+        assert_eq!(key_from_keysym(XK_F13), Key::Other(XK_F13 as i32));
+    }
+
+    #[test]
+    fn modifiers() {
+        assert_eq!(modifiers_from_state(0), Modifiers::default());
+        let state = xlib::ControlMask | xlib::Mod1Mask | xlib::LockMask;
+        assert_eq!(
+            modifiers_from_state(state),
+            Modifiers {
+                ctrl: true,
+                shift: false,
+                alt: true,
+            }
+        );
+    }
+
+    #[test]
+    fn buttons() {
+        assert_eq!(
+            button_event(xlib::Button2, true, 1, 2),
+            Some(EventType::MouseDown {
+                button: MouseButton::Middle,
+                x: 1,
+                y: 2
+            })
+        );
+        assert_eq!(
+            button_event(xlib::Button1, false, 1, 2),
+            Some(EventType::MouseUp {
+                button: MouseButton::Left,
+                x: 1,
+                y: 2
+            })
+        );
+        assert_eq!(
+            button_event(xlib::Button5, true, 3, 4),
+            Some(EventType::MouseWheel {
+                delta: -WHEEL_DELTA,
+                x: 3,
+                y: 4
+            })
+        );
+        // the wheel doesn't release
+        assert_eq!(button_event(xlib::Button4, false, 0, 0), None);
+        assert_eq!(
+            button_event(9, true, 0, 0),
+            Some(EventType::MouseDown {
+                button: MouseButton::X2,
+                x: 0,
+                y: 0
+            })
+        );
+        assert_eq!(button_event(6, true, 0, 0), None);
+        assert_eq!(button_event(12, false, 0, 0), None);
+    }
+}
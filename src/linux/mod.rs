@@ -0,0 +1,13 @@
+//! The X11 frontend. Wayland sessions get it through XWayland
+//!
+//! Everything is drawn on the CPU: the image through the shared `Framebuffer`, and the
+//! file list and trackbar with plain Xlib calls.
+mod dnd;
+mod event;
+mod panel;
+
+mod app;
+
+pub use self::app::*;
+pub use self::event::*;
+pub use self::panel::*;
@@ -0,0 +1,250 @@
+use std::cmp;
+
+/// How wide the file list is, when it's shown
+pub const PANEL_WIDTH: i32 = 200;
+/// The height of a row in the file list. the first row is the title
+pub const ROW_HEIGHT: i32 = 16;
+/// The trackbar sits along the bottom of the image
+pub const TRACKBAR_HEIGHT: i32 = 20;
+const TRACKBAR_MARGIN: i32 = 10;
+
+/// What's under a point in the window
+#[derive(Debug, PartialEq)]
+pub enum Hit {
+    /// a row of the file list, if there's a file there
+    Panel(Option<usize>),
+    /// a position on the trackbar
+    Trackbar(usize),
+    /// a point relative to the image area
+    Image(i32, i32),
+}
+
+/// The file list, drawn by us along the left side of the window
+#[derive(Debug, Default)]
+pub struct Panel {
+    pub visible: bool,
+    pub title: String,
+    pub files: Vec<(String, usize)>,
+    pub selected: Option<usize>,
+    scroll: usize, // the first row that's shown
+}
+
+impl Panel {
+    pub fn populate(&mut self, title: &str, files: &[(String, usize)]) {
+        debug!("populating ({}) panel from {}", files.len(), title);
+        self.title = title.to_string();
+        self.files = files.to_vec();
+        self.selected = None;
+        self.scroll = 0;
+    }
+
+    /// selects `index` and scrolls it into view, for a window that's `height` tall
+    pub fn select(&mut self, index: usize, height: i32) {
+        debug!("selecting index: {}", index);
+        self.selected = Some(index);
+
+        let rows = Self::rows(height);
+        if index < self.scroll {
+            self.scroll = index;
+        } else if index >= self.scroll + rows {
+            self.scroll = index + 1 - rows;
+        }
+    }
+
+    /// scrolls by `delta` rows, negative is up
+    pub fn scroll_by(&mut self, delta: i32, height: i32) {
+        let max = self.files.len().saturating_sub(Self::rows(height));
+        let scroll = self.scroll as i64 + i64::from(delta);
+        self.scroll = cmp::min(cmp::max(scroll, 0) as usize, max);
+    }
+
+    /// the files that are shown, with their index
+    pub fn visible_rows(&self, height: i32) -> impl Iterator<Item = (usize, &(String, usize))> {
+        self.files
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(Self::rows(height))
+    }
+
+    fn row_at(&self, y: i32) -> Option<usize> {
+        if y < ROW_HEIGHT {
+            return None; // the title
+        }
+        let row = self.scroll + ((y - ROW_HEIGHT) / ROW_HEIGHT) as usize;
+        if row < self.files.len() {
+            Some(row)
+        } else {
+            None
+        }
+    }
+
+    // how many files fit under the title
+    fn rows(height: i32) -> usize {
+        cmp::max((height - ROW_HEIGHT) / ROW_HEIGHT, 1) as usize
+    }
+}
+
+/// The frame trackbar for animations
+#[derive(Debug, Default)]
+pub struct Trackbar {
+    /// the range is `0..=max`. `None` when it's hidden
+    pub max: Option<usize>,
+    pub pos: usize,
+    pub dragging: bool,
+}
+
+/// Where everything goes in a window of a given size
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Layout {
+    pub width: i32,
+    pub height: i32,
+    pub panel: bool,
+    pub trackbar: bool,
+}
+
+impl Layout {
+    /// (x, y, w, h)
+    pub fn panel_rect(&self) -> (i32, i32, i32, i32) {
+        if !self.panel {
+            return (0, 0, 0, 0);
+        }
+        (0, 0, cmp::min(PANEL_WIDTH, self.width), self.height)
+    }
+
+    /// (x, y, w, h)
+    pub fn image_rect(&self) -> (i32, i32, i32, i32) {
+        let (_, _, panel, _) = self.panel_rect();
+        (panel, 0, self.width - panel, self.height)
+    }
+
+    /// (x, y, w, h). this covers the bottom of the image, like the win32 trackbar
+    pub fn trackbar_rect(&self) -> (i32, i32, i32, i32) {
+        if !self.trackbar {
+            return (0, 0, 0, 0);
+        }
+        let (x, _, w, h) = self.image_rect();
+        (
+            x + TRACKBAR_MARGIN,
+            h - TRACKBAR_HEIGHT,
+            cmp::max(w - 2 * TRACKBAR_MARGIN, 0),
+            TRACKBAR_HEIGHT,
+        )
+    }
+
+    /// the trackbar position under `x`, for a range of `0..=max`
+    pub fn trackbar_pos(&self, x: i32, max: usize) -> usize {
+        let (left, _, w, _) = self.trackbar_rect();
+        if w <= 0 || max == 0 {
+            return 0;
+        }
+        let t = f64::from(x - left) / f64::from(w);
+        (t.clamp(0.0, 1.0) * max as f64).round() as usize
+    }
+
+    /// where the thumb for `pos` is drawn
+    pub fn thumb_x(&self, pos: usize, max: usize) -> i32 {
+        let (left, _, w, _) = self.trackbar_rect();
+        if max == 0 {
+            return left;
+        }
+        left + (f64::from(w) * pos as f64 / max as f64).round() as i32
+    }
+
+    pub fn hit(&self, x: i32, y: i32, panel: &Panel, trackbar: &Trackbar) -> Hit {
+        let contains = |(rx, ry, rw, rh): (i32, i32, i32, i32)| {
+            x >= rx && x < rx + rw && y >= ry && y < ry + rh
+        };
+
+        if contains(self.panel_rect()) {
+            return Hit::Panel(panel.row_at(y));
+        }
+        if let Some(max) = trackbar.max {
+            if contains(self.trackbar_rect()) {
+                return Hit::Trackbar(self.trackbar_pos(x, max));
+            }
+        }
+        let (ix, iy, _, _) = self.image_rect();
+        Hit::Image(x - ix, y - iy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn panel(n: usize) -> Panel {
+        let mut panel = Panel::default();
+        let files = (0..n)
+            .map(|i| (format!("{}.png", i), i))
+            .collect::<Vec<_>>();
+        panel.populate("dir", &files);
+        panel
+    }
+
+    #[test]
+    fn layout() {
+        let mut layout = Layout {
+            width: 800,
+            height: 600,
+            panel: false,
+            trackbar: false,
+        };
+        assert_eq!(layout.image_rect(), (0, 0, 800, 600));
+        assert_eq!(layout.trackbar_rect(), (0, 0, 0, 0));
+
+        layout.panel = true;
+        layout.trackbar = true;
+        assert_eq!(layout.panel_rect(), (0, 0, 200, 600));
+        assert_eq!(layout.image_rect(), (200, 0, 600, 600));
+        assert_eq!(layout.trackbar_rect(), (210, 580, 580, 20));
+
+        assert_eq!(layout.trackbar_pos(210, 10), 0);
+        assert_eq!(layout.trackbar_pos(500, 10), 5);
+        assert_eq!(layout.trackbar_pos(1000, 10), 10);
+        assert_eq!(layout.thumb_x(10, 10), 790);
+    }
+
+    #[test]
+    fn hits() {
+        let layout = Layout {
+            width: 800,
+            height: 600,
+            panel: true,
+            trackbar: true,
+        };
+        let panel = panel(3);
+        let mut trackbar = Trackbar::default();
+
+        assert_eq!(layout.hit(10, 5, &panel, &trackbar), Hit::Panel(None));
+        assert_eq!(layout.hit(10, 20, &panel, &trackbar), Hit::Panel(Some(0)));
+        assert_eq!(layout.hit(10, 50, &panel, &trackbar), Hit::Panel(Some(2)));
+        assert_eq!(layout.hit(10, 70, &panel, &trackbar), Hit::Panel(None));
+
+        // the trackbar only gets hit when it's shown
+        assert_eq!(layout.hit(500, 590, &panel, &trackbar), Hit::Image(300, 590));
+        trackbar.max = Some(10);
+        assert_eq!(layout.hit(500, 590, &panel, &trackbar), Hit::Trackbar(5));
+    }
+
+    #[test]
+    fn scrolling() {
+        // the title and 4 rows
+        let height = ROW_HEIGHT * 5;
+        let mut panel = panel(10);
+        assert_eq!(panel.visible_rows(height).count(), 4);
+
+        panel.select(6, height);
+        let rows = panel.visible_rows(height).map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(rows, vec![3, 4, 5, 6]);
+        assert_eq!(panel.row_at(ROW_HEIGHT), Some(3));
+
+        panel.select(1, height);
+        assert_eq!(panel.visible_rows(height).next().unwrap().0, 1);
+
+        panel.scroll_by(100, height);
+        assert_eq!(panel.visible_rows(height).next().unwrap().0, 6);
+        panel.scroll_by(-100, height);
+        assert_eq!(panel.visible_rows(height).next().unwrap().0, 0);
+    }
+}
@@ -0,0 +1,156 @@
+use imageview::{Frame, ImageViewport};
+
+/// What's drawn behind (and around) the image, as `0x00RRGGBB`
+pub const BACKGROUND: u32 = 0x00_20_20_20;
+
+/// An in-memory window. Pixels are `0x00RRGGBB`, row by row from the top left
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: i32, height: i32) -> Self {
+        let mut this = Self::default();
+        this.resize(width, height);
+        this
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.pixels[y * self.width + x])
+    }
+
+    /// resizing throws away whatever was drawn
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.width = width.max(0) as usize;
+        self.height = height.max(0) as usize;
+        self.pixels = vec![BACKGROUND; self.width * self.height];
+    }
+
+    pub fn clear(&mut self) {
+        for px in &mut self.pixels {
+            *px = BACKGROUND
+        }
+    }
+
+    /// draws `frame` where the viewport puts it, with nearest neighbor sampling.
+    /// transparent pixels are blended with the background
+    pub fn draw(&mut self, frame: &Frame, viewport: &ImageViewport) {
+        self.clear();
+
+        let buffer = frame.buffer();
+        let (left, top) = (f64::from(frame.left()), f64::from(frame.top()));
+        let (w, h) = (f64::from(buffer.width()), f64::from(buffer.height()));
+
+        // only visit the part of the window that the frame covers
+        let (x0, y0) = viewport.to_screen(left, top);
+        let (x1, y1) = viewport.to_screen(left + w, top + h);
+        let span = |a: f64, b: f64, max: usize| {
+            let a = a.floor().max(0.0).min(max as f64) as usize;
+            let b = b.ceil().max(0.0).min(max as f64) as usize;
+            a..b
+        };
+
+        for y in span(y0, y1, self.height) {
+            for x in span(x0, x1, self.width) {
+                // sample from the middle of the screen pixel
+                let (ix, iy) = viewport.to_image(x as f64 + 0.5, y as f64 + 0.5);
+                let (fx, fy) = ((ix - left).floor(), (iy - top).floor());
+                if fx < 0.0 || fy < 0.0 || fx >= w || fy >= h {
+                    continue;
+                }
+
+                let px = buffer.get_pixel(fx as u32, fy as u32).data;
+                self.pixels[y * self.width + x] = blend(px, BACKGROUND);
+            }
+        }
+    }
+
+    /// mixes what was drawn with `from`, `amount` of the way from it. nothing happens if
+    /// they aren't the same size
+    pub fn fade(&mut self, from: &Framebuffer, amount: f64) {
+        if (from.width, from.height) != (self.width, self.height) {
+            return;
+        }
+        let alpha = (amount.clamp(0.0, 1.0) * 255.0).round() as u8;
+        for (px, &from) in self.pixels.iter_mut().zip(&from.pixels) {
+            let [_, r, g, b] = px.to_be_bytes();
+            *px = blend([r, g, b, alpha], from);
+        }
+    }
+}
+
+// straight alpha `src` over an opaque `dst`
+fn blend(src: [u8; 4], dst: u32) -> u32 {
+    let alpha = u32::from(src[3]);
+    let mix = |s: u8, shift: u32| {
+        let d = (dst >> shift) & 0xFF;
+        (u32::from(s) * alpha + d * (255 - alpha) + 127) / 255
+    };
+    mix(src[0], 16) << 16 | mix(src[1], 8) << 8 | mix(src[2], 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn blending() {
+        assert_eq!(blend([0x12, 0x34, 0x56, 255], BACKGROUND), 0x00_12_34_56);
+        assert_eq!(blend([0xFF, 0xFF, 0xFF, 0], BACKGROUND), BACKGROUND);
+        assert_eq!(blend([0xFF, 0x00, 0x00, 128], 0), 0x00_80_00_00);
+    }
+
+    #[test]
+    fn fading() {
+        let mut from = Framebuffer::new(2, 1);
+        from.pixels_mut()[0] = 0x00_FF_FF_FF;
+        let mut fb = Framebuffer::new(2, 1);
+        fb.pixels_mut()[0] = 0;
+
+        let mut half = fb.clone();
+        half.fade(&from, 0.5);
+        assert_eq!(half.pixels(), &[0x00_7F_7F_7F, BACKGROUND]);
+        let mut done = fb.clone();
+        done.fade(&from, 1.0);
+        assert_eq!(done, fb);
+
+        // a different size is left alone
+        let mut small = Framebuffer::new(1, 1);
+        small.fade(&from, 0.0);
+        assert_eq!(small.pixels(), &[BACKGROUND]);
+    }
+
+    #[test]
+    fn resize_clears() {
+        let mut fb = Framebuffer::new(4, 3);
+        assert_eq!(fb.pixels().len(), 12);
+        assert_eq!(fb.pixel(3, 2), Some(BACKGROUND));
+        assert_eq!(fb.pixel(4, 0), None);
+
+        fb.resize(-1, 10);
+        assert_eq!(fb.width(), 0);
+        assert!(fb.pixels().is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use entry::Entry;
+use filter::glob_match;
+
+/// How a directory is listed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// look in the directories below it too
+    pub recursive: bool,
+    /// how many directories down to go, when recursive. no limit without one
+    pub max_depth: Option<usize>,
+    /// list files and directories that start with a `.`
+    pub hidden: bool,
+    /// go into directories that are symlinks. files that are symlinks are always listed
+    pub follow_symlinks: bool,
+    /// globs for the names (or the paths, relative to the directory) to leave out
+    pub ignore: Vec<String>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            max_depth: None,
+            hidden: true,
+            follow_symlinks: false,
+            ignore: vec![],
+        }
+    }
+}
+
+impl ScanOptions {
+    fn max_depth(&self) -> usize {
+        match (self.recursive, self.max_depth) {
+            (false, _) => 0,
+            (true, Some(depth)) => depth,
+            (true, None) => usize::MAX,
+        }
+    }
+
+    fn is_ignored(&self, name: &str, relative: &Path) -> bool {
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        self.ignore
+            .iter()
+            .any(|glob| glob_match(glob, name) || glob_match(glob, &relative))
+    }
+}
+
+/// What a scan found
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub entries: Vec<Entry>,
+    /// the files that aren't images
+    pub others: Vec<Entry>,
+    /// the directories that were listed, starting with the one that was asked for
+    pub dirs: Vec<PathBuf>,
+    /// what couldn't be read, and why
+    pub skipped: Vec<(PathBuf, io::Error)>,
+}
+
+/// lists the images in `dir`, and in the directories below it if `options` says so. files
+/// are looked into to see whether they're images, whatever they're named. only `dir` itself
+/// not being readable is an error, anything below it that can't be read is skipped
+#[cfg(test)]
+pub fn scan(dir: &Path, options: &ScanOptions) -> io::Result<Scan> {
+    rescan(dir, options, &HashMap::new())
+}
+
+/// like `scan`, but the files in `known` that haven't changed since are taken from there,
+/// instead of being looked into again
+pub fn rescan(
+    dir: &Path,
+    options: &ScanOptions,
+    known: &HashMap<PathBuf, Entry>,
+) -> io::Result<Scan> {
+    debug!("scanning directory: {:?} {:?}", dir, options);
+    let rd = fs::read_dir(dir)?;
+
+    let mut walker = Walker {
+        root: dir,
+        options,
+        known,
+        visited: HashSet::new(),
+        scan: Scan::default(),
+    };
+    if options.follow_symlinks {
+        if let Ok(dir) = dir.canonicalize() {
+            walker.visited.insert(dir);
+        }
+    }
+    walker.scan.dirs.push(dir.to_path_buf());
+    walker.walk(dir, rd, 0);
+
+    let scan = walker.scan;
+    for (path, err) in &scan.skipped {
+        warn!("skipped {:?}: {}", path, err);
+    }
+    debug!(
+        "found {} files, skipped {}",
+        scan.entries.len(),
+        scan.skipped.len()
+    );
+    Ok(scan)
+}
+
+struct Walker<'a> {
+    root: &'a Path,
+    options: &'a ScanOptions,
+    known: &'a HashMap<PathBuf, Entry>,
+    visited: HashSet<PathBuf>, // the directories that were followed into
+    scan: Scan,
+}
+
+impl<'a> Walker<'a> {
+    fn walk(&mut self, dir: &Path, rd: fs::ReadDir, depth: usize) {
+        for entry in rd {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    self.scan.skipped.push((dir.to_path_buf(), err));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = path.strip_prefix(self.root).unwrap_or(&path).to_path_buf();
+
+            if !self.options.hidden && name.starts_with('.') {
+                continue;
+            }
+            if self.options.is_ignored(&name, &relative) {
+                trace!("ignoring: {:?}", relative);
+                continue;
+            }
+
+            // a symlink is looked at as what it points to
+            let link = entry.file_type().map(|ty| ty.is_symlink()).unwrap_or(false);
+            let md = if link {
+                fs::metadata(&path)
+            } else {
+                entry.metadata()
+            };
+            let md = match md {
+                Ok(md) => md,
+                Err(err) => {
+                    self.scan.skipped.push((path, err));
+                    continue;
+                }
+            };
+
+            if md.is_dir() {
+                if depth < self.options.max_depth() && self.should_follow(&path, link) {
+                    match fs::read_dir(&path) {
+                        Ok(rd) => {
+                            self.scan.dirs.push(path.clone());
+                            self.walk(&path, rd, depth + 1)
+                        }
+                        Err(err) => self.scan.skipped.push((path, err)),
+                    }
+                }
+            } else {
+                // the format it's sniffed as stays with the entry
+                let mut entry = Entry::with_metadata(path, &md);
+                if let Some(known) = self.known.get(entry.path()) {
+                    if known.is_unchanged(&entry) {
+                        entry = known.clone();
+                    }
+                }
+                if !entry.is_image() {
+                    trace!("not an image: {:?}", relative);
+                    self.scan.others.push(entry);
+                    continue;
+                }
+                if depth > 0 {
+                    entry.set_relative(relative);
+                }
+                self.scan.entries.push(entry);
+            }
+        }
+    }
+
+    // symlinks only when asked to, and never the same directory twice
+    fn should_follow(&mut self, path: &Path, link: bool) -> bool {
+        if !self.options.follow_symlinks {
+            return !link;
+        }
+        match path.canonicalize() {
+            Ok(path) => self.visited.insert(path),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::ImageFormat;
+    use testutil::TempDir;
+
+    fn touch(dir: &Path, file: &str) {
+        copy(dir, "test.png", file)
+    }
+
+    fn copy(dir: &Path, from: &str, file: &str) {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::copy(Path::new("testdata").join(from), path).unwrap();
+    }
+
+    fn names(scan: &Scan) -> Vec<String> {
+        let mut names = scan
+            .entries
+            .iter()
+            .map(|entry| entry.display_name().replace('\\', "/"))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn recursive() {
+        let dir = TempDir::new("scan-recursive");
+        for file in &["a.png", ".hidden.png", "one/b.png", "one/two/c.png"] {
+            touch(&dir, file);
+        }
+        fs::write(dir.join("b.txt"), "not an image").unwrap();
+        touch(&dir, "skip/d.png");
+
+        let mut options = ScanOptions::default();
+        let found = scan(&dir, &options).unwrap();
+        assert_eq!(names(&found), vec![".hidden.png", "a.png"]);
+        assert!(found.entries.iter().all(|e| e.path().starts_with(&dir)));
+
+        options.recursive = true;
+        options.hidden = false;
+        options.ignore = vec!["skip".into()];
+        let found = scan(&dir, &options).unwrap();
+        assert_eq!(names(&found), vec!["a.png", "one/b.png", "one/two/c.png"]);
+        assert_eq!(found.entries[0].size(), found.entries[1].size());
+        assert_eq!(found.dirs.len(), 3);
+        assert_eq!(found.dirs[0], *dir);
+
+        options.max_depth = Some(1);
+        options.ignore = vec!["*/b.png".into(), "skip".into()];
+        assert_eq!(names(&scan(&dir, &options).unwrap()), vec!["a.png"]);
+
+        assert!(scan(&dir.join("missing"), &options).is_err());
+    }
+
+    #[test]
+    fn sniffed() {
+        let dir = TempDir::new("scan-sniffed");
+        copy(&dir, "test.png", "no-extension");
+        copy(&dir, "test.png", "really-a-png.jpg");
+        copy(&dir, "test.png", "still.apng");
+        copy(&dir, "test.jpg", "photo.jfif");
+        fs::write(dir.join("fake.png"), "not an image").unwrap();
+        fs::write(dir.join("empty.gif"), "").unwrap();
+
+        let found = scan(&dir, &ScanOptions::default()).unwrap();
+        assert_eq!(
+            names(&found),
+            vec!["no-extension", "photo.jfif", "really-a-png.jpg", "still.apng"]
+        );
+        assert!(found
+            .entries
+            .iter()
+            .filter(|e| e.name() != "photo.jfif")
+            .all(|e| e.format() == Some(ImageFormat::PNG)));
+    }
+
+    #[test]
+    fn known() {
+        let dir = TempDir::new("scan-known");
+        touch(&dir, "a.png");
+        touch(&dir, "b.png");
+        fs::write(dir.join("c.txt"), "not an image").unwrap();
+
+        let found = scan(&dir, &ScanOptions::default()).unwrap();
+        assert_eq!(found.others.len(), 1);
+        let known = found
+            .entries
+            .into_iter()
+            .map(|entry| (entry.path().to_path_buf(), entry))
+            .collect::<HashMap<_, _>>();
+        for entry in known.values() {
+            entry.set_dimensions(1, 2);
+        }
+
+        // what was read is kept, unless the file changed
+        copy(&dir, "test.jpg", "b.png");
+        let found = rescan(&dir, &ScanOptions::default(), &known).unwrap();
+        let dimensions = |name| {
+            let entry = found.entries.iter().find(|e| e.name() == name).unwrap();
+            entry.dimensions()
+        };
+        assert_eq!(dimensions("a.png"), Some((1, 2)));
+        assert_eq!(dimensions("b.png"), Some((8, 6)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new("scan-symlinks");
+        touch(&dir, "real/a.png");
+        symlink(dir.join("real"), dir.join("link")).unwrap();
+        symlink(dir.join("real/a.png"), dir.join("b.png")).unwrap();
+        // a loop, and a link to nothing
+        symlink(&dir, dir.join("real/up")).unwrap();
+        symlink(dir.join("missing.png"), dir.join("broken.png")).unwrap();
+
+        let mut options = ScanOptions {
+            recursive: true,
+            ..ScanOptions::default()
+        };
+        let found = scan(&dir, &options).unwrap();
+        assert_eq!(names(&found), vec!["b.png", "real/a.png"]);
+        assert!(found.entries.iter().all(|e| e.size() > 0));
+        assert_eq!(found.skipped.len(), 1);
+        assert_eq!(found.skipped[0].0, dir.join("broken.png"));
+
+        options.follow_symlinks = true;
+        let found = names(&scan(&dir, &options).unwrap());
+        // one way or the other into `real`, but only once
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&"b.png".to_string()));
+    }
+}
@@ -0,0 +1,137 @@
+use std::cmp;
+
+/// The position and range behind the frame trackbar.
+///
+/// The trackbar follows playback, except while the user is dragging it, in
+/// which case the playback follows the trackbar.
+#[derive(Debug, Default)]
+pub struct Scrubber {
+    frames: usize,
+    position: usize,
+    dragging: bool,
+    resume: bool, // whether playback should resume once the drag is over
+}
+
+impl Scrubber {
+    /// resets the scrubber for an image with `frames` frames
+    pub fn set_frames(&mut self, frames: usize) {
+        trace!("scrubber frames: {}", frames);
+        *self = Self {
+            frames,
+            ..Self::default()
+        }
+    }
+
+    /// stills don't get a trackbar
+    pub fn is_visible(&self) -> bool {
+        self.frames > 1
+    }
+
+    /// the last position, the range is `0..=max`
+    pub fn max(&self) -> usize {
+        self.frames.saturating_sub(1)
+    }
+
+    /// playback moved to `frame`. returns the new position if the thumb should be moved
+    pub fn follow(&mut self, frame: usize) -> Option<usize> {
+        if self.dragging {
+            return None;
+        }
+        self.move_to(frame)
+    }
+
+    /// the thumb is being dragged to `pos`. returns the frame to seek to, if it changed
+    ///
+    /// `playing` is whether the animation was playing when this was called
+    pub fn drag(&mut self, pos: usize, playing: bool) -> Option<usize> {
+        if !self.dragging {
+            self.dragging = true;
+            self.resume = playing;
+        }
+        self.move_to(pos)
+    }
+
+    /// the drag is over. returns whether playback should be resumed
+    pub fn release(&mut self) -> bool {
+        let resume = self.dragging && self.resume;
+        self.dragging = false;
+        self.resume = false;
+        resume
+    }
+
+    /// the thumb was moved without dragging (keyboard, clicking the channel).
+    /// returns the frame to seek to, if it changed
+    #[cfg(any(windows, test))]
+    pub fn seek(&mut self, pos: usize) -> Option<usize> {
+        self.move_to(pos)
+    }
+
+    fn move_to(&mut self, pos: usize) -> Option<usize> {
+        let pos = cmp::min(pos, self.max());
+        if pos == self.position {
+            return None;
+        }
+        self.position = pos;
+        Some(pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn range() {
+        let mut scrubber = Scrubber::default();
+        assert!(!scrubber.is_visible());
+
+        scrubber.set_frames(1);
+        assert!(!scrubber.is_visible());
+        assert_eq!(scrubber.max(), 0);
+
+        scrubber.set_frames(10);
+        assert!(scrubber.is_visible());
+        assert_eq!(scrubber.max(), 9);
+        assert_eq!(scrubber.seek(20), Some(9));
+        assert_eq!(scrubber.seek(9), None);
+
+        // a new image starts over
+        scrubber.set_frames(3);
+        assert_eq!(scrubber.position, 0);
+    }
+
+    #[test]
+    fn follows_playback() {
+        let mut scrubber = Scrubber::default();
+        scrubber.set_frames(5);
+        assert_eq!(scrubber.follow(1), Some(1));
+        assert_eq!(scrubber.follow(1), None);
+        assert_eq!(scrubber.follow(4), Some(4));
+        assert_eq!(scrubber.position, 4);
+    }
+
+    #[test]
+    fn dragging() {
+        let mut scrubber = Scrubber::default();
+        scrubber.set_frames(5);
+
+        assert_eq!(scrubber.drag(3, true), Some(3));
+        assert!(scrubber.dragging);
+        // playing state is only captured at the start of the drag
+        assert_eq!(scrubber.drag(2, false), Some(2));
+        assert_eq!(scrubber.drag(2, false), None);
+
+        // playback doesn't move the thumb while it's held
+        assert_eq!(scrubber.follow(4), None);
+        assert_eq!(scrubber.position, 2);
+
+        assert!(scrubber.release());
+        assert!(!scrubber.dragging);
+        assert_eq!(scrubber.follow(4), Some(4));
+
+        // a paused animation stays paused
+        scrubber.drag(0, false);
+        assert!(!scrubber.release());
+        assert!(!scrubber.release());
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use rand::prelude::*;
+use rand::prng::ChaChaRng;
+
+/// How the list is shuffled
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShuffleOptions {
+    /// the same seed plays the same list in the same order. a new order every time without one
+    pub seed: Option<u64>,
+}
+
+/// A random order through the list, that goes through every file once before any of
+/// them come around again, and can be walked back through.
+///
+/// It holds paths rather than positions, so it doesn't mind the list being sorted,
+/// filtered or listed again. Files that aren't shown anymore are skipped, and files that
+/// are new show up in the next round
+#[derive(Debug)]
+pub struct Shuffle {
+    rng: ChaChaRng,
+    shown: HashSet<PathBuf>,
+    order: Vec<PathBuf>, // what was or will be shown, in order
+    pos: Option<usize>,  // where in `order` the current file is
+}
+
+// how many files back can be walked through, before the oldest ones are forgotten
+const HISTORY: usize = 1000;
+
+impl Shuffle {
+    pub fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| thread_rng().gen());
+        debug!("shuffling with seed: {}", seed);
+        Self {
+            rng: ChaChaRng::from_seed(expand_seed(seed)),
+            shown: HashSet::new(),
+            order: vec![],
+            pos: None,
+        }
+    }
+
+    /// forgets the order, the next one starts from the current file
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.pos = None;
+    }
+
+    /// the files that can come up
+    pub fn set_shown<'a>(&mut self, shown: impl IntoIterator<Item = &'a Path>) {
+        self.shown = shown.into_iter().map(Path::to_path_buf).collect();
+    }
+
+    /// whether the current file is the last one that's been shuffled, so the next one
+    /// starts another round
+    pub fn is_at_end(&self) -> bool {
+        self.pos.is_some_and(|pos| pos + 1 >= self.order.len())
+    }
+
+    /// the file after the current one. returns `None` if nothing is shown
+    pub fn next(&mut self, current: Option<&Path>) -> Option<PathBuf> {
+        if self.shown.is_empty() {
+            return None;
+        }
+
+        if self.order.is_empty() {
+            self.start(current);
+        }
+        loop {
+            let next = self.pos.map_or(0, |pos| pos + 1);
+            let next = if next >= self.order.len() {
+                self.extend(next)
+            } else {
+                next
+            };
+            self.pos = Some(next);
+            if self.shown.contains(&self.order[next]) {
+                return Some(self.order[next].clone());
+            }
+        }
+    }
+
+    /// the file before the current one. returns `None` at the start of the order
+    pub fn previous(&mut self) -> Option<PathBuf> {
+        let mut pos = self.pos?;
+        while pos > 0 {
+            pos -= 1;
+            if self.shown.contains(&self.order[pos]) {
+                self.pos = Some(pos);
+                return Some(self.order[pos].clone());
+            }
+        }
+        None
+    }
+
+    // the current file goes first, so that it isn't seen again in the first round
+    fn start(&mut self, current: Option<&Path>) {
+        let current = match current {
+            Some(current) => current,
+            None => return,
+        };
+        self.order.push(current.to_path_buf());
+        self.pos = Some(0);
+
+        let mut round = self.round();
+        round.retain(|path| path != current);
+        self.rng.shuffle(&mut round);
+        self.order.extend(round);
+    }
+
+    // shuffles another round onto the end, which doesn't start with the file the last one
+    // ended on. files too far behind `next` are dropped, returns where `next` ends up
+    fn extend(&mut self, next: usize) -> usize {
+        let mut round = self.round();
+        self.rng.shuffle(&mut round);
+        if round.len() > 1 && self.order.last() == round.first() {
+            let last = round.len() - 1;
+            round.swap(0, last);
+        }
+        trace!("shuffled another round of {} files", round.len());
+        self.order.extend(round);
+
+        let old = next.saturating_sub(HISTORY);
+        if old == 0 {
+            return next;
+        }
+        trace!("forgetting the first {} shuffled files", old);
+        self.order.drain(..old);
+        self.pos = self.pos.map(|pos| pos - old);
+        next - old
+    }
+
+    // everything that's shown, sorted so the same seed gives the same order
+    fn round(&self) -> Vec<PathBuf> {
+        let mut round = self.shown.iter().cloned().collect::<Vec<_>>();
+        round.sort();
+        round
+    }
+}
+
+// spreads the seed over all of the bytes the rng is seeded with, using splitmix64
+fn expand_seed(mut seed: u64) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for chunk in bytes.chunks_mut(8) {
+        seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        chunk.copy_from_slice(&(z ^ (z >> 31)).to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn paths(n: usize) -> Vec<PathBuf> {
+        (0..n).map(|i| PathBuf::from(format!("{}.png", i))).collect()
+    }
+
+    fn shuffled(seed: u64, paths: &[PathBuf]) -> Shuffle {
+        let mut shuffle = Shuffle::new(Some(seed));
+        shuffle.set_shown(paths.iter().map(PathBuf::as_path));
+        shuffle
+    }
+
+    #[test]
+    fn rounds() {
+        let paths = paths(5);
+
+        let mut shuffle = shuffled(42, &paths);
+        let mut seen = vec![];
+        let mut current = paths[2].clone();
+        for _ in 0..4 {
+            current = shuffle.next(Some(&current)).unwrap();
+            seen.push(current.clone());
+        }
+        // everything but the one it started on, once each
+        seen.sort();
+        let rest = [&paths[..2], &paths[3..]].concat();
+        assert_eq!(seen, rest);
+
+        // the next round doesn't start with the one before it
+        let next = shuffle.next(Some(&current)).unwrap();
+        assert_ne!(next, current);
+        assert_eq!(shuffle.previous(), Some(current.clone()));
+        assert_eq!(shuffle.next(Some(&current)), Some(next));
+
+        // back to the start, and no further
+        for _ in 0..5 {
+            assert!(shuffle.previous().is_some());
+        }
+        assert_eq!(shuffle.previous(), None);
+
+        // the same seed is the same order, whatever order the files are shown in
+        let order = |seed, paths: &[PathBuf]| {
+            let mut shuffle = shuffled(seed, paths);
+            (0..10)
+                .map(|_| shuffle.next(None).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let mut reversed = paths.clone();
+        reversed.reverse();
+        assert_eq!(order(7, &paths), order(7, &reversed));
+        assert_ne!(order(7, &paths), order(8, &paths));
+    }
+
+    #[test]
+    fn skips_hidden() {
+        let mut paths = paths(4);
+
+        let mut shuffle = shuffled(1, &paths);
+        let first = shuffle.next(None).unwrap();
+        let second = shuffle.next(Some(&first)).unwrap();
+        paths.retain(|path| *path != first);
+        shuffle.set_shown(paths.iter().map(PathBuf::as_path));
+        assert_eq!(shuffle.previous(), None);
+
+        for _ in 0..10 {
+            assert_ne!(shuffle.next(Some(&second)).unwrap(), first);
+        }
+        shuffle.set_shown(vec![]);
+        assert_eq!(shuffle.next(None), None);
+    }
+
+    #[test]
+    fn history() {
+        let paths = paths(3);
+        let mut shuffle = shuffled(3, &paths);
+        for _ in 0..HISTORY * 2 {
+            shuffle.next(None).unwrap();
+        }
+        assert!(shuffle.order.len() <= HISTORY + paths.len());
+
+        // walking back stops at the oldest one that's remembered
+        let back = (0..).take_while(|_| shuffle.previous().is_some()).count();
+        assert!(back >= HISTORY - 1, "{}", back);
+    }
+
+    #[test]
+    fn seeds() {
+        // every part of the seed is different, even for small ones
+        let bytes = expand_seed(0);
+        assert_ne!(bytes[..8], bytes[8..16]);
+        assert_ne!(expand_seed(1), expand_seed(2));
+    }
+}
@@ -0,0 +1,409 @@
+use std::time::Duration;
+
+/// How the slideshow goes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlideshowOptions {
+    /// how many seconds each image is shown for
+    pub interval: f64,
+    /// start over at the end of the list, instead of stopping
+    pub repeat: bool,
+    /// go through the list in a random order
+    pub shuffle: bool,
+    /// stop when a key is pressed or the mouse is used, instead of giving the image that's
+    /// shown a whole interval again
+    pub pause_on_input: bool,
+    /// let an animation play all the way through once before moving on
+    pub wait_for_animation: bool,
+    /// how one image gives way to the next
+    pub transition: Transition,
+    /// how many seconds the transition takes
+    pub transition_time: f64,
+}
+
+/// How the slideshow moves from one image to the next
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transition {
+    /// the next image just replaces it
+    #[default]
+    None,
+    /// the next image fades in over it
+    Fade,
+}
+
+impl Default for SlideshowOptions {
+    fn default() -> Self {
+        Self {
+            interval: 5.0,
+            repeat: true,
+            shuffle: false,
+            pause_on_input: false,
+            wait_for_animation: true,
+            transition: Transition::None,
+            transition_time: 0.5,
+        }
+    }
+}
+
+impl SlideshowOptions {
+    /// the interval, or the default one if it doesn't make sense
+    pub fn interval(&self) -> Duration {
+        let secs = if self.interval > 0.0 && self.interval.is_finite() {
+            self.interval
+        } else {
+            warn!("invalid slideshow interval: {}", self.interval);
+            Self::default().interval
+        };
+        Duration::from_millis((secs * 1000.0).round() as u64)
+    }
+
+    /// how long the transition takes, if there is one
+    pub fn transition_time(&self) -> Option<Duration> {
+        if self.transition == Transition::None {
+            return None;
+        }
+        if self.transition_time < 0.0 || !self.transition_time.is_finite() {
+            warn!("invalid slideshow transition time: {}", self.transition_time);
+            return None;
+        }
+        match (self.transition_time * 1000.0).round() as u64 {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+}
+
+/// What the slideshow wants done, once the image that's shown has been up long enough
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Advance {
+    Next,
+    /// the end of the list was reached, and it doesn't repeat
+    Stop,
+}
+
+/// Moves through the list on a timer.
+///
+/// It doesn't know about the list or the clock, the viewer tells it what time it is and
+/// about the image that's shown. The times are on any clock, as long as it's always the
+/// same one
+#[derive(Debug)]
+pub struct Slideshow {
+    options: SlideshowOptions,
+    interval: Duration,
+    deadline: Option<Duration>, // when the image that's shown is done, while running
+}
+
+impl Default for Slideshow {
+    fn default() -> Self {
+        Self::new(SlideshowOptions::default())
+    }
+}
+
+impl Slideshow {
+    pub fn new(options: SlideshowOptions) -> Self {
+        Self {
+            interval: options.interval(),
+            options,
+            deadline: None,
+        }
+    }
+
+    pub fn options(&self) -> &SlideshowOptions {
+        &self.options
+    }
+
+    /// takes effect from the next image on
+    #[cfg(test)]
+    pub fn set_options(&mut self, options: SlideshowOptions) {
+        self.interval = options.interval();
+        self.options = options;
+    }
+
+    #[cfg(test)]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    /// starts from `now`, with `interval` instead of the one in the options, if there is one
+    pub fn start(&mut self, now: Duration, interval: Option<Duration>) {
+        self.interval = interval.unwrap_or_else(|| self.options.interval());
+        debug!("starting the slideshow, every {:?}", self.interval);
+        self.deadline = Some(now + self.interval);
+    }
+
+    pub fn stop(&mut self) {
+        debug!("stopping the slideshow");
+        self.deadline = None;
+    }
+
+    /// a new image was shown at `now`, it gets a whole interval
+    pub fn shown(&mut self, now: Duration) {
+        if self.is_running() {
+            self.deadline = Some(now + self.interval);
+        }
+    }
+
+    /// a key was pressed or the mouse was used at `now`. returns whether that stopped it,
+    /// otherwise the image that's shown gets a whole interval again
+    pub fn on_input(&mut self, now: Duration) -> bool {
+        if self.is_running() && self.options.pause_on_input {
+            self.stop();
+            return true;
+        }
+        self.shown(now);
+        false
+    }
+
+    /// how long until it's time to move on, if it's running. `animating` is whether the
+    /// image that's shown is an animation that hasn't played through yet. when it's being
+    /// waited for, its frames say when to look again
+    pub fn time_to_next(&self, now: Duration, animating: bool) -> Option<Duration> {
+        let deadline = self.deadline?;
+        if deadline <= now {
+            if animating && self.options.wait_for_animation {
+                return None;
+            }
+            return Some(Duration::default());
+        }
+        Some(deadline - now)
+    }
+
+    /// what to do at `now`, if anything. `at_end` is whether the image that's shown is the
+    /// last one in the list
+    pub fn advance(&mut self, now: Duration, animating: bool, at_end: bool) -> Option<Advance> {
+        if self.time_to_next(now, animating)? > Duration::default() {
+            return None;
+        }
+
+        if at_end && !self.options.repeat {
+            self.stop();
+            return Some(Advance::Stop);
+        }
+        // in case there's nothing to move on to
+        self.deadline = Some(now + self.interval);
+        Some(Advance::Next)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use args::Args;
+    use event::{EventType, Key};
+    use headless::{Headless, ManualClock};
+    use sort::SortKey;
+    use testutil::{drop, key};
+    use viewer::ANIMATION_TIMER;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn timing() {
+        let mut slideshow = Slideshow::default();
+        assert_eq!(slideshow.interval(), secs(5));
+        assert_eq!(slideshow.time_to_next(secs(0), false), None);
+        assert_eq!(slideshow.advance(secs(10), false, false), None);
+
+        slideshow.start(secs(0), Some(secs(2)));
+        assert_eq!(slideshow.time_to_next(secs(1), false), Some(secs(1)));
+        assert_eq!(slideshow.advance(secs(1), false, false), None);
+        assert_eq!(slideshow.advance(secs(2), false, true), Some(Advance::Next));
+
+        // a new image starts the interval over
+        slideshow.shown(secs(3));
+        assert_eq!(slideshow.time_to_next(secs(4), false), Some(secs(1)));
+
+        // input doesn't stop it, unless it's asked to. it waits a whole interval instead
+        assert!(!slideshow.on_input(secs(4)));
+        assert_eq!(slideshow.time_to_next(secs(4), false), Some(secs(2)));
+        let options = SlideshowOptions {
+            interval: -1.0,
+            repeat: false,
+            pause_on_input: true,
+            ..SlideshowOptions::default()
+        };
+        slideshow.set_options(options);
+        assert_eq!(slideshow.interval(), secs(5));
+        assert!(slideshow.on_input(secs(5)));
+        assert!(!slideshow.is_running());
+        assert!(!slideshow.on_input(secs(6)));
+    }
+
+    #[test]
+    fn waits_and_stops() {
+        let mut slideshow = Slideshow::new(SlideshowOptions {
+            interval: 1.5,
+            repeat: false,
+            ..SlideshowOptions::default()
+        });
+        slideshow.start(secs(0), None);
+        assert_eq!(slideshow.interval(), Duration::from_millis(1500));
+
+        // an animation that hasn't played through is waited for
+        assert_eq!(slideshow.time_to_next(secs(2), true), None);
+        assert_eq!(slideshow.advance(secs(2), true, false), None);
+        assert_eq!(slideshow.advance(secs(3), false, false), Some(Advance::Next));
+
+        // the end of the list stops it
+        slideshow.shown(secs(3));
+        assert_eq!(slideshow.advance(secs(5), false, true), Some(Advance::Stop));
+        assert!(!slideshow.is_running());
+
+        // unless it doesn't wait
+        slideshow.set_options(SlideshowOptions {
+            wait_for_animation: false,
+            ..SlideshowOptions::default()
+        });
+        slideshow.start(secs(0), None);
+        assert_eq!(slideshow.advance(secs(5), true, true), Some(Advance::Next));
+    }
+
+    #[test]
+    fn transitions() {
+        let mut options = SlideshowOptions::default();
+        assert_eq!(options.transition_time(), None);
+
+        options.transition = Transition::Fade;
+        assert_eq!(options.transition_time(), Some(Duration::from_millis(500)));
+        options.transition_time = 0.0;
+        assert_eq!(options.transition_time(), None);
+        options.transition_time = -1.0;
+        assert_eq!(options.transition_time(), None);
+    }
+
+    #[test]
+    fn slideshow() {
+        let clock = ManualClock::default();
+        let mut app = Headless::with_clock(8, 6, clock.clone());
+        let timer = EventType::Timer {
+            id: ANIMATION_TIMER,
+        };
+        let args = Args {
+            paths: vec!["testdata/test.jpg".into()],
+            sort: Some(SortKey::Name),
+            slideshow: Some(Duration::from_secs(2)),
+            ..Args::default()
+        };
+
+        app.start(&args);
+        assert_eq!(app.selected(), Some(1));
+        assert_eq!(app.timer(), Some(Duration::from_secs(2)));
+
+        clock.advance(Duration::from_secs(2));
+        app.handle(&timer);
+        assert_eq!(app.selected(), Some(2));
+        assert_eq!(app.timer(), Some(Duration::from_secs(2)));
+
+        // moving by hand restarts the interval
+        clock.advance(Duration::from_secs(1));
+        app.handle(&key(Key::D));
+        assert_eq!(app.selected(), Some(0));
+        clock.advance(Duration::from_secs(1));
+        app.handle(&timer);
+        assert_eq!(app.selected(), Some(0));
+
+        // the gif's frames come before the next slide
+        assert!(app.timer().unwrap() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn slideshow_options() {
+        let clock = ManualClock::default();
+        let mut app = Headless::with_clock(8, 6, clock.clone());
+        let timer = EventType::Timer {
+            id: ANIMATION_TIMER,
+        };
+        app.viewer_mut().set_slideshow_options(SlideshowOptions {
+            interval: 1.0,
+            repeat: false,
+            pause_on_input: true,
+            ..SlideshowOptions::default()
+        });
+
+        app.run(&[drop("test.jpg"), key(Key::P)]);
+        assert!(app.viewer().slideshow().is_running());
+        clock.advance(Duration::from_secs(1));
+        app.handle(&timer);
+        assert_eq!(app.selected(), Some(2));
+
+        // it stops at the end of the list
+        clock.advance(Duration::from_secs(1));
+        app.handle(&timer);
+        assert_eq!(app.selected(), Some(2));
+        assert!(!app.viewer().slideshow().is_running());
+
+        // and when a key is pressed
+        app.run(&[key(Key::P), key(Key::D)]);
+        assert_eq!(app.selected(), Some(0));
+        assert!(!app.viewer().slideshow().is_running());
+
+        // the gif plays through before moving on, even with a shorter interval
+        app.viewer_mut().set_slideshow_options(SlideshowOptions {
+            interval: 0.1,
+            ..SlideshowOptions::default()
+        });
+        app.handle(&key(Key::P));
+        let mut ticks = 0;
+        while app.selected() == Some(0) && ticks < 20 {
+            clock.advance(app.timer().unwrap());
+            app.handle(&timer);
+            ticks += 1;
+        }
+        assert_eq!(app.selected(), Some(1));
+        // one for each of its frames
+        assert_eq!(ticks, 3);
+    }
+
+    #[test]
+    fn slideshow_fade() {
+        let clock = ManualClock::default();
+        let mut app = Headless::with_clock(8, 6, clock.clone());
+        let timer = EventType::Timer {
+            id: ANIMATION_TIMER,
+        };
+        app.viewer_mut().set_slideshow_options(SlideshowOptions {
+            interval: 1.0,
+            transition: Transition::Fade,
+            transition_time: 0.5,
+            ..SlideshowOptions::default()
+        });
+        let shown = |file| {
+            let mut app = Headless::new(8, 6);
+            app.handle(&drop(file));
+            app.framebuffer().clone()
+        };
+        let (jpg, png) = (shown("test.jpg"), shown("test.png"));
+        assert_ne!(jpg, png);
+
+        app.run(&[drop("test.jpg"), key(Key::P)]);
+        clock.advance(Duration::from_secs(1));
+        app.handle(&timer);
+        assert_eq!(app.selected(), Some(2));
+        // it starts from the image before, and draws often until it's done
+        assert_eq!(app.framebuffer(), &jpg);
+        assert_eq!(app.timer(), Some(Duration::from_millis(33)));
+
+        clock.advance(Duration::from_millis(250));
+        app.handle(&timer);
+        assert_ne!(app.framebuffer(), &jpg);
+        assert_ne!(app.framebuffer(), &png);
+
+        clock.advance(Duration::from_millis(250));
+        app.handle(&timer);
+        assert_eq!(app.framebuffer(), &png);
+        assert_eq!(app.timer(), Some(Duration::from_millis(500)));
+
+        // moving by hand doesn't fade
+        clock.advance(Duration::from_millis(100));
+        app.handle(&timer);
+        app.handle(&key(Key::A));
+        assert_eq!(app.framebuffer(), &jpg);
+    }
+}
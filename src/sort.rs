@@ -0,0 +1,298 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use entry::Entry;
+
+/// What the file list is ordered by
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// natural order, so `2.png` comes before `10.png`
+    #[default]
+    Name,
+    Size,
+    Modified,
+    Extension,
+    /// the number of pixels
+    Dimensions,
+    /// when the picture was taken, from the EXIF data
+    Taken,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 6] = [
+        SortKey::Name,
+        SortKey::Size,
+        SortKey::Modified,
+        SortKey::Extension,
+        SortKey::Dimensions,
+        SortKey::Taken,
+    ];
+
+    /// the key after this one, wrapping around
+    pub fn next(self) -> Self {
+        let pos = Self::ALL.iter().position(|&key| key == self).unwrap_or(0);
+        Self::ALL[(pos + 1) % Self::ALL.len()]
+    }
+
+    // files that tie are ordered by name
+    fn compare(self, a: &Entry, b: &Entry) -> Ordering {
+        match self {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => a.size().cmp(&b.size()),
+            SortKey::Modified => a.modified().cmp(&b.modified()),
+            SortKey::Extension => extension(a).cmp(&extension(b)),
+            SortKey::Dimensions => pixels(a).cmp(&pixels(b)),
+            SortKey::Taken => a.taken().cmp(&b.taken()),
+        }
+    }
+
+    // whether the entry doesn't have what's sorted by, it can't be read or has no date
+    fn is_missing(self, entry: &Entry) -> bool {
+        match self {
+            SortKey::Dimensions => entry.dimensions().is_none(),
+            SortKey::Taken => entry.taken().is_none(),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|key| key.to_string() == s)
+            .cloned()
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Modified => "modified",
+            SortKey::Extension => "extension",
+            SortKey::Dimensions => "dimensions",
+            SortKey::Taken => "taken",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How the file list is ordered
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortOrder {
+    pub key: SortKey,
+    #[serde(default)]
+    pub reverse: bool,
+}
+
+impl SortOrder {
+    pub fn new(key: SortKey, reverse: bool) -> Self {
+        Self { key, reverse }
+    }
+
+    pub fn sort(self, list: &mut [Entry]) {
+        debug!("sorting {} files by {}", list.len(), self);
+        list.sort_by(|a, b| {
+            // the ones that are missing what's sorted by go at the end, either way
+            let missing = self.key.is_missing(a).cmp(&self.key.is_missing(b));
+            missing.then_with(|| {
+                let ord = self
+                    .key
+                    .compare(a, b)
+                    .then_with(|| natural_cmp(&a.display_name(), &b.display_name()));
+                if self.reverse {
+                    ord.reverse()
+                } else {
+                    ord
+                }
+            })
+        });
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.key)?;
+        if self.reverse {
+            write!(f, ", reversed")?;
+        }
+        Ok(())
+    }
+}
+
+/// compares the way people count: runs of digits are compared as numbers, and case only
+/// matters when everything else is the same
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut left, mut right) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ord = match (left.peek().cloned(), right.peek().cloned()) {
+            (None, None) => break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) if l.is_ascii_digit() && r.is_ascii_digit() => {
+                let (l, r) = (take_number(&mut left), take_number(&mut right));
+                let (l, r) = (l.trim_start_matches('0'), r.trim_start_matches('0'));
+                l.len().cmp(&r.len()).then_with(|| l.cmp(r))
+            }
+            (Some(l), Some(r)) => {
+                left.next();
+                right.next();
+                l.to_lowercase().cmp(r.to_lowercase())
+            }
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    // `a01` and `a1`, or `A` and `a`
+    a.cmp(b)
+}
+
+fn take_number<I: Iterator<Item = char>>(iter: &mut ::std::iter::Peekable<I>) -> String {
+    let mut s = String::new();
+    while let Some(&c) = iter.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        s.push(c);
+        iter.next();
+    }
+    s
+}
+
+fn extension(entry: &Entry) -> String {
+    entry
+        .path()
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn pixels(entry: &Entry) -> Option<u64> {
+    entry
+        .dimensions()
+        .map(|(w, h)| u64::from(w) * u64::from(h))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::Key;
+    use headless::Headless;
+    use testutil::{drop, key};
+
+    fn names(list: &[Entry]) -> Vec<String> {
+        list.iter().map(Entry::name).collect()
+    }
+
+    #[test]
+    fn sorting() {
+        let mut list = vec![
+            Entry::new("testdata/test.png"),
+            Entry::new("testdata/test.gif"),
+            Entry::new("testdata/test.jpg"),
+        ];
+
+        SortOrder::new(SortKey::Name, false).sort(&mut list);
+        assert_eq!(names(&list), vec!["test.gif", "test.jpg", "test.png"]);
+
+        SortOrder::new(SortKey::Size, false).sort(&mut list);
+        assert_eq!(names(&list), vec!["test.png", "test.gif", "test.jpg"]);
+
+        SortOrder::new(SortKey::Size, true).sort(&mut list);
+        assert_eq!(names(&list), vec!["test.jpg", "test.gif", "test.png"]);
+
+        // they're all the same size, and none of them have a date
+        SortOrder::new(SortKey::Dimensions, false).sort(&mut list);
+        assert_eq!(names(&list), vec!["test.gif", "test.jpg", "test.png"]);
+        SortOrder::new(SortKey::Taken, true).sort(&mut list);
+        assert_eq!(names(&list), vec!["test.png", "test.jpg", "test.gif"]);
+
+        assert_eq!("size".parse(), Ok(SortKey::Size));
+        assert_eq!("taken".parse(), Ok(SortKey::Taken));
+        assert_eq!("date".parse::<SortKey>(), Err(()));
+        assert_eq!(SortKey::Taken.next(), SortKey::Name);
+
+        // without any dimensions goes at the end
+        list.push(Entry::new("testdata/missing.png"));
+        list[0].set_dimensions(100, 100);
+        SortOrder::new(SortKey::Dimensions, false).sort(&mut list);
+        assert_eq!(
+            names(&list),
+            vec!["test.gif", "test.jpg", "test.png", "missing.png"]
+        );
+        // even when it's reversed
+        SortOrder::new(SortKey::Dimensions, true).sort(&mut list);
+        assert_eq!(
+            names(&list),
+            vec!["test.png", "test.jpg", "test.gif", "missing.png"]
+        );
+    }
+
+    #[test]
+    fn natural() {
+        let mut names = vec![
+            "img10.png",
+            "img2.png",
+            "IMG1.png",
+            "img02.png",
+            "img1.jpg",
+            "b.png",
+            "a.png",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "a.png",
+                "b.png",
+                "img1.jpg",
+                "IMG1.png",
+                "img02.png",
+                "img2.png",
+                "img10.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_keys() {
+        let mut app = Headless::new(80, 60);
+        let names = |app: &Headless| {
+            app.files()
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        app.handle(&drop("test.jpg"));
+        assert_eq!(names(&app), "test.gif test.jpg test.png");
+        assert_eq!(app.selected(), Some(1));
+
+        // by size, then backwards. the same file stays selected
+        app.handle(&key(Key::S));
+        assert_eq!(names(&app), "test.png test.gif test.jpg");
+        assert_eq!(app.selected(), Some(2));
+        app.handle(&key(Key::I));
+        assert_eq!(names(&app), "test.jpg test.gif test.png");
+        assert_eq!(app.selected(), Some(0));
+        assert_eq!(app.title(), Some("testdata"));
+        assert_eq!(
+            app.context().get_sort(),
+            SortOrder::new(SortKey::Size, true)
+        );
+
+        // dropping another file keeps the order
+        app.handle(&drop("test.png"));
+        assert_eq!(names(&app), "test.jpg test.gif test.png");
+        assert_eq!(app.selected(), Some(2));
+    }
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use event::{EventType, Key, Modifiers};
+
+/// A fresh directory under the system temp directory. it's removed when it's dropped,
+/// even when the test fails
+#[derive(Debug)]
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` keeps it apart from the other tests, which run at the same time
+    pub fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("pict-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// dropping a file from the testdata directory
+pub fn drop(file: &str) -> EventType {
+    EventType::DropFile {
+        file: format!("testdata/{}", file),
+    }
+}
+
+/// dropping anything else
+pub fn drop_path(path: &Path) -> EventType {
+    EventType::DropFile {
+        file: path.to_string_lossy().into_owned(),
+    }
+}
+
+/// pressing `key` without any modifiers
+pub fn key(key: Key) -> EventType {
+    EventType::KeyDown {
+        key,
+        modifiers: Modifiers::default(),
+        repeat: false,
+    }
+}
+
+/// the test jpeg, with exif saying it needs a quarter turn
+pub fn turned_jpeg() -> Vec<u8> {
+    let mut tiff = b"II*\0\x08\0\0\0\x01\0".to_vec();
+    tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+    let jpeg = fs::read("testdata/test.jpg").unwrap();
+    let mut data = jpeg[..2].to_vec();
+    data.extend_from_slice(&[0xFF, 0xE1, 0, (tiff.len() + 8) as u8]);
+    data.extend_from_slice(b"Exif\0\0");
+    data.extend_from_slice(&tiff);
+    data.extend_from_slice(&jpeg[2..]);
+    data
+}
@@ -0,0 +1,229 @@
+use std::cmp;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use args::Args;
+use context::Context;
+use entry::Entry;
+use event::EventType;
+use render::Framebuffer;
+use tui::encode::{self, Protocol};
+use tui::term::{key_from_char, Input, Size, Terminal};
+use viewer::{Effect, Viewer, ANIMATION_TIMER, WATCH_INTERVAL, WATCH_TIMER};
+
+// the terminal doesn't tell us when it's resized, so it's checked at least this often
+const RESIZE_POLL: Duration = Duration::from_millis(250);
+// a guess at the size of a cell, for terminals that don't report pixels
+const CELL_WIDTH: usize = 8;
+const CELL_HEIGHT: usize = 16;
+
+/// The terminal frontend. The image takes up the whole terminal, except for a status
+/// line along the bottom
+pub struct Tui {
+    term: Terminal,
+    protocol: Protocol,
+    viewer: Viewer,
+    framebuffer: Framebuffer,
+    size: Size,
+
+    timer: Option<Instant>, // when the animation timer fires
+    watch: Instant,         // when the files are checked for changes
+    dirty: bool,
+    quit: bool,
+}
+
+impl Tui {
+    /// takes over the terminal. returns `None` if there isn't one
+    pub fn new() -> Option<Self> {
+        let mut term = Terminal::new()?;
+        let protocol = term.detect();
+        debug!("drawing with {:?}", protocol);
+
+        let context = Arc::new(Mutex::new(Context::new()));
+        Some(Self {
+            term,
+            protocol,
+            viewer: Viewer::new(context),
+            framebuffer: Framebuffer::default(),
+            size: Size::default(),
+            timer: None,
+            watch: Instant::now() + WATCH_INTERVAL,
+            dirty: true,
+            quit: false,
+        })
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// shows what's in `args` until the user quits
+    pub fn run(&mut self, args: &Args) {
+        self.check_size();
+        let effects = self.viewer.start(args);
+        self.apply(effects);
+
+        while !self.quit {
+            self.check_size();
+            if self.dirty {
+                self.paint();
+            }
+
+            let deadline = match self.timer {
+                Some(deadline) => cmp::min(deadline, self.watch),
+                None => self.watch,
+            };
+            let now = Instant::now();
+            let left = if deadline > now {
+                deadline - now
+            } else {
+                Duration::default()
+            };
+            let timeout = cmp::min(left, RESIZE_POLL);
+
+            match self.term.read(Some(timeout)) {
+                Some(Input::Quit) => self.quit = true,
+                Some(Input::Key(key, modifiers)) => self.handle(&EventType::KeyDown {
+                    key,
+                    modifiers,
+                    repeat: false,
+                }),
+                Some(Input::Char(c)) => self.on_char(c),
+                None => {}
+            }
+
+            if self.timer.is_some_and(|deadline| deadline <= Instant::now()) {
+                self.timer = None;
+                self.handle(&EventType::Timer {
+                    id: ANIMATION_TIMER,
+                });
+            }
+
+            if self.watch <= Instant::now() {
+                self.watch = Instant::now() + WATCH_INTERVAL;
+                self.handle(&EventType::Timer { id: WATCH_TIMER });
+            }
+        }
+    }
+
+    pub fn handle(&mut self, ev: &EventType) {
+        let effects = self.viewer.handle(ev);
+        self.apply(effects);
+    }
+
+    // q and escape quit, unless there's a filter being typed or to clear
+    fn on_char(&mut self, c: char) {
+        if !self.viewer.is_typing() {
+            let quit = match c {
+                'q' | 'Q' => true,
+                '\x1b' => self.viewer.filter_query().is_none(),
+                _ => false,
+            };
+            if quit {
+                self.quit = true;
+                return;
+            }
+        }
+
+        self.handle(&EventType::Char { c });
+        let (key, modifiers) = key_from_char(c);
+        self.handle(&EventType::KeyDown {
+            key,
+            modifiers,
+            repeat: false,
+        });
+    }
+
+    fn apply(&mut self, effects: Vec<Effect>) {
+        for effect in effects {
+            match effect {
+                Effect::Schedule(next) => self.timer = next.map(|delay| Instant::now() + delay),
+                // there's no file list to show, the status line has the name instead
+                Effect::ToggleFileList | Effect::AlignFileList => {}
+                _ => self.dirty = true,
+            }
+        }
+    }
+
+    // the framebuffer follows the terminal. half blocks are two pixels to a cell
+    fn check_size(&mut self) {
+        let size = self.term.size();
+        if size == self.size {
+            return;
+        }
+        debug!("terminal resized to {:?}", size);
+        self.size = size;
+
+        let rows = size.rows.saturating_sub(1); // the status line
+        let (width, height) = match self.protocol {
+            Protocol::HalfBlock => (size.cols, rows * 2),
+            Protocol::Kitty | Protocol::Sixel => {
+                let (cw, ch) = if size.width > 0 && size.height > 0 {
+                    (size.width / size.cols, size.height / size.rows)
+                } else {
+                    (CELL_WIDTH, CELL_HEIGHT)
+                };
+                (size.cols * cw, rows * ch)
+            }
+        };
+
+        let (width, height) = (width as i32, height as i32);
+        self.framebuffer.resize(width, height);
+        self.term.write("\x1b[2J");
+        self.handle(&EventType::Resize { width, height });
+        self.dirty = true;
+    }
+
+    fn paint(&mut self) {
+        self.dirty = false;
+        self.viewer.render(&mut self.framebuffer);
+
+        let rows = self.size.rows.saturating_sub(1);
+        let mut out = String::from("\x1b[H");
+        match self.protocol {
+            Protocol::Kitty => out.push_str(&encode::kitty(&self.framebuffer, self.size.cols, rows)),
+            Protocol::Sixel => out.push_str(&encode::sixel(&self.framebuffer)),
+            Protocol::HalfBlock => out.push_str(&encode::half_block(&self.framebuffer)),
+        }
+
+        let status = self.status().chars().take(self.size.cols).collect::<String>();
+        out.push_str(&format!("\x1b[{};1H\x1b[2K\x1b[7m{}\x1b[0m", self.size.rows, status));
+        self.term.write(&out);
+    }
+
+    // which file this is, and what's being shown of it
+    fn status(&self) -> String {
+        let (index, len, name) = {
+            let context = self.viewer.context();
+            let name = context.current().map(Entry::display_name);
+            (context.get_index(), context.get_len(), name)
+        };
+
+        let name = match name {
+            Some(name) => name,
+            None if self.viewer.filter_query().is_some() => String::new(),
+            None => return " nothing to show. try `pict --tui <file or directory>`, q quits".into(),
+        };
+
+        let mut status = match self.viewer.filter_query() {
+            Some(query) => format!(" /{}", query),
+            None => String::new(),
+        };
+        status += &format!(" {}/{} {}", cmp::min(index + 1, len), len, name);
+        if self.viewer.context().is_shuffled() {
+            status += " shuffled";
+        }
+        if self.viewer.slideshow().is_running() {
+            status += " slideshow";
+        }
+        if let Some(image) = self.viewer.image() {
+            status += &format!(" {}x{}", image.width(), image.height());
+            let animation = self.viewer.animation();
+            if animation.frames() > 1 {
+                status += &format!(" frame {}/{}", animation.frame() + 1, animation.frames());
+            }
+            status += &format!(" {:.0}%", self.viewer.viewport().scale() * 100.0);
+        }
+        status
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt::Write;
+
+use render::Framebuffer;
+
+/// How the image gets drawn in the terminal
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Protocol {
+    /// the kitty graphics protocol, the image is sent as raw rgb
+    Kitty,
+    /// DEC sixel, with a fixed palette
+    Sixel,
+    /// `▀` with a truecolor foreground and background, two pixels to a cell
+    HalfBlock,
+}
+
+// kitty wants the payload split up into chunks of at most this many bytes
+const KITTY_CHUNK: usize = 4096;
+// the image id we use, so the last image can be replaced
+const KITTY_ID: u32 = 1;
+
+fn rgb(pixel: u32) -> (u8, u8, u8) {
+    ((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+}
+
+/// sends the framebuffer with the kitty graphics protocol, scaled into `cols`x`rows` cells
+/// at the cursor. the previous image is deleted first
+pub fn kitty(fb: &Framebuffer, cols: usize, rows: usize) -> String {
+    let mut data = Vec::with_capacity(fb.pixels().len() * 3);
+    for &pixel in fb.pixels() {
+        let (r, g, b) = rgb(pixel);
+        data.extend_from_slice(&[r, g, b]);
+    }
+    let payload = base64(&data);
+
+    let mut out = format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", KITTY_ID);
+    let chunks = payload.as_bytes().chunks(KITTY_CHUNK).collect::<Vec<_>>();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        if i == 0 {
+            let _ = write!(
+                out,
+                "\x1b_Ga=T,f=24,i={},s={},v={},c={},r={},q=2,m={};",
+                KITTY_ID,
+                fb.width(),
+                fb.height(),
+                cols,
+                rows,
+                more
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={};", more);
+        }
+        // base64 is ascii, so the chunks are always on a char boundary
+        out.push_str(::std::str::from_utf8(chunk).unwrap());
+        out.push_str("\x1b\\");
+    }
+    out
+}
+
+// the index into the 6x6x6 color cube
+fn cube(pixel: u32) -> usize {
+    let (r, g, b) = rgb(pixel);
+    let level = |c: u8| (usize::from(c) * 5 + 127) / 255;
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+/// draws the framebuffer as a sixel image, using a 216 color cube for the palette
+pub fn sixel(fb: &Framebuffer) -> String {
+    let (width, height) = (fb.width(), fb.height());
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+
+    // sixel colors are in percent
+    for i in 0..216 {
+        let percent = |level: usize| level * 100 / 5;
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            i,
+            percent(i / 36),
+            percent(i / 6 % 6),
+            percent(i % 6)
+        );
+    }
+
+    let indices = fb.pixels().iter().map(|&p| cube(p)).collect::<Vec<_>>();
+    for band in 0..height.div_ceil(6) {
+        let top = band * 6;
+        let rows = ::std::cmp::min(6, height - top);
+
+        let mut colors = [false; 216];
+        for y in top..top + rows {
+            for &c in &indices[y * width..(y + 1) * width] {
+                colors[c] = true;
+            }
+        }
+
+        let mut first = true;
+        for (color, _) in colors.iter().enumerate().filter(|(_, &used)| used) {
+            if !first {
+                out.push('$'); // back to the start of the band
+            }
+            first = false;
+
+            let _ = write!(out, "#{}", color);
+            let mut run = (0, 0u8);
+            for x in 0..width {
+                let mut bits = 0u8;
+                for dy in 0..rows {
+                    if indices[(top + dy) * width + x] == color {
+                        bits |= 1 << dy;
+                    }
+                }
+                if run.0 > 0 && run.1 != bits {
+                    push_run(&mut out, run);
+                    run.0 = 0;
+                }
+                run = (run.0 + 1, bits);
+            }
+            push_run(&mut out, run);
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+fn push_run(out: &mut String, (len, bits): (usize, u8)) {
+    let c = (b'?' + bits) as char;
+    match len {
+        0 => {}
+        1..=3 => (0..len).for_each(|_| out.push(c)),
+        _ => {
+            let _ = write!(out, "!{}{}", len, c);
+        }
+    }
+}
+
+/// draws the framebuffer with `▀`, the top pixel is the foreground and the bottom one
+/// the background. an odd last row gets drawn over black
+pub fn half_block(fb: &Framebuffer) -> String {
+    let mut out = String::new();
+    for y in (0..fb.height()).step_by(2) {
+        let mut last = None;
+        for x in 0..fb.width() {
+            let top = fb.pixel(x, y).unwrap_or_default();
+            let bottom = fb.pixel(x, y + 1).unwrap_or_default();
+            if last != Some((top, bottom)) {
+                let (tr, tg, tb) = rgb(top);
+                let (br, bg, bb) = rgb(bottom);
+                let _ = write!(
+                    out,
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                    tr, tg, tb, br, bg, bb
+                );
+                last = Some((top, bottom));
+            }
+            out.push('\u{2580}');
+        }
+        out.push_str("\x1b[0m\r\n");
+    }
+    out
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fb(pixels: &[u32], width: i32) -> Framebuffer {
+        let mut fb = Framebuffer::new(width, pixels.len() as i32 / width);
+        fb.pixels_mut().copy_from_slice(pixels);
+        fb
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn kitty_chunks() {
+        let small = kitty(&fb(&[0x00_FF_00_80], 1), 1, 1);
+        assert_eq!(
+            small,
+            "\x1b_Ga=d,d=I,i=1,q=2\x1b\\\x1b_Ga=T,f=24,i=1,s=1,v=1,c=1,r=1,q=2,m=0;/wCA\x1b\\"
+        );
+
+        // 2000 pixels is 6000 bytes, which is 8000 bytes of base64
+        let big = kitty(&Framebuffer::new(2000, 1), 10, 1);
+        assert_eq!(big.matches("\x1b_G").count(), 3);
+        assert!(big.contains(",m=1;"));
+        assert!(big.contains("\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn sixel_bands() {
+        // a red pixel over a blue one
+        let out = sixel(&fb(&[0x00_FF_00_00, 0x00_00_00_FF], 1));
+        assert!(out.starts_with("\x1bPq\"1;1;1;2#0;2;0;0;0"));
+        assert!(out.contains("#180;2;100;0;0"));
+        assert!(out.ends_with("#5A$#180@-\x1b\\"));
+
+        // runs get compressed. the background is #43 in the cube
+        let wide = sixel(&Framebuffer::new(10, 1));
+        assert!(wide.ends_with("#43!10@-\x1b\\"));
+        // 7 rows is two bands
+        assert_eq!(sixel(&Framebuffer::new(1, 7)).matches('-').count(), 2);
+    }
+
+    #[test]
+    fn half_blocks() {
+        let out = half_block(&fb(&[0x00_01_02_03, 0x00_01_02_03, 0x00_04_05_06, 0x00_04_05_06], 2));
+        assert_eq!(
+            out,
+            "\x1b[38;2;1;2;3;48;2;4;5;6m\u{2580}\u{2580}\x1b[0m\r\n"
+        );
+        // an odd row is drawn over black
+        let out = half_block(&fb(&[0x00_FF_FF_FF], 1));
+        assert_eq!(out, "\x1b[38;2;255;255;255;48;2;0;0;0m\u{2580}\x1b[0m\r\n");
+    }
+}
@@ -0,0 +1,12 @@
+//! The terminal frontend, for when there's no window to open (e.g. over ssh)
+//!
+//! The image is drawn with the kitty graphics protocol or sixel when the terminal says it
+//! can do either, otherwise with truecolor half blocks.
+mod encode;
+mod term;
+
+mod app;
+
+pub use self::app::*;
+pub use self::encode::*;
+pub use self::term::{detect, key_from_char, parse_input, Input, Size};
@@ -0,0 +1,360 @@
+use std::io::{self, Read, Write};
+use std::os::raw::c_int;
+use std::time::{Duration, Instant};
+use std::{cmp, env, mem, str};
+
+use libc;
+
+use event::{Key, Modifiers};
+use tui::encode::Protocol;
+
+// asks kitty whether it can take an image, then asks for the device attributes.
+// every terminal answers the second one, so we know when to stop waiting
+const QUERY: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c";
+// how long to wait for the terminal to answer
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+// how long to wait for the rest of an escape sequence, before it's just escape
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// What the terminal reads as
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Key(Key, Modifiers),
+    /// something was typed. `key_from_char` says which key it was
+    Char(char),
+    Quit,
+}
+
+/// The size of the terminal
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Size {
+    pub cols: usize,
+    pub rows: usize,
+    /// in pixels, if the terminal says
+    pub width: usize,
+    pub height: usize,
+}
+
+/// The controlling terminal, in raw mode on the alternate screen.
+/// Everything gets put back when it's dropped
+pub struct Terminal {
+    fd: c_int,
+    original: libc::termios,
+    buf: Vec<u8>,
+}
+
+impl Terminal {
+    /// returns `None` if stdin isn't a terminal
+    pub fn new() -> Option<Self> {
+        let fd = libc::STDIN_FILENO;
+        unsafe {
+            if libc::isatty(fd) == 0 || libc::isatty(libc::STDOUT_FILENO) == 0 {
+                error!("stdin and stdout need to be a terminal");
+                return None;
+            }
+
+            let mut original = mem::zeroed();
+            if libc::tcgetattr(fd, &mut original) != 0 {
+                error!("cannot get the terminal attributes");
+                return None;
+            }
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            libc::tcsetattr(fd, libc::TCSANOW, &raw);
+
+            let this = Self {
+                fd,
+                original,
+                buf: vec![],
+            };
+            // the alternate screen, with the cursor hidden
+            this.write("\x1b[?1049h\x1b[?25l");
+            Some(this)
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        let mut ws: libc::winsize = unsafe { mem::zeroed() };
+        unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+        Size {
+            cols: cmp::max(ws.ws_col, 1) as usize,
+            rows: cmp::max(ws.ws_row, 1) as usize,
+            width: ws.ws_xpixel as usize,
+            height: ws.ws_ypixel as usize,
+        }
+    }
+
+    pub fn write(&self, s: &str) {
+        let mut out = io::stdout();
+        if let Err(err) = out.write_all(s.as_bytes()).and_then(|_| out.flush()) {
+            warn!("cannot write to the terminal: {}", err);
+        }
+    }
+
+    /// asks the terminal what it can draw with
+    pub fn detect(&mut self) -> Protocol {
+        self.write(QUERY);
+
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        let mut reply = vec![];
+        // the device attributes end with a `c`
+        while !reply.ends_with(b"c") {
+            let now = Instant::now();
+            if now >= deadline || !self.poll(Some(deadline - now)) {
+                debug!("the terminal didn't answer the query");
+                break;
+            }
+            match self.read_some() {
+                Some(bytes) => reply.extend(bytes),
+                None => break,
+            }
+        }
+
+        let term = env::var("TERM").unwrap_or_default();
+        let kitty = env::var_os("KITTY_WINDOW_ID").is_some();
+        detect(&reply, &term, kitty)
+    }
+
+    /// waits up to `timeout` (or forever, for `None`) for the next input
+    pub fn read(&mut self, timeout: Option<Duration>) -> Option<Input> {
+        loop {
+            if let Some((input, len)) = parse_input(&self.buf) {
+                self.buf.drain(..len);
+                return Some(input);
+            }
+
+            // the rest of an escape sequence comes right after the start of it
+            let wait = if self.buf.is_empty() {
+                timeout
+            } else {
+                Some(ESCAPE_TIMEOUT)
+            };
+            if !self.poll(wait) {
+                let lone = self.buf == b"\x1b";
+                self.buf.clear();
+                return if lone { Some(Input::Char('\x1b')) } else { None };
+            }
+
+            match self.read_some() {
+                Some(bytes) => self.buf.extend(bytes),
+                None => return Some(Input::Quit), // the terminal went away
+            }
+        }
+    }
+
+    fn poll(&self, timeout: Option<Duration>) -> bool {
+        let ms = match timeout {
+            Some(t) => {
+                let ms = t.as_secs() * 1000 + u64::from(t.subsec_millis());
+                cmp::min(ms, c_int::MAX as u64) as c_int
+            }
+            None => -1,
+        };
+        let mut fd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut fd, 1, ms) > 0 }
+    }
+
+    fn read_some(&self) -> Option<Vec<u8>> {
+        let mut buf = [0; 256];
+        match io::stdin().read(&mut buf) {
+            Ok(0) | Err(_) => None,
+            Ok(n) => Some(buf[..n].to_vec()),
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.write("\x1b[0m\x1b[?25h\x1b[?1049l");
+        unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// picks a protocol from the answer to `QUERY`, falling back to the environment
+pub fn detect(reply: &[u8], term: &str, kitty_window: bool) -> Protocol {
+    let reply = String::from_utf8_lossy(reply);
+    if reply.contains("\x1b_Gi=31;OK") {
+        return Protocol::Kitty;
+    }
+
+    // `ESC [ ? 62 ; 4 ; ... c`, where 4 means sixel
+    if let Some(start) = reply.find("\x1b[?") {
+        let attrs = &reply[start + 3..];
+        let attrs = &attrs[..attrs.find('c').unwrap_or(attrs.len())];
+        if attrs.split(';').skip(1).any(|attr| attr == "4") {
+            return Protocol::Sixel;
+        }
+    }
+
+    if term.contains("kitty") || kitty_window {
+        Protocol::Kitty
+    } else {
+        Protocol::HalfBlock
+    }
+}
+
+/// the input at the start of `buf`, and how many bytes it took
+pub fn parse_input(buf: &[u8]) -> Option<(Input, usize)> {
+    let none = Modifiers::default();
+    match *buf.first()? {
+        0x03 => Some((Input::Quit, 1)),
+        n @ 0x20..=0x7E | n @ b'\r' | n @ b'\t' | n @ 0x08 | n @ 0x7F => {
+            Some((Input::Char(char::from(n)), 1))
+        }
+        // ctrl and a letter
+        n @ 0x01..=0x1A => {
+            let ctrl = Modifiers {
+                ctrl: true,
+                ..none
+            };
+            Some((Input::Key(Key::LETTERS[n as usize - 1], ctrl), 1))
+        }
+        // `ESC [ params final`, or `ESC O final`
+        0x1b if buf.get(1) == Some(&b'[') || buf.get(1) == Some(&b'O') => {
+            let len = if buf[1] == b'O' {
+                buf.get(2).map(|_| 3)?
+            } else {
+                buf[2..].iter().position(|b| (0x40..=0x7E).contains(b))? + 3
+            };
+            let (key, modifiers) = parse_sequence(&buf[2..len - 1], buf[len - 1]);
+            Some((Input::Key(key, modifiers), len))
+        }
+        // a late answer to a query, `ESC _ ... ESC \`
+        0x1b if buf.get(1) == Some(&b'_') || buf.get(1) == Some(&b'P') => {
+            let end = buf.windows(2).position(|w| w == b"\x1b\\")?;
+            Some((Input::Key(Key::Other(0), none), end + 2))
+        }
+        // the start of a sequence
+        0x1b if buf.len() == 1 => None,
+        // escape, followed by something else
+        0x1b => Some((Input::Char('\x1b'), 1)),
+        // the start of a utf-8 sequence
+        n @ 0xC0..=0xF7 => {
+            let len = match n {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                _ => 4,
+            };
+            match str::from_utf8(buf.get(..len)?) {
+                Ok(s) => Some((Input::Char(s.chars().next()?), len)),
+                Err(_) => Some((Input::Key(Key::Other(i32::from(n)), none), 1)),
+            }
+        }
+        n => Some((Input::Key(Key::Other(i32::from(n)), none), 1)),
+    }
+}
+
+// the key in `ESC [ params final`. the modifiers come after a `;`, as one more than the
+// bits for shift, alt and ctrl
+fn parse_sequence(params: &[u8], last: u8) -> (Key, Modifiers) {
+    let params = str::from_utf8(params).unwrap_or_default();
+    let mut params = params.split(';').map(|p| p.parse::<u8>().ok());
+    let number = params.next().and_then(|p| p);
+    let bits = params.next().and_then(|p| p).unwrap_or(1).saturating_sub(1);
+    let modifiers = Modifiers {
+        shift: bits & 1 != 0,
+        alt: bits & 2 != 0,
+        ctrl: bits & 4 != 0,
+    };
+
+    let key = match (last, number) {
+        (b'A', _) => Key::Up,
+        (b'B', _) => Key::Down,
+        (b'C', _) => Key::Right,
+        (b'D', _) => Key::Left,
+        (b'H', _) => Key::Home,
+        (b'F', _) => Key::End,
+        (b'P'..=b'S', _) => Key::FUNCTION[(last - b'P') as usize],
+        (b'~', Some(1)) | (b'~', Some(7)) => Key::Home,
+        (b'~', Some(2)) => Key::Insert,
+        (b'~', Some(3)) => Key::Delete,
+        (b'~', Some(4)) | (b'~', Some(8)) => Key::End,
+        (b'~', Some(5)) => Key::PageUp,
+        (b'~', Some(6)) => Key::PageDown,
+        (b'~', Some(n @ 11..=15)) => Key::FUNCTION[n as usize - 11],
+        (b'~', Some(n @ 17..=21)) => Key::FUNCTION[n as usize - 12],
+        (b'~', Some(n @ 23..=24)) => Key::FUNCTION[n as usize - 13],
+        (n, _) => Key::Other(i32::from(n)),
+    };
+    (key, modifiers)
+}
+
+/// the key that types `c`, and shift if it's a capital
+pub fn key_from_char(c: char) -> (Key, Modifiers) {
+    let key = Key::from_char(c).unwrap_or(Key::Other(c as i32));
+    let modifiers = Modifiers {
+        shift: c.is_uppercase(),
+        ..Modifiers::default()
+    };
+    (key, modifiers)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detection() {
+        let kitty = b"\x1b_Gi=31;OK\x1b\\\x1b[?62;22c";
+        assert_eq!(detect(kitty, "xterm-kitty", false), Protocol::Kitty);
+
+        let sixel = b"\x1b[?62;4;6;22c";
+        assert_eq!(detect(sixel, "xterm", false), Protocol::Sixel);
+        // a 4 in the wrong place isn't sixel
+        assert_eq!(detect(b"\x1b[?4;1c", "xterm", false), Protocol::HalfBlock);
+
+        // nothing came back
+        assert_eq!(detect(b"", "xterm-kitty", false), Protocol::Kitty);
+        assert_eq!(detect(b"", "xterm", true), Protocol::Kitty);
+        assert_eq!(detect(b"", "xterm-256color", false), Protocol::HalfBlock);
+    }
+
+    #[test]
+    fn input() {
+        let none = Modifiers::default();
+        let shift = Modifiers {
+            shift: true,
+            ..none
+        };
+        let ctrl = Modifiers { ctrl: true, ..none };
+        assert_eq!(parse_input(b"d"), Some((Input::Char('d'), 1)));
+        assert_eq!(parse_input(b"3x"), Some((Input::Char('3'), 1)));
+        assert_eq!(key_from_char('d'), (Key::D, none));
+        assert_eq!(key_from_char('D'), (Key::D, shift));
+        assert_eq!(key_from_char('3'), (Key::Key3, none));
+        assert_eq!(key_from_char('/'), (Key::Slash, none));
+        assert_eq!(key_from_char('é'), (Key::Other(0xE9), none));
+        assert_eq!(parse_input(b"\x1b[C"), Some((Input::Key(Key::Right, none), 3)));
+        assert_eq!(parse_input(b"\x1bOD"), Some((Input::Key(Key::Left, none), 3)));
+        assert_eq!(parse_input(b"\x1bOQ"), Some((Input::Key(Key::F2, none), 3)));
+        assert_eq!(parse_input(b"\x1b[6~"), Some((Input::Key(Key::PageDown, none), 4)));
+        assert_eq!(parse_input(b"\x1b[24~"), Some((Input::Key(Key::F12, none), 5)));
+        assert_eq!(parse_input(b"\x1b[1;5C"), Some((Input::Key(Key::Right, ctrl), 6)));
+        assert_eq!(parse_input(b"\x04"), Some((Input::Key(Key::D, ctrl), 1)));
+        assert_eq!(parse_input(b"\t"), Some((Input::Char('\t'), 1)));
+        assert_eq!(parse_input(b"\x03"), Some((Input::Quit, 1)));
+        assert_eq!(parse_input(b"\x1bx"), Some((Input::Char('\x1b'), 1)));
+        assert_eq!(parse_input("éa".as_bytes()), Some((Input::Char('é'), 2)));
+        // the whole sequence is taken, even if we don't know it
+        assert_eq!(
+            parse_input(b"\x1b[1;5Zd"),
+            Some((Input::Key(Key::Other(i32::from(b'Z')), ctrl), 6))
+        );
+        assert_eq!(
+            parse_input(b"\x1b[99~"),
+            Some((Input::Key(Key::Other(i32::from(b'~')), none), 5))
+        );
+        assert_eq!(
+            parse_input(b"\x1b_Gi=31;OK\x1b\\q"),
+            Some((Input::Key(Key::Other(0), none), 12))
+        );
+        // not enough yet
+        assert_eq!(parse_input(b"\x1b["), None);
+        assert_eq!(parse_input(b"\xC3"), None);
+        assert_eq!(parse_input(b""), None);
+    }
+}
@@ -0,0 +1,1336 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use animation::{Animation, SystemClock, TimeSource};
+use args::Args;
+use config::Config;
+use context::Context;
+use entry::Entry;
+use event::{EventType, Key, Modifiers, MouseButton};
+use exif::ExifOptions;
+use filter::Filter;
+use format::Formats;
+use image::ImageFormat;
+use imageview::{ImageBuffer, ImageViewport, Repeat, Zoom};
+use keys::{Action, Input, KeyBindings};
+use render::Framebuffer;
+use scan::{rescan, Scan, ScanOptions};
+use scrubber::Scrubber;
+use slideshow::{Advance, Slideshow};
+use sort::SortOrder;
+use watch::Watcher;
+
+/// The timer id that drives the animation
+pub const ANIMATION_TIMER: usize = 1;
+
+/// The timer id for checking whether the files in the list changed
+pub const WATCH_TIMER: usize = 2;
+
+/// How often the frontend should fire the watch timer
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How far the wheel has to turn for a single notch
+pub const WHEEL_DELTA: i16 = 120;
+
+/// The title of the file list when it's made of the files that were asked for
+pub const CUSTOM_SELECTION: &str = "custom selection";
+
+// how much a single notch of the mouse wheel zooms by
+const ZOOM_STEP: f64 = 1.1;
+
+// how often the window is drawn while the slideshow fades from one image to the next
+const FADE_STEP: Duration = Duration::from_millis(33);
+
+/// Something the frontend has to do after the viewer handled an event
+#[derive(Debug, PartialEq)]
+pub enum Effect {
+    /// the selected index changed
+    Select(usize),
+    /// the file list was replaced
+    Populate {
+        title: String,
+        files: Vec<(String, usize)>,
+    },
+    ToggleFileList,
+    AlignFileList,
+    /// show the trackbar with a `0..=max` range, or hide it
+    SetFrames(Option<usize>),
+    /// move the trackbar thumb
+    SetFramePosition(usize),
+    /// the animation timer should fire after this long, or be stopped
+    Schedule(Option<Duration>),
+    /// the window has to be drawn again
+    Redraw,
+}
+
+/// Where the file list came from, so that it can be listed again
+#[derive(Debug, Clone)]
+enum Source {
+    Directory(PathBuf),
+    /// the files and directories that were asked for
+    Selection(Vec<PathBuf>),
+}
+
+/// The image the slideshow is fading out from
+#[derive(Debug)]
+struct Fade {
+    image: ImageBuffer,
+    viewport: ImageViewport,
+    start: Duration,
+    duration: Duration,
+}
+
+impl Fade {
+    // how far along it is at `now`, from 0 to 1
+    fn amount(&self, now: Duration) -> f64 {
+        let elapsed = now.checked_sub(self.start).unwrap_or_default();
+        (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0)
+    }
+
+    // `None` once it's done
+    fn time_left(&self, now: Duration) -> Option<Duration> {
+        (self.start + self.duration)
+            .checked_sub(now)
+            .filter(|&left| left > Duration::default())
+    }
+}
+
+/// The frontend independent part of the main window.
+///
+/// Events go in, and the `Effect`s that the frontend has to apply come out. None of the
+/// locks are held once a method returns, so applying them can call back into the viewer.
+#[derive(Debug)]
+pub struct Viewer<T = SystemClock> {
+    context: Arc<Mutex<Context>>,
+    image: Option<ImageBuffer>,
+    animation: Animation<T>,
+    scrubber: Scrubber,
+    viewport: ImageViewport,
+    title: String, // of the file list
+    skipped: usize, // the files that couldn't be read, the last time they were listed
+    source: Option<Source>,
+    sorted: bool, // whether the source gets sorted when it's listed again
+    scan_options: ScanOptions,
+    formats: Formats,
+    exif: ExifOptions,
+    known: HashMap<PathBuf, Entry>, // every file from the last listing, images or not
+    watcher: Option<Watcher>,
+    query: Option<String>, // the filter, while it's typed or used
+    typing: bool,
+    slideshow: Slideshow,
+    fade: Option<Fade>,
+    keys: KeyBindings,
+    clock: T,
+}
+
+impl Viewer {
+    pub fn new(context: Arc<Mutex<Context>>) -> Self {
+        Self::with_clock(context, SystemClock::default())
+    }
+}
+
+impl<T: TimeSource + Clone> Viewer<T> {
+    pub fn with_clock(context: Arc<Mutex<Context>>, clock: T) -> Self {
+        Self {
+            context,
+            image: None,
+            animation: Animation::new(vec![], Repeat::Finite(1), clock.clone()),
+            scrubber: Scrubber::default(),
+            viewport: ImageViewport::default(),
+            title: String::new(),
+            skipped: 0,
+            source: None,
+            sorted: false,
+            scan_options: Config::get().scan.clone(),
+            formats: Formats::from_table(&Config::get().formats),
+            exif: Config::get().exif.clone(),
+            known: HashMap::new(),
+            watcher: None,
+            query: None,
+            typing: false,
+            slideshow: Slideshow::new(Config::get().slideshow.clone()),
+            fade: None,
+            keys: KeyBindings::from_table(&Config::get().keys).unwrap_or_else(|err| {
+                error!("ignoring [keys] in {}: {}", Config::path().display(), err);
+                KeyBindings::default()
+            }),
+            clock,
+        }
+    }
+
+    pub fn context(&self) -> MutexGuard<'_, Context> {
+        self.context.lock().unwrap()
+    }
+
+    pub fn image(&self) -> Option<&ImageBuffer> {
+        self.image.as_ref()
+    }
+
+    pub fn animation(&self) -> &Animation<T> {
+        &self.animation
+    }
+
+    pub fn viewport(&self) -> &ImageViewport {
+        &self.viewport
+    }
+
+    pub fn slideshow(&self) -> &Slideshow {
+        &self.slideshow
+    }
+
+    pub fn handle(&mut self, ev: &EventType) -> Vec<Effect> {
+        let input = match *ev {
+            EventType::MouseDown { .. }
+            | EventType::MouseWheel { .. }
+            | EventType::Char { .. } => true,
+            // it's what starts and stops the slideshow
+            EventType::KeyDown { key, modifiers, .. } => {
+                self.keys.action(Input::Key(key), modifiers) != Some(Action::Slideshow)
+            }
+            _ => false,
+        };
+        if input && self.slideshow.on_input(self.clock.now()) {
+            debug!("the slideshow was stopped by input");
+        }
+
+        match *ev {
+            EventType::MouseMove { x, y } => self.on_mouse_move((x, y)),
+            EventType::MouseDown { ref button, x, y } => self.on_mouse_down(button, (x, y)),
+            EventType::MouseUp { ref button, x, y } => self.on_mouse_up(button, (x, y)),
+            EventType::MouseWheel { delta, x, y } => self.on_mouse_wheel(delta, (x, y)),
+            EventType::KeyDown {
+                key,
+                modifiers,
+                repeat,
+            } => self.on_key_down(key, modifiers, repeat),
+            EventType::Char { c } => self.on_char(c),
+            EventType::Resize { width, height } => self.on_resize((width, height)),
+            EventType::DropFile { ref file } => self.on_drop_file(file),
+            EventType::DropFiles { ref files } => {
+                let paths = files.iter().map(PathBuf::from).collect::<Vec<_>>();
+                self.open_all(&paths, None)
+            }
+            EventType::Timer { id } if id == ANIMATION_TIMER => self.tick(),
+            EventType::Timer { id } if id == WATCH_TIMER => self.check_watch(),
+            _ => vec![],
+        }
+    }
+
+    /// opens what was asked for on the command line
+    pub fn start(&mut self, args: &Args) -> Vec<Effect> {
+        if args.sort.is_some() || args.reverse {
+            let this = &mut self.context();
+            let mut sort = this.get_sort();
+            match args.sort {
+                Some(key) => sort = SortOrder::new(key, args.reverse),
+                None => sort.reverse = true,
+            }
+            this.set_sort(sort);
+        }
+
+        let options = &mut self.scan_options;
+        options.recursive |= args.recursive;
+        if let Some(depth) = args.depth {
+            options.recursive = true;
+            options.max_depth = Some(depth);
+        }
+        options.ignore.extend(args.ignore.iter().cloned());
+
+        if args.seed.is_some() {
+            self.context().set_seed(args.seed);
+        }
+        // the index asked for is where the shuffle starts, otherwise it starts anywhere
+        let shuffle = args.shuffle && args.index.is_none();
+        if args.shuffle && !shuffle {
+            self.context().set_shuffle(true);
+        }
+
+        let mut effects = self.open_all(&args.paths, args.index);
+        if shuffle {
+            effects.extend(self.set_shuffle(true));
+        }
+        effects.extend(self.set_slideshow(args.slideshow));
+        effects
+    }
+
+    /// lists the directory of `path` (or `path` itself, if it's a directory) and shows
+    /// either `index`, `path` or the first file
+    pub fn open(&mut self, path: &Path, index: Option<usize>) -> Vec<Effect> {
+        let mut effects = self.list_directory(path);
+        let (len, found) = {
+            let this = self.context();
+            // nested files have their directory in front, so they don't match
+            let name = path.file_name().filter(|_| path.is_file());
+            let name = name.map(|name| name.to_string_lossy());
+            let found = name.and_then(|name| {
+                this.get_list_iter()
+                    .position(|entry| entry.display_name() == name)
+            });
+            (this.get_len(), found)
+        };
+        if len == 0 {
+            return effects;
+        }
+
+        if path.is_file() && found.is_none() {
+            warn!("{:?} isn't an accepted image, showing the first file instead", path);
+        }
+        let index = cmp::min(index.or(found).unwrap_or(0), len - 1);
+        effects.extend(self.select(index));
+        effects
+    }
+
+    /// opens a single path like `open`. more than that makes a list of just those files,
+    /// where directories add all of their images
+    pub fn open_all(&mut self, paths: &[PathBuf], index: Option<usize>) -> Vec<Effect> {
+        match paths.len() {
+            0 => return vec![],
+            1 => return self.open(&paths[0], index),
+            _ => {}
+        }
+
+        let mut effects = self.set_source(Source::Selection(paths.to_vec()));
+        let len = self.context().get_len();
+        if len > 0 {
+            effects.extend(self.select(cmp::min(index.unwrap_or(0), len - 1)));
+        }
+        effects
+    }
+
+    /// lists the files again. the same file stays selected, or if it's gone, the one that
+    /// took its place in the list is shown
+    pub fn refresh(&mut self) -> Vec<Effect> {
+        let source = match self.source {
+            Some(ref source) => source.clone(),
+            None => return vec![],
+        };
+        // the directory itself going away empties the list
+        let mut list = self.list_source(&source).unwrap_or_default();
+        if self.sorted {
+            self.context().get_sort().sort(&mut list);
+        }
+
+        let (kept, reload, len, index) = {
+            let this = &mut self.context();
+            let before = this.current().cloned();
+            let kept = match this.replace_list(list) {
+                Some(kept) => kept,
+                None => return vec![],
+            };
+            // the file that's shown was written to
+            let reload = match (before, this.current()) {
+                (Some(before), Some(now)) => !now.is_unchanged(&before),
+                _ => false,
+            };
+            (kept, reload, this.get_len(), this.get_index())
+        };
+        debug!("the files changed, there's {} now", len);
+
+        let mut effects = vec![self.populate()];
+        if len == 0 {
+            effects.extend(self.unload_image());
+        } else if kept && !reload {
+            effects.push(Effect::Select(index));
+        } else {
+            effects.extend(self.show_selected(Some(index)));
+        }
+        effects
+    }
+
+    /// moves to the next file every `interval`, or stops doing so
+    pub fn set_slideshow(&mut self, interval: Option<Duration>) -> Vec<Effect> {
+        debug!("slideshow interval: {:?}", interval);
+        match interval {
+            Some(interval) => self.start_slideshow(Some(interval)),
+            None => {
+                self.slideshow.stop();
+                vec![self.schedule_frame()]
+            }
+        }
+    }
+
+    #[cfg(test)]
+    /// instead of the ones from the config
+    pub fn set_key_bindings(&mut self, keys: KeyBindings) {
+        self.keys = keys
+    }
+
+    #[cfg(test)]
+    /// instead of the ones from the config. takes effect when the files are listed again
+    pub fn set_formats(&mut self, formats: Formats) {
+        self.formats = formats
+    }
+
+    /// does what a key bound to `action` does
+    pub fn perform(&mut self, action: Action) -> Vec<Effect> {
+        trace!("performing: {}", action);
+        match action {
+            Action::Previous => self.previous(),
+            Action::Next => self.next(),
+            Action::ToggleFileList => vec![Effect::ToggleFileList],
+            Action::SnapFileList => self.align_filelist(),
+            Action::Shuffle => self.toggle_shuffle(),
+            Action::Slideshow => self.toggle_slideshow(),
+            Action::NextSort => self.next_sort_key(),
+            Action::ReverseSort => self.reverse_sort(),
+
+            Action::ZoomFit => self.scale(Zoom::Fit),
+            Action::ZoomFill => self.scale(Zoom::Fill),
+            Action::Zoom50 => self.scale(Zoom::Fixed(0.5)),
+            Action::Zoom100 => self.scale(Zoom::Actual),
+            Action::Zoom150 => self.scale(Zoom::Fixed(1.5)),
+            Action::Zoom200 => self.scale(Zoom::Fixed(2.0)),
+
+            // for animated images
+            Action::PreviousFrame => self.previous_frame(),
+            Action::NextFrame => self.next_frame(),
+            Action::PlayPause => self.toggle_playing(),
+        }
+    }
+
+    #[cfg(test)]
+    /// used the next time the slideshow starts
+    pub fn set_slideshow_options(&mut self, options: ::slideshow::SlideshowOptions) {
+        self.slideshow.set_options(options)
+    }
+
+    /// goes through the list in a random order, moving on to the first file in it, or
+    /// goes back to the sorted order, staying on the current file
+    pub fn set_shuffle(&mut self, shuffle: bool) -> Vec<Effect> {
+        debug!("shuffle: {}", shuffle);
+        self.context().set_shuffle(shuffle);
+        if shuffle {
+            self.next()
+        } else {
+            vec![Effect::Redraw]
+        }
+    }
+
+    /// orders the list by `sort`, keeping the current file selected
+    pub fn sort_by(&mut self, sort: SortOrder) -> Vec<Effect> {
+        debug!("sorting the list by {}", sort);
+        self.sorted = true;
+        let (len, index) = {
+            let this = &mut self.context();
+            this.set_sort(sort);
+            this.sort_list();
+            (this.get_len(), this.get_index())
+        };
+
+        let mut effects = vec![self.populate()];
+        if len > 0 {
+            effects.push(Effect::Select(index));
+        }
+        effects
+    }
+
+    /// only shows the files that match `query`, see `Filter` for what it can be. if the
+    /// current file is filtered out, the first one that's left is shown
+    pub fn set_filter(&mut self, query: &str) -> Vec<Effect> {
+        self.query = if query.is_empty() && !self.typing {
+            None
+        } else {
+            Some(query.to_string())
+        };
+
+        let filter = match Filter::parse(query) {
+            Ok(filter) => filter,
+            Err(err) => {
+                // probably not finished typing it, so keep the last one
+                debug!("cannot filter by {:?}: {}", query, err);
+                return vec![self.populate()];
+            }
+        };
+
+        debug!("filtering by {:?}", query);
+        let (kept, len, index) = {
+            let this = &mut self.context();
+            let kept = this.set_filter(filter);
+            (kept, this.get_len(), this.get_index())
+        };
+
+        let mut effects = vec![self.populate()];
+        if len > 0 {
+            if kept {
+                effects.push(Effect::Select(index));
+            } else {
+                effects.extend(self.show_selected(Some(index)));
+            }
+        }
+        effects
+    }
+
+    /// whether a filter is being typed. keys don't do anything else until it's done
+    pub fn is_typing(&self) -> bool {
+        self.typing
+    }
+
+    /// the filter that's being typed or used
+    pub fn filter_query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// draws the current frame into `fb`, faded in over the last one if the slideshow is
+    /// moving on
+    pub fn render(&self, fb: &mut Framebuffer) {
+        match self.image {
+            Some(ref image) => fb.draw(image.current(), &self.viewport),
+            None => fb.clear(),
+        }
+
+        if let Some(ref fade) = self.fade {
+            let mut from = Framebuffer::new(fb.width() as i32, fb.height() as i32);
+            from.draw(fade.image.current(), &fade.viewport);
+            fb.fade(&from, fade.amount(self.clock.now()));
+        }
+    }
+
+    // `format` is what the file was sniffed as, if it was
+    fn load(&mut self, path: &Path, format: Option<ImageFormat>) -> Vec<Effect> {
+        let image = match ImageBuffer::open(path, format, self.exif.orientation) {
+            Ok(image) => image,
+            Err(err) => {
+                error!("cannot load image {:?}: {:?}", path, err);
+                // the image from before isn't left up, as if it was the selected one
+                return self.unload_image();
+            }
+        };
+
+        debug!(
+            "loaded {:?}: {}x{} with {} frames",
+            path,
+            image.width(),
+            image.height(),
+            image.frames()
+        );
+
+        self.animation = Animation::for_image(&image, self.clock.clone());
+        self.scrubber.set_frames(image.frames());
+        self.viewport.set_image_size(image.width(), image.height());
+        self.image = Some(image);
+
+        let mut effects = vec![if self.scrubber.is_visible() {
+            Effect::SetFrames(Some(self.scrubber.max()))
+        } else {
+            Effect::SetFrames(None)
+        }];
+        effects.extend(self.set_frame(0));
+        effects.push(self.schedule_frame());
+        effects
+    }
+
+    /// the animation timer fired
+    pub fn tick(&mut self) -> Vec<Effect> {
+        let mut effects = vec![];
+        if self.animation.tick() {
+            let frame = self.animation.frame();
+            trace!("animation frame: {}", frame);
+            effects = self.set_frame(frame);
+        }
+        if let Some(left) = self.fade.as_ref().map(|fade| fade.time_left(self.clock.now())) {
+            if left.is_none() {
+                self.fade = None;
+            }
+            effects.push(Effect::Redraw);
+        }
+
+        let at_end = self.context().is_at_end();
+        match self.slideshow.advance(self.clock.now(), self.is_animating(), at_end) {
+            Some(Advance::Next) => {
+                debug!("next slide");
+                effects.extend(self.next_slide());
+            }
+            Some(Advance::Stop) => {
+                debug!("the slideshow reached the end of the list");
+                effects.push(Effect::Redraw);
+            }
+            None => {}
+        }
+        effects.push(self.schedule_frame());
+        effects
+    }
+
+    /// the trackbar thumb is being dragged to `pos`
+    pub fn scrub_drag(&mut self, pos: usize) -> Vec<Effect> {
+        let seek = self.scrubber.drag(pos, self.animation.is_playing());
+        // hold the animation still while the thumb is being dragged
+        self.animation.pause();
+        match seek {
+            Some(frame) => self.seek_frame(frame),
+            None => vec![self.schedule_frame()],
+        }
+    }
+
+    /// the trackbar thumb was let go
+    pub fn scrub_release(&mut self) -> Vec<Effect> {
+        if !self.scrubber.release() {
+            return vec![];
+        }
+        self.animation.play();
+        vec![self.schedule_frame()]
+    }
+
+    /// the trackbar thumb was moved to `pos` without dragging
+    #[cfg(windows)]
+    pub fn scrub_seek(&mut self, pos: usize) -> Vec<Effect> {
+        match self.scrubber.seek(pos) {
+            Some(frame) => self.seek_frame(frame),
+            None => vec![],
+        }
+    }
+
+    /// selects `index` in the list and shows it
+    pub fn select(&mut self, index: usize) -> Vec<Effect> {
+        if index >= self.context().get_len() {
+            return vec![];
+        }
+        self.context().set_index(index);
+        self.show_selected(Some(index))
+    }
+
+    fn next(&mut self) -> Vec<Effect> {
+        let next = self.context().next();
+        self.show_selected(next)
+    }
+
+    fn previous(&mut self) -> Vec<Effect> {
+        let prev = self.context().previous();
+        self.show_selected(prev)
+    }
+
+    // moves on to the next file, fading it in over this one if the slideshow does that
+    fn next_slide(&mut self) -> Vec<Effect> {
+        let duration = match self.slideshow.options().transition_time() {
+            Some(duration) => duration,
+            None => return self.next(),
+        };
+
+        let image = self.image.take();
+        let viewport = self.viewport.clone();
+        let effects = self.next();
+        match image {
+            // there wasn't anything to move on to
+            Some(image) if effects.is_empty() => self.image = Some(image),
+            Some(image) if self.image.is_some() => {
+                self.fade = Some(Fade {
+                    image,
+                    viewport,
+                    start: self.clock.now(),
+                    duration,
+                })
+            }
+            _ => {}
+        }
+        effects
+    }
+
+    fn toggle_slideshow(&mut self) -> Vec<Effect> {
+        if self.slideshow.is_running() {
+            self.set_slideshow(None)
+        } else {
+            self.start_slideshow(None)
+        }
+    }
+
+    // with the interval in the options if there isn't one
+    fn start_slideshow(&mut self, interval: Option<Duration>) -> Vec<Effect> {
+        self.slideshow.start(self.clock.now(), interval);
+        let mut effects = vec![];
+        if self.slideshow.options().shuffle && !self.context().is_shuffled() {
+            // the current file was already shown, so it moves on to the first shuffled one
+            effects = self.set_shuffle(true);
+        }
+        effects.push(Effect::Redraw);
+        effects.push(self.schedule_frame());
+        effects
+    }
+
+    // whether the slideshow has to wait for the animation to play through
+    fn is_animating(&self) -> bool {
+        self.animation.is_playing() && self.animation.plays() == 0
+    }
+
+    fn toggle_shuffle(&mut self) -> Vec<Effect> {
+        let shuffle = !self.context().is_shuffled();
+        self.set_shuffle(shuffle)
+    }
+
+    // loads the file at `index` in the list
+    fn show_selected(&mut self, index: Option<usize>) -> Vec<Effect> {
+        let index = match index {
+            Some(index) => index,
+            None => return vec![],
+        };
+
+        let (path, format) = match self.context().get_entry(index) {
+            Some(entry) => (entry.path().to_path_buf(), entry.format()),
+            None => return vec![Effect::Select(index)],
+        };
+
+        // the slideshow waits for a whole interval on whatever's shown
+        self.slideshow.shown(self.clock.now());
+        self.fade = None;
+
+        let mut effects = vec![Effect::Select(index)];
+        effects.extend(self.load(&path, format));
+
+        // saves reading the header again later
+        if let Some(ref image) = self.image {
+            if let Some(entry) = self.context().get_entry(index) {
+                entry.set_dimensions(image.width(), image.height());
+            }
+        }
+        effects
+    }
+
+    // there's nothing left to show
+    fn unload_image(&mut self) -> Vec<Effect> {
+        self.image = None;
+        self.fade = None;
+        self.animation = Animation::new(vec![], Repeat::Finite(1), self.clock.clone());
+        self.scrubber.set_frames(0);
+        vec![Effect::SetFrames(None), Effect::Redraw, self.schedule_frame()]
+    }
+
+    // the watch timer fired
+    fn check_watch(&mut self) -> Vec<Effect> {
+        let now = self.clock.now();
+        if self.watcher.as_mut().is_some_and(|w| w.changed(now)) {
+            self.refresh()
+        } else {
+            vec![]
+        }
+    }
+
+    fn next_sort_key(&mut self) -> Vec<Effect> {
+        let sort = self.context().get_sort();
+        self.sort_by(SortOrder::new(sort.key.next(), sort.reverse))
+    }
+
+    fn reverse_sort(&mut self) -> Vec<Effect> {
+        let sort = self.context().get_sort();
+        self.sort_by(SortOrder::new(sort.key, !sort.reverse))
+    }
+
+    fn align_filelist(&mut self) -> Vec<Effect> {
+        debug!("aligning filelist");
+        let this = &mut self.context();
+        let snap = this.get_snap();
+        this.set_snap(!snap);
+        vec![Effect::AlignFileList]
+    }
+
+    fn scale(&mut self, zoom: Zoom) -> Vec<Effect> {
+        debug!("scaling to {:?}", zoom);
+        self.viewport.set_zoom(zoom);
+        vec![Effect::Redraw]
+    }
+
+    fn set_frame(&mut self, frame: usize) -> Vec<Effect> {
+        if let Some(image) = self.image.as_mut() {
+            image.seek(frame);
+        }
+        self.context().set_frame_index(frame);
+
+        let mut effects = vec![];
+        if let Some(pos) = self.scrubber.follow(frame) {
+            effects.push(Effect::SetFramePosition(pos));
+        }
+        effects.push(Effect::Redraw);
+        effects
+    }
+
+    fn seek_frame(&mut self, frame: usize) -> Vec<Effect> {
+        let frame = self.animation.seek(frame);
+        trace!("seeking to frame: {}", frame);
+        let mut effects = self.set_frame(frame);
+        effects.push(self.schedule_frame());
+        effects
+    }
+
+    // when the animation timer should fire next, if at all. the slideshow shares it
+    fn schedule_frame(&self) -> Effect {
+        let now = self.clock.now();
+        let slide = self.slideshow.time_to_next(now, self.is_animating());
+        let fade = self.fade.as_ref().map(|fade| {
+            let left = fade.time_left(now).unwrap_or_default();
+            cmp::min(left, FADE_STEP)
+        });
+
+        Effect::Schedule(
+            vec![self.animation.time_to_next(), slide, fade]
+                .into_iter()
+                .flatten()
+                .min(),
+        )
+    }
+
+    fn previous_frame(&mut self) -> Vec<Effect> {
+        let frame = self.animation.step_back();
+        debug!("previous frame: {}", frame);
+        let mut effects = self.set_frame(frame);
+        effects.push(self.schedule_frame());
+        effects
+    }
+
+    fn next_frame(&mut self) -> Vec<Effect> {
+        let frame = self.animation.step_forward();
+        debug!("next frame: {}", frame);
+        let mut effects = self.set_frame(frame);
+        effects.push(self.schedule_frame());
+        effects
+    }
+
+    fn toggle_playing(&mut self) -> Vec<Effect> {
+        let playing = self.animation.toggle();
+        debug!("toggling playing: {}", playing);
+        vec![self.schedule_frame()]
+    }
+
+    fn on_key_down(&mut self, key: Key, modifiers: Modifiers, repeat: bool) -> Vec<Effect> {
+        if self.typing {
+            return vec![];
+        }
+
+        match self.keys.action(Input::Key(key), modifiers) {
+            Some(action) if !repeat || action.repeats() => {
+                trace!("on keydown: {:?} {:?} is {}", key, modifiers, action);
+                self.perform(action)
+            }
+            _ => vec![],
+        }
+    }
+
+    // `/` starts typing a filter, enter finishes it and escape clears it
+    fn on_char(&mut self, c: char) -> Vec<Effect> {
+        if !self.typing {
+            return match c {
+                '/' => {
+                    self.typing = true;
+                    let query = self.query.take().unwrap_or_default();
+                    self.set_filter(&query)
+                }
+                '\x1b' if self.query.is_some() => self.set_filter(""),
+                _ => vec![],
+            };
+        }
+
+        let mut query = self.query.clone().unwrap_or_default();
+        match c {
+            '\r' | '\n' => self.typing = false,
+            '\x1b' => {
+                self.typing = false;
+                query.clear();
+            }
+            '\x08' | '\x7f' => {
+                query.pop();
+            }
+            c if !c.is_control() => query.push(c),
+            _ => return vec![],
+        }
+        self.set_filter(&query)
+    }
+
+    fn on_mouse_down(&mut self, button: &MouseButton, pos: (i32, i32)) -> Vec<Effect> {
+        // middle click is for panning
+        // right click will do nothing
+        // left click maybe gets forwarded to containing controls?
+        trace!("click: {:?} {},{}", button, pos.0, pos.1);
+
+        match *button {
+            MouseButton::Middle => self.viewport.begin_pan(pos),
+            // the side buttons can be bound like keys
+            MouseButton::X1 | MouseButton::X2 => {
+                let input = Input::Button(*button);
+                if let Some(action) = self.keys.action(input, Modifiers::default()) {
+                    return self.perform(action);
+                }
+            }
+            _ => {}
+        }
+        vec![]
+    }
+
+    fn on_mouse_up(&mut self, button: &MouseButton, pos: (i32, i32)) -> Vec<Effect> {
+        trace!("release: {:?} {},{}", button, pos.0, pos.1);
+
+        if *button == MouseButton::Middle {
+            self.viewport.end_pan();
+        }
+        vec![]
+    }
+
+    fn on_mouse_move(&mut self, pos: (i32, i32)) -> Vec<Effect> {
+        if self.viewport.is_panning() && self.viewport.pan_to(pos) {
+            trace!("panned to: {:?}", self.viewport.image_rect());
+            return vec![Effect::Redraw];
+        }
+        vec![]
+    }
+
+    fn on_mouse_wheel(&mut self, delta: i16, pos: (i32, i32)) -> Vec<Effect> {
+        // zoom in and out, around the cursor (in client coords)
+        trace!("scroll: {:?} {},{}", delta, pos.0, pos.1);
+
+        let factor = ZOOM_STEP.powf(f64::from(delta) / f64::from(WHEEL_DELTA));
+        self.viewport.zoom_at(factor, pos);
+        vec![Effect::Redraw]
+    }
+
+    fn on_resize(&mut self, size: (i32, i32)) -> Vec<Effect> {
+        // resize the canvas
+        trace!("resized: {:?}", size);
+        self.viewport.set_window_size(size.0, size.1);
+        if let Some(ref mut fade) = self.fade {
+            fade.viewport.set_window_size(size.0, size.1);
+        }
+        vec![Effect::Redraw]
+    }
+
+    // shows the dropped file, along with the rest of its directory
+    fn on_drop_file<P: Into<PathBuf>>(&mut self, path: P) -> Vec<Effect> {
+        self.open(&path.into(), None)
+    }
+
+    // replaces the list with the directory `path` is in (or `path`, if it's a directory)
+    fn list_directory(&mut self, path: &Path) -> Vec<Effect> {
+        let dir = if path.is_dir() {
+            path
+        } else {
+            match parent_dir(path) {
+                Some(dir) => dir,
+                None => {
+                    error!("cannot get the parent of: {:?}", path);
+                    return vec![];
+                }
+            }
+        };
+        self.set_source(Source::Directory(dir.to_path_buf()))
+    }
+
+    // replaces the list with what's in `source`. directories are sorted, a selection stays
+    // in the order it was asked for
+    fn set_source(&mut self, source: Source) -> Vec<Effect> {
+        let mut list = match self.list_source(&source) {
+            Some(list) => list,
+            None => return vec![],
+        };
+
+        let title = match source {
+            Source::Directory(ref dir) => {
+                self.context().get_sort().sort(&mut list);
+                dir.to_string_lossy().into_owned()
+            }
+            Source::Selection(_) => CUSTOM_SELECTION.to_string(),
+        };
+        self.sorted = match source {
+            Source::Directory(_) => true,
+            Source::Selection(_) => false,
+        };
+        self.source = Some(source);
+        self.set_list(&title, &list)
+    }
+
+    // the files in `source`. the directories they're in get watched for changes, what's
+    // read of the files is kept for the next time
+    fn list_source(&mut self, source: &Source) -> Option<Vec<Entry>> {
+        let mut dirs = vec![];
+        let mut known = HashMap::new();
+        let mut skipped = 0;
+        let list = match source {
+            Source::Directory(dir) => match self.scan_directory(dir) {
+                Some(scan) => {
+                    dirs = scan.dirs;
+                    skipped = scan.skipped.len();
+                    remember(&mut known, scan.others);
+                    remember(&mut known, scan.entries.iter().cloned());
+                    scan.entries
+                }
+                None => {
+                    // so that it's noticed if it's made again
+                    let mut parent = parent_dir(dir);
+                    while let Some(dir) = parent.filter(|dir| !dir.is_dir()) {
+                        parent = parent_dir(dir);
+                    }
+                    let dirs = parent.map(Path::to_path_buf).into_iter().collect::<Vec<_>>();
+                    self.watcher.get_or_insert_with(Watcher::new).watch(&dirs);
+                    self.skipped = 0;
+                    return None;
+                }
+            },
+            Source::Selection(paths) => {
+                let mut list: Vec<Entry> = vec![];
+                for path in paths {
+                    let mut add = |entry: Entry| {
+                        if !list.iter().any(|e| e.path() == entry.path()) {
+                            list.push(entry)
+                        }
+                    };
+
+                    if path.is_dir() {
+                        if let Some(scan) = self.scan_directory(path) {
+                            dirs.extend(scan.dirs);
+                            skipped += scan.skipped.len();
+                            remember(&mut known, scan.others);
+                            remember(&mut known, scan.entries.iter().cloned());
+                            scan.entries.into_iter().for_each(add)
+                        }
+                    } else if !path.is_file() {
+                        warn!("{:?} doesn't exist, skipping it", path);
+                    } else {
+                        let mut entry = Entry::new(path.clone());
+                        entry.set_orient(self.exif.orientation);
+                        let entry = match self.known.get(path) {
+                            Some(old) if old.is_unchanged(&entry) => old.clone(),
+                            _ => entry,
+                        };
+                        remember(&mut known, Some(entry.clone()));
+                        match entry.format() {
+                            Some(format) if self.formats.is_enabled(format) => {}
+                            Some(format) => {
+                                warn!("{:?} is {:?}, which is turned off", path, format);
+                                continue;
+                            }
+                            None => {
+                                warn!("{:?} isn't an image we can show, skipping it", path);
+                                continue;
+                            }
+                        }
+                        add(entry);
+                        dirs.extend(parent_dir(path).map(Path::to_path_buf));
+                    }
+                }
+                debug!("custom selection of {} files", list.len());
+                list
+            }
+        };
+        debug!("got {} files", list.len());
+
+        dirs.sort();
+        dirs.dedup();
+        self.watcher.get_or_insert_with(Watcher::new).watch(&dirs);
+        self.known = known;
+        self.skipped = skipped;
+        Some(list)
+    }
+
+    // the images in `dir`, in the formats that are on. the ones that aren't on are with
+    // the other files. whatever couldn't be read was skipped, and is in the log
+    fn scan_directory(&self, dir: &Path) -> Option<Scan> {
+        match rescan(dir, &self.scan_options, &self.known) {
+            Ok(mut scan) => {
+                for entry in &mut scan.entries {
+                    entry.set_orient(self.exif.orientation);
+                }
+                let formats = &self.formats;
+                let (on, off): (Vec<_>, Vec<_>) = scan
+                    .entries
+                    .into_iter()
+                    .partition(|entry| entry.format().is_some_and(|f| formats.is_enabled(f)));
+                scan.entries = on;
+                scan.others.extend(off);
+                Some(scan)
+            }
+            Err(err) => {
+                error!("cannot get a file listing for {:?}: {}", dir, err);
+                None
+            }
+        }
+    }
+
+    // replaces the list, starting over at the top
+    fn set_list(&mut self, title: &str, list: &[Entry]) -> Vec<Effect> {
+        {
+            let this = &mut self.context();
+            this.clear_list();
+            this.set_index(0);
+            this.extend_list(list);
+        }
+        self.title = title.to_string();
+        // a new list isn't filtered
+        self.query = None;
+        self.typing = false;
+        vec![self.populate()]
+    }
+
+    // the file list, in the order that the context has it, with the filter and how many
+    // files were skipped in the title
+    fn populate(&self) -> Effect {
+        let files = self
+            .context()
+            .get_list_iter()
+            .map(|entry| (entry.display_name(), entry.size() as usize))
+            .collect();
+
+        let mut title = match self.query {
+            Some(ref query) => format!("{} /{}", self.title, query),
+            None => self.title.clone(),
+        };
+        if self.skipped > 0 {
+            title += &format!(" ({} skipped)", self.skipped);
+        }
+        Effect::Populate { title, files }
+    }
+}
+
+// keeps `entries` by their path
+fn remember<I: IntoIterator<Item = Entry>>(known: &mut HashMap<PathBuf, Entry>, entries: I) {
+    known.extend(
+        entries
+            .into_iter()
+            .map(|entry| (entry.path().to_path_buf(), entry)),
+    )
+}
+
+// the directory a file is in. a bare file name is in the current directory
+fn parent_dir(path: &Path) -> Option<&Path> {
+    match path.parent()? {
+        dir if dir.as_os_str().is_empty() => Some(Path::new(".")),
+        dir => Some(dir),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use headless::{Headless, ManualClock};
+    use render::BACKGROUND;
+    use sort::SortKey;
+    use std::fs;
+    use testutil::{drop, drop_path, key, TempDir};
+
+    #[test]
+    fn play_animation() {
+        let clock = ManualClock::default();
+        let mut app = Headless::with_clock(8, 6, clock.clone());
+        let timer = EventType::Timer {
+            id: ANIMATION_TIMER,
+        };
+
+        app.run(&[drop("test.gif")]);
+        assert_eq!(app.trackbar(), Some(2));
+        assert_eq!(app.timer(), Some(Duration::from_millis(100)));
+        assert_eq!(app.framebuffer().pixel(0, 0), Some(0x00_FF_00_00));
+
+        clock.advance(Duration::from_millis(100));
+        app.handle(&timer);
+        assert_eq!(app.context().get_frame_index(), 1);
+        assert_eq!(app.trackbar_pos(), 1);
+        assert_eq!(app.timer(), Some(Duration::from_millis(200)));
+        assert_eq!(app.framebuffer().pixel(0, 0), Some(0x00_00_FF_00));
+
+        // pausing stops the timer, stepping still works
+        app.run(&[key(Key::Space), key(Key::Right)]);
+        assert_eq!(app.timer(), None);
+        assert_eq!(app.trackbar_pos(), 2);
+        assert_eq!(app.framebuffer().pixel(0, 0), Some(0x00_00_00_FF));
+
+        clock.advance(Duration::from_secs(10));
+        app.handle(&timer);
+        assert_eq!(app.context().get_frame_index(), 2);
+
+        // a still image hides the trackbar again
+        app.handle(&drop("test.jpg"));
+        assert_eq!(app.trackbar(), None);
+        assert_eq!(app.timer(), None);
+    }
+
+    #[test]
+    fn drop_selects_file() {
+        let mut app = Headless::new(80, 60);
+        for file in &["test.gif", "test.jpg", "test.png"] {
+            app.handle(&drop(file));
+            let index = app.selected().unwrap();
+            assert_eq!(app.files()[index].0, *file);
+            assert_eq!(app.context().get_index(), index);
+        }
+
+        // something that isn't an image shows the first file instead
+        let dir = TempDir::new("drop");
+        for file in &["test.gif", "test.jpg", "test.png"] {
+            fs::copy(Path::new("testdata").join(file), dir.join(file)).unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "not an image").unwrap();
+        app.handle(&drop_path(&dir.join("test.png")));
+        assert_eq!(app.selected(), Some(2));
+        app.handle(&drop_path(&dir.join("notes.txt")));
+        assert_eq!(app.files().len(), 3);
+        assert_eq!(app.selected(), Some(0));
+        assert_eq!(app.viewer().image().unwrap().frames(), 3);
+
+        // as does a directory
+        app.handle(&drop("test.jpg"));
+        app.handle(&EventType::DropFile {
+            file: "testdata".into(),
+        });
+        assert_eq!(app.selected(), Some(0));
+    }
+
+    #[test]
+    fn formats_turned_off() {
+        let mut app = Headless::new(80, 60);
+        let mut formats = Formats::default();
+        formats.set_enabled(ImageFormat::GIF, false);
+        app.viewer_mut().set_formats(formats);
+
+        app.handle(&drop("test.png"));
+        let names = app.files().iter().map(|f| f.0.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["test.jpg", "test.png"]);
+        assert_eq!(app.selected(), Some(1));
+
+        // it isn't in the list, so the first one is shown
+        app.handle(&drop("test.gif"));
+        assert_eq!(app.selected(), Some(0));
+        assert_eq!(app.files().len(), 2);
+    }
+
+    #[test]
+    fn drop_many() {
+        let mut app = Headless::new(80, 60);
+        // a file that isn't an image, and one that doesn't exist, are skipped
+        let files = [
+            "testdata/test.png",
+            "Cargo.toml",
+            "testdata/missing.png",
+            "testdata/test.gif",
+        ];
+        let files = files.iter().map(|file| file.to_string()).collect();
+        app.handle(&EventType::DropFiles { files });
+
+        assert_eq!(app.title(), Some(CUSTOM_SELECTION));
+        let names = app.files().iter().map(|(s, _)| s.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["test.png", "test.gif"]);
+        assert_eq!(app.selected(), Some(0));
+        assert_eq!(app.trackbar(), None);
+
+        app.handle(&key(Key::D));
+        assert_eq!(app.trackbar(), Some(2));
+
+        // directories add their images, but only once
+        let files = vec!["testdata/test.png".into(), "testdata".into()];
+        app.handle(&EventType::DropFiles { files });
+        assert_eq!(app.files().len(), 3);
+        assert_eq!(app.files()[0].0, "test.png");
+
+        // a single file is like any other drop
+        let files = vec!["testdata/test.gif".into()];
+        app.handle(&EventType::DropFiles { files });
+        assert_eq!(app.title(), Some("testdata"));
+    }
+
+    #[test]
+    fn navigation_shows_file() {
+        let mut app = Headless::new(80, 60);
+        app.handle(&drop("test.jpg"));
+        assert_eq!(app.viewer().image().unwrap().frames(), 1);
+
+        for _ in 0..3 {
+            let index = app.context().get_index();
+            if app.files()[index].0 == "test.gif" {
+                break;
+            }
+            app.handle(&key(Key::D));
+        }
+        assert_eq!(app.viewer().image().unwrap().frames(), 3);
+        assert_eq!(app.trackbar(), Some(2));
+    }
+
+    #[test]
+    fn corrupt_file() {
+        let dir = TempDir::new("corrupt");
+        fs::copy("testdata/test.gif", dir.join("test.gif")).unwrap();
+        // it looks like a png, but there's nothing after the signature
+        fs::write(dir.join("broken.png"), b"\x89PNG\r\n\x1a\n garbage").unwrap();
+
+        let mut app = Headless::new(80, 60);
+        app.handle(&drop_path(&dir.join("test.gif")));
+        assert_eq!(app.selected(), Some(1));
+        assert!(app.timer().is_some());
+        app.handle(&key(Key::D));
+
+        // the gif doesn't stay up, or keep playing, while the broken file is selected
+        assert_eq!(app.selected(), Some(0));
+        assert!(app.viewer().image().is_none());
+        assert_eq!(app.timer(), None);
+        assert_eq!(app.trackbar(), None);
+        assert!(app.framebuffer().pixels().iter().all(|&px| px == BACKGROUND));
+    }
+
+    #[test]
+    fn start_from_args() {
+        let mut app = Headless::new(80, 60);
+        let args = Args {
+            paths: vec!["testdata/test.gif".into()],
+            ..Args::default()
+        };
+        app.start(&args);
+        let index = app.selected().unwrap();
+        assert_eq!(app.files()[index].0, "test.gif");
+        assert_eq!(app.viewer().image().unwrap().frames(), 3);
+
+        // an index wins over the file, and is kept in range
+        let args = Args {
+            paths: vec!["testdata".into()],
+            index: Some(10),
+            sort: Some(SortKey::Name),
+            ..Args::default()
+        };
+        app.start(&args);
+        assert_eq!(app.selected(), Some(2));
+        assert_eq!(app.files()[2].0, "test.png");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skipped_in_title() {
+        let dir = TempDir::new("skipped");
+        fs::copy("testdata/test.png", dir.join("a.png")).unwrap();
+        ::std::os::unix::fs::symlink(dir.join("missing.png"), dir.join("broken.png")).unwrap();
+
+        let mut app = Headless::new(80, 60);
+        app.handle(&drop_path(&dir));
+        let title = format!("{} (1 skipped)", dir.to_string_lossy());
+        assert_eq!(app.title(), Some(title.as_str()));
+        assert_eq!(app.files().len(), 1);
+    }
+
+    #[test]
+    fn zoom_and_pan() {
+        let mut app = Headless::new(80, 60);
+        app.run(&[
+            drop("test.png"),
+            EventType::MouseWheel {
+                delta: WHEEL_DELTA,
+                x: 0,
+                y: 0,
+            },
+        ]);
+        // zoomed in around the top left corner
+        assert!((app.viewer().viewport().scale() - 11.0).abs() < 1e-9);
+        assert_eq!(app.framebuffer().pixel(10, 10), Some(0x00_00_00_80));
+        let right = app.framebuffer().pixel(70, 0);
+
+        app.run(&[
+            EventType::MouseDown {
+                button: MouseButton::Middle,
+                x: 40,
+                y: 30,
+            },
+            EventType::MouseMove { x: 32, y: 30 },
+            EventType::MouseUp {
+                button: MouseButton::Middle,
+                x: 32,
+                y: 30,
+            },
+        ]);
+        // can only pan as far as the right edge
+        assert_eq!(app.viewer().viewport().image_rect().0, -8.0);
+        assert_ne!(app.framebuffer().pixel(70, 0), right);
+
+        // moving without a button held does nothing
+        assert!(app.handle(&EventType::MouseMove { x: 0, y: 0 }).is_empty());
+    }
+}
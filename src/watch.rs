@@ -0,0 +1,301 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+// how often the files get listed again, when there's nothing to tell us they changed
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Says when the watched directories might have changed.
+///
+/// On linux this is inotify. Elsewhere, or when inotify can't be had, it says so every
+/// couple of seconds, and it's up to the caller to compare the listings.
+#[derive(Debug)]
+pub struct Watcher {
+    #[cfg(target_os = "linux")]
+    inotify: Option<inotify::Inotify>,
+    last_poll: Option<Duration>,
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Watcher {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "linux")]
+            inotify: inotify::Inotify::new(),
+            last_poll: None,
+        }
+    }
+
+    #[cfg(test)]
+    /// a watcher that only ever polls
+    pub fn polling() -> Self {
+        Self {
+            #[cfg(target_os = "linux")]
+            inotify: None,
+            last_poll: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn is_polling(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            if self.inotify.is_some() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// watches `dirs`, instead of what was watched before
+    pub fn watch(&mut self, dirs: &[PathBuf]) {
+        trace!("watching {} directories", dirs.len());
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(ref mut inotify) = self.inotify {
+                inotify.watch(dirs);
+            }
+        }
+    }
+
+    /// whether anything might have changed since the last time. `now` is on any clock,
+    /// as long as it's always the same one
+    pub fn changed(&mut self, now: Duration) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(ref mut inotify) = self.inotify {
+                return inotify.read_events();
+            }
+        }
+
+        match self.last_poll {
+            Some(last) if now < last + POLL_INTERVAL => false,
+            // the first time only starts the interval
+            Some(_) | None => self.last_poll.replace(now).is_some(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::raw::c_int;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+
+    use libc;
+
+    // anything that can change a listing
+    const MASK: u32 = libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_ATTRIB
+        | libc::IN_DELETE_SELF
+        | libc::IN_MOVE_SELF;
+
+    // the size of `struct inotify_event`, without the name
+    const EVENT_SIZE: usize = 16;
+
+    #[derive(Debug)]
+    pub struct Inotify {
+        fd: c_int,
+        watches: HashMap<PathBuf, c_int>,
+    }
+
+    impl Inotify {
+        pub fn new() -> Option<Self> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                warn!("cannot use inotify: {}", io::Error::last_os_error());
+                return None;
+            }
+            Some(Self {
+                fd,
+                watches: HashMap::new(),
+            })
+        }
+
+        pub fn watch(&mut self, dirs: &[PathBuf]) {
+            let gone = self
+                .watches
+                .keys()
+                .filter(|dir| !dirs.contains(dir))
+                .cloned()
+                .collect::<Vec<_>>();
+            for dir in gone {
+                if let Some(wd) = self.watches.remove(&dir) {
+                    unsafe { libc::inotify_rm_watch(self.fd, wd) };
+                }
+            }
+
+            for dir in dirs {
+                if self.watches.contains_key(dir) {
+                    continue;
+                }
+                let path = match CString::new(dir.as_os_str().as_bytes()) {
+                    Ok(path) => path,
+                    Err(_) => continue,
+                };
+                match unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), MASK) } {
+                    wd if wd < 0 => {
+                        warn!("cannot watch {:?}: {}", dir, io::Error::last_os_error())
+                    }
+                    wd => {
+                        self.watches.insert(dir.clone(), wd);
+                    }
+                }
+            }
+        }
+
+        /// whether any events came in since the last time
+        pub fn read_events(&mut self) -> bool {
+            let mut buf = [0u8; 4096];
+            let mut any = false;
+            loop {
+                let n = unsafe { libc::read(self.fd, buf.as_mut_ptr() as *mut _, buf.len()) };
+                if n <= 0 {
+                    break; // there's nothing left
+                }
+                any = true;
+                self.forget_removed(&buf[..n as usize]);
+            }
+            any
+        }
+
+        // a directory that was deleted stops being watched, so it has to be added again
+        // if it comes back
+        fn forget_removed(&mut self, mut buf: &[u8]) {
+            let u32_at = |buf: &[u8], pos: usize| {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(&buf[pos..pos + 4]);
+                u32::from_ne_bytes(bytes)
+            };
+
+            while buf.len() >= EVENT_SIZE {
+                let wd = u32_at(buf, 0) as c_int;
+                let mask = u32_at(buf, 4);
+                let len = u32_at(buf, 12) as usize;
+                if mask & libc::IN_IGNORED != 0 {
+                    self.watches.retain(|_, w| *w != wd);
+                }
+                buf = &buf[(EVENT_SIZE + len).min(buf.len())..];
+            }
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use event::EventType;
+    use headless::{Headless, ManualClock};
+    use std::fs;
+    use std::path::Path;
+    use testutil::{drop_path, TempDir};
+    use viewer::WATCH_TIMER;
+
+    #[test]
+    fn polling() {
+        let mut watcher = Watcher::polling();
+        assert!(watcher.is_polling());
+        let secs = Duration::from_secs;
+        assert!(!watcher.changed(secs(0)));
+        assert!(!watcher.changed(secs(1)));
+        assert!(watcher.changed(secs(2)));
+        assert!(!watcher.changed(secs(3)));
+        assert!(watcher.changed(secs(5)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn inotify() {
+        let dir = TempDir::new("watch");
+        fs::create_dir(dir.join("sub")).unwrap();
+
+        let mut watcher = Watcher::new();
+        assert!(!watcher.is_polling());
+        watcher.watch(&[dir.to_path_buf(), dir.join("sub")]);
+        assert!(!watcher.changed(Duration::default()));
+
+        fs::write(dir.join("a.png"), b"").unwrap();
+        assert!(watcher.changed(Duration::default()));
+        assert!(!watcher.changed(Duration::default()));
+
+        // a directory that's deleted and made again is still watched
+        fs::remove_dir(dir.join("sub")).unwrap();
+        assert!(watcher.changed(Duration::default()));
+        fs::create_dir(dir.join("sub")).unwrap();
+        assert!(watcher.changed(Duration::default()));
+        watcher.watch(&[dir.to_path_buf(), dir.join("sub")]);
+        fs::write(dir.join("sub/b.png"), b"").unwrap();
+        assert!(watcher.changed(Duration::default()));
+
+        // and one that isn't watched anymore isn't
+        watcher.watch(&[dir.to_path_buf()]);
+        watcher.changed(Duration::default());
+        fs::write(dir.join("sub/c.png"), b"").unwrap();
+        assert!(!watcher.changed(Duration::default()));
+    }
+
+    #[test]
+    fn watch_folder() {
+        let dir = TempDir::new("watch-folder");
+        for (from, to) in &[("test.png", "a.png"), ("test.gif", "b.gif"), ("test.jpg", "c.jpg")] {
+            fs::copy(Path::new("testdata").join(from), dir.join(to)).unwrap();
+        }
+
+        let clock = ManualClock::default();
+        let mut app = Headless::with_clock(80, 60, clock.clone());
+        let watch = EventType::Timer { id: WATCH_TIMER };
+        // when polling, the first check only starts the interval
+        let check = |app: &mut Headless<ManualClock>| {
+            clock.advance(Duration::from_secs(2));
+            app.handle(&watch);
+        };
+
+        app.handle(&drop_path(&dir.join("b.gif")));
+        app.handle(&watch);
+        assert_eq!(app.selected(), Some(1));
+        assert_eq!(app.trackbar(), Some(2));
+
+        // the same file stays selected
+        fs::copy("testdata/test.png", dir.join("0.png")).unwrap();
+        check(&mut app);
+        assert_eq!(app.files().len(), 4);
+        assert_eq!(app.selected(), Some(2));
+        assert_eq!(app.context().current().unwrap().name(), "b.gif");
+
+        // the one after it takes the place of the one that's gone
+        fs::remove_file(dir.join("b.gif")).unwrap();
+        check(&mut app);
+        assert_eq!(app.files().len(), 3);
+        assert_eq!(app.selected(), Some(2));
+        assert_eq!(app.context().current().unwrap().name(), "c.jpg");
+        assert_eq!(app.trackbar(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+        check(&mut app);
+        assert!(app.files().is_empty());
+        assert!(app.viewer().image().is_none());
+
+        // it's still looked for after it was deleted
+        fs::create_dir(&dir).unwrap();
+        fs::copy("testdata/test.png", dir.join("d.png")).unwrap();
+        check(&mut app);
+        assert_eq!(app.files().len(), 1);
+    }
+}
@@ -0,0 +1,297 @@
+use std::panic;
+use std::time::Duration;
+
+use image::{self, ImageError, ImageFormat, ImageResult, RgbaImage};
+
+use compose::{Animated, Blend, Dispose, Part};
+use imageview::Repeat;
+
+/// the animation in the webp `data`. `None` if it's just a still
+///
+/// The webp decoder only knows about a single lossy frame, so every frame is put back
+/// together into a webp of its own. Alpha is only kept when it's stored uncompressed
+pub fn decode(data: &[u8]) -> ImageResult<Option<Animated>> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err(format_error("not a webp"));
+    }
+    let chunks = chunks(&data[12..])?;
+
+    let header = match chunks.first() {
+        Some(&(ty, header)) if ty == b"VP8X" => header,
+        // the simple format, which the webp decoder handles on its own
+        _ => return Ok(None),
+    };
+    if header.len() < 10 || header[0] & 0x02 == 0 {
+        return Ok(None);
+    }
+
+    let repeat = match chunks.iter().find(|&&(ty, _)| ty == b"ANIM") {
+        Some(&(_, anim)) if anim.len() >= 6 => match le16(&anim[4..]) {
+            0 => Repeat::Infinite,
+            n => Repeat::Finite(u32::from(n)),
+        },
+        _ => return Err(format_error("no ANIM chunk")),
+    };
+
+    let parts = chunks
+        .iter()
+        .filter(|&&(ty, _)| ty == b"ANMF")
+        .map(|&(_, frame)| part(frame))
+        .collect::<ImageResult<Vec<_>>>()?;
+
+    Ok(Some(Animated {
+        width: le24(&header[4..]) + 1,
+        height: le24(&header[7..]) + 1,
+        repeat,
+        parts,
+    }))
+}
+
+/// the size of the canvas, from the start of a webp file. `None` if it's not something
+/// that says so up front
+pub fn canvas_size(header: &[u8]) -> Option<(u32, u32)> {
+    match header.get(12..16)? {
+        b"VP8X" if header.len() >= 30 => {
+            Some((le24(&header[24..]) + 1, le24(&header[27..]) + 1))
+        }
+        b"VP8 " if header.len() >= 30 => Some((
+            u32::from(le16(&header[26..]) & 0x3FFF),
+            u32::from(le16(&header[28..]) & 0x3FFF),
+        )),
+        _ => None,
+    }
+}
+
+// an ANMF chunk
+fn part(data: &[u8]) -> ImageResult<Part> {
+    if data.len() < 16 {
+        return Err(format_error("short ANMF chunk"));
+    }
+    let (width, height) = (le24(&data[6..]) + 1, le24(&data[9..]) + 1);
+
+    let mut alpha = None;
+    let mut buffer = None;
+    for (ty, data) in chunks(&data[16..])? {
+        match ty {
+            b"ALPH" => alpha = Some(data),
+            b"VP8 " => buffer = Some(decode_vp8(data)?),
+            b"VP8L" => return Err(format_error("lossless frames aren't supported")),
+            _ => {}
+        }
+    }
+    let mut buffer = buffer.ok_or_else(|| format_error("frame without an image"))?;
+    if buffer.dimensions() != (width, height) {
+        return Err(format_error("frame is the wrong size"));
+    }
+    if let Some(alpha) = alpha {
+        apply_alpha(&mut buffer, alpha);
+    }
+
+    let flags = data[15];
+    Ok(Part {
+        buffer,
+        left: le24(data) * 2,
+        top: le24(&data[3..]) * 2,
+        delay: Duration::from_millis(u64::from(le24(&data[12..]))),
+        blend: if flags & 0x02 == 0 {
+            Blend::Over
+        } else {
+            Blend::Source
+        },
+        dispose: if flags & 0x01 == 0 {
+            Dispose::Keep
+        } else {
+            Dispose::Background
+        },
+    })
+}
+
+// the lossy bitstream, as a webp of its own
+fn decode_vp8(data: &[u8]) -> ImageResult<RgbaImage> {
+    let mut webp = b"RIFF\0\0\0\0WEBP".to_vec();
+    write_chunk(&mut webp, b"VP8 ", data);
+    let size = (webp.len() - 8) as u32;
+    webp[4..8].copy_from_slice(&size.to_le_bytes());
+
+    // the vp8 decoder panics on some frame sizes, rather than giving an error
+    match panic::catch_unwind(|| image::load_from_memory_with_format(&webp, ImageFormat::WEBP)) {
+        Ok(image) => Ok(image?.to_rgba()),
+        Err(_) => Err(format_error("cannot decode frame")),
+    }
+}
+
+// the ALPH chunk: a byte of flags, then a value per pixel
+fn apply_alpha(buffer: &mut RgbaImage, data: &[u8]) {
+    let (width, height) = buffer.dimensions();
+    let (flags, data) = match data.split_first() {
+        Some((&flags, data)) if data.len() >= (width * height) as usize => (flags, data),
+        _ => {
+            debug!("short ALPH chunk");
+            return;
+        }
+    };
+    if flags & 0x03 != 0 {
+        debug!("compressed alpha isn't supported, the frame stays opaque");
+        return;
+    }
+
+    let alpha = unfilter(data, width as usize, height as usize, (flags >> 2) & 0x03);
+    for (pixel, &a) in buffer.pixels_mut().zip(&alpha) {
+        pixel.data[3] = a;
+    }
+}
+
+// undoes the prediction, each value is the difference from the one predicted from its
+// left, above, or both. the first row always goes by the left, and the first column by
+// what's above
+fn unfilter(data: &[u8], width: usize, height: usize, filter: u8) -> Vec<u8> {
+    let mut out = data[..width * height].to_vec();
+    if filter == 0 {
+        return out;
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let predicted = match (x, y) {
+                (0, 0) => 0,
+                (_, 0) => out[i - 1],
+                (0, _) => out[i - width],
+                _ => match filter {
+                    1 => out[i - 1],
+                    2 => out[i - width],
+                    _ => {
+                        let (left, top) = (i32::from(out[i - 1]), i32::from(out[i - width]));
+                        let gradient = left + top - i32::from(out[i - width - 1]);
+                        gradient.clamp(0, 255) as u8
+                    }
+                },
+            };
+            out[i] = out[i].wrapping_add(predicted);
+        }
+    }
+    out
+}
+
+// (type, data), with the padding skipped
+fn chunks(mut data: &[u8]) -> ImageResult<Vec<(&[u8], &[u8])>> {
+    let mut chunks = vec![];
+    while !data.is_empty() {
+        let len = if data.len() >= 8 {
+            le32(&data[4..]) as usize
+        } else {
+            0
+        };
+        if data.len() < 8 || data.len() - 8 < len {
+            return Err(format_error("truncated chunk"));
+        }
+        chunks.push((&data[..4], &data[8..8 + len]));
+        data = &data[::std::cmp::min(8 + len + len % 2, data.len())..];
+    }
+    Ok(chunks)
+}
+
+fn write_chunk(out: &mut Vec<u8>, ty: &[u8], data: &[u8]) {
+    out.extend_from_slice(ty);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+fn le32(data: &[u8]) -> u32 {
+    le24(data) | (u32::from(data[3]) << 24)
+}
+
+fn le24(data: &[u8]) -> u32 {
+    u32::from(data[0]) | (u32::from(data[1]) << 8) | (u32::from(data[2]) << 16)
+}
+
+fn le16(data: &[u8]) -> u16 {
+    u16::from(data[0]) | (u16::from(data[1]) << 8)
+}
+
+fn format_error(msg: &str) -> ImageError {
+    ImageError::FormatError(format!("webp: {}", msg))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Rgba;
+    use std::fs;
+
+    #[test]
+    fn frames() {
+        let data = fs::read("testdata/animated/test.webp").unwrap();
+        assert_eq!(canvas_size(&data), Some((16, 16)));
+        let animated = decode(&data).unwrap().unwrap();
+        assert_eq!((animated.width, animated.height), (16, 16));
+        assert_eq!(animated.repeat, Repeat::Infinite);
+
+        let parts = animated
+            .parts
+            .iter()
+            .map(|p| (p.left, p.top, p.buffer.dimensions(), p.delay.subsec_millis()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parts,
+            vec![
+                (0, 0, (16, 16), 200),
+                (4, 4, (8, 8), 300),
+                (8, 8, (8, 8), 100)
+            ]
+        );
+        let ops = animated
+            .parts
+            .iter()
+            .map(|p| (p.blend, p.dispose))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                (Blend::Over, Dispose::Keep),
+                (Blend::Source, Dispose::Background),
+                (Blend::Over, Dispose::Keep),
+            ]
+        );
+
+        // the raw alpha of the last frame, see-through on the left half
+        let alpha = animated.parts[2]
+            .buffer
+            .pixels()
+            .map(|p| p.data[3])
+            .collect::<Vec<_>>();
+        assert_eq!(&alpha[..8], &[0, 0, 0, 0, 255, 255, 255, 255]);
+        assert_eq!(&alpha[56..], &[0, 0, 0, 0, 255, 255, 255, 255]);
+
+        // the simple format is left to the webp decoder
+        assert!(decode(b"RIFF\x10\0\0\0WEBPVP8 \x04\0\0\0....").unwrap().is_none());
+        assert!(decode(&data[..40]).is_err());
+        assert!(decode(b"RIFF\0\0\0\0WAVE").is_err());
+    }
+
+    #[test]
+    fn alpha_filters() {
+        // 3x2, each filter undoing to the same values
+        let want = [10, 20, 30, 40, 50, 60];
+        let cases: [(u8, [u8; 6]); 4] = [
+            (0, want),
+            (1, [10, 10, 10, 30, 10, 10]),
+            (2, [10, 10, 10, 30, 30, 30]),
+            // 50 is predicted as 40 + 20 - 10, and 60 as 50 + 30 - 20
+            (3, [10, 10, 10, 30, 0, 0]),
+        ];
+        for &(filter, ref data) in &cases {
+            assert_eq!(unfilter(data, 3, 2, filter), want.to_vec(), "{}", filter);
+        }
+
+        let mut buffer = RgbaImage::from_pixel(2, 1, Rgba { data: [1, 2, 3, 255] });
+        apply_alpha(&mut buffer, &[0x04, 7, 1]);
+        assert_eq!(buffer.get_pixel(1, 0).data, [1, 2, 3, 8]);
+        // compressed alpha is left alone
+        apply_alpha(&mut buffer, &[0x01, 7, 1]);
+        assert_eq!(buffer.get_pixel(1, 0).data, [1, 2, 3, 8]);
+    }
+}
@@ -1,10 +1,10 @@
 use std::sync::{Arc, Mutex, RwLock};
 use std::{mem, ptr};
 
-use common::*;
+use win32::common::*;
 
-use filelist::FileList;
-use mainwindow::MainWindow;
+use win32::filelist::FileList;
+use win32::mainwindow::MainWindow;
 
 thread_local!{
     pub static MAIN_HWND: Mutex<Option<windef::HWND>> = Mutex::new(None);
@@ -21,10 +21,12 @@ pub struct App {
     mainwindow: MainWindow,
     filelist: FileList,
     context: Arc<Mutex<Context>>,
+    fullscreen: bool,
 }
 
 impl App {
-    pub fn new() -> Arc<Self> {
+    /// creates the windows, showing what's in `args`
+    pub fn new(args: &Args) -> Arc<Self> {
         COM_INITIALIZED.with(|_| {});
 
         let context = Arc::new(Mutex::new(Context::new()));
@@ -35,6 +37,7 @@ impl App {
             mainwindow,
             filelist,
             context,
+            fullscreen: args.fullscreen,
         });
 
         APP.with(|app| {
@@ -44,8 +47,12 @@ impl App {
             }
         });
 
+        if args.fullscreen {
+            this.mainwindow.window.set_fullscreen();
+        }
         this.mainwindow.window.show();
         this.filelist.align_to(this.mainwindow.hwnd().into());
+        this.mainwindow.start(args);
         this
     }
 
@@ -66,24 +73,20 @@ impl App {
         }
     }
 
-    fn main_hwnd() -> ::window::HWND {
-        MAIN_HWND
-            .with(|hwnd| {
-                hwnd.lock()
-                    .expect("unwrap lock at get MAIN_HWND")
-                    .expect("second uwnrap MAIN_HWND")
-            })
-            .into()
+    fn main_hwnd() -> Option<::win32::window::HWND> {
+        MAIN_HWND.with(|hwnd| {
+            hwnd.lock()
+                .expect("unwrap lock at get MAIN_HWND")
+                .map(Into::into)
+        })
     }
 
-    fn list_hwnd() -> ::window::HWND {
-        LIST_HWND
-            .with(|hwnd| {
-                hwnd.lock()
-                    .expect("unwrap lock at get LIST_HWND")
-                    .expect("second unwrap LIST_HWND")
-            })
-            .into()
+    fn list_hwnd() -> Option<::win32::window::HWND> {
+        LIST_HWND.with(|hwnd| {
+            hwnd.lock()
+                .expect("unwrap lock at get LIST_HWND")
+                .map(Into::into)
+        })
     }
 
     pub fn with_mainwindow<T>(f: impl Fn(&MainWindow) -> T) -> Option<T> {
@@ -109,8 +112,11 @@ impl App {
     }
 
     pub fn handle(ev: &Event) -> isize {
-        let main: ::window::HWND = Self::main_hwnd();
-        let list: ::window::HWND = Self::list_hwnd();
+        // messages (e.g. WM_SIZE) can show up while the windows are still being created
+        let (main, list) = match (Self::main_hwnd(), Self::list_hwnd()) {
+            (Some(main), Some(list)) => (main, list),
+            _ => return 0,
+        };
 
         if ev.event == EventType::CloseRequest && ev.hwnd == main {
             APP.with(|app| {
@@ -123,7 +129,7 @@ impl App {
         }
 
         if ev.hwnd == main {
-            return App::with_mainwindow(|m| m.handle(&ev.event)).unwrap();
+            return App::with_mainwindow(|m| m.handle(&ev.event)).unwrap_or(0);
         }
 
         if ev.hwnd == list {
@@ -134,14 +140,22 @@ impl App {
     }
 
     fn save(&self) {
-        let pos = self.mainwindow.window.get_pos();
-        let size = self.mainwindow.window.get_size_win();
+        // a fullscreen window would be saved as the whole monitor, so keep what we had
+        let (pos, size) = if self.fullscreen {
+            let conf = Config::get();
+            ((conf.position.x, conf.position.y), (conf.size.w, conf.size.h))
+        } else {
+            (
+                self.mainwindow.window.get_pos(),
+                self.mainwindow.window.get_size_win(),
+            )
+        };
 
         Config {
             position: Position { x: pos.0, y: pos.1 },
             size: Size {
-                w: size.0 as i32,
-                h: size.1 as i32,
+                w: size.0,
+                h: size.1,
             },
             filelist: ::config::FileList {
                 snap: self
@@ -150,6 +164,18 @@ impl App {
                     .expect("at unwrap for get snap")
                     .get_snap(),
             },
+            slideshow: Config::get().slideshow.clone(),
+            sort: self
+                .context
+                .lock()
+                .expect("at unwrap for get sort")
+                .get_sort(),
+            // only ever changed by hand
+            scan: Config::get().scan.clone(),
+            shuffle: Config::get().shuffle.clone(),
+            keys: Config::get().keys.clone(),
+            formats: Config::get().formats.clone(),
+            exif: Config::get().exif.clone(),
         }.save();
     }
 }
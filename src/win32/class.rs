@@ -1,6 +1,6 @@
 use std::{mem, ptr};
 
-use common::*;
+use win32::common::*;
 
 #[derive(Debug)]
 pub struct Class(pub ntdef::LPCWSTR);
@@ -1,11 +1,14 @@
-pub use app::*;
-pub use class::*;
+pub use args::*;
 pub use config::*;
 pub use context::*;
-pub use error::*;
 pub use event::*;
 pub use util::*;
-pub use window::*;
+pub use win32::app::*;
+pub use win32::class::*;
+pub use win32::error::*;
+pub use win32::event::*;
+pub use win32::util::*;
+pub use win32::window::*;
 
 pub use winapi::shared::{basetsd, minwindef, ntdef, windef, windowsx};
 pub use winapi::um::{
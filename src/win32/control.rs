@@ -5,8 +5,8 @@ use std::ptr;
 use winapi::shared::{minwindef, ntdef, windef};
 use winapi::um::{commctrl, winuser};
 
-use error::*;
-use util::*;
+use win32::error::*;
+use win32::util::*;
 
 // TODO: determine if we should wrap the HWND so we can have "methods" on it (like SendMessage, ShowWindow)
 
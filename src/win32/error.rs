@@ -1,6 +1,6 @@
 use std::{fmt, ptr};
 
-use common::*;
+use win32::common::*;
 
 pub enum Error {
     Win32Error(u32),
@@ -0,0 +1,76 @@
+use win32::common::*;
+
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    pub event: EventType,
+    pub hwnd: HWND,
+}
+
+impl MouseButton {
+    /// the button a button down/up message is about. wp only has the buttons that are
+    /// held down, so the message has to say which one it was
+    pub fn from_msg(msg: u32, wp: usize) -> Option<MouseButton> {
+        use winapi::um::winuser::*;
+        let button = match msg {
+            WM_LBUTTONDOWN | WM_LBUTTONUP => MouseButton::Left,
+            WM_MBUTTONDOWN | WM_MBUTTONUP => MouseButton::Middle,
+            WM_RBUTTONDOWN | WM_RBUTTONUP => MouseButton::Right,
+            WM_XBUTTONDOWN | WM_XBUTTONUP => match GET_XBUTTON_WPARAM(wp) {
+                XBUTTON1 => MouseButton::X1,
+                XBUTTON2 => MouseButton::X2,
+                button => {
+                    debug!("unknown x button: {}", button);
+                    return None;
+                }
+            },
+            _ => return None,
+        };
+        Some(button)
+    }
+}
+
+impl Modifiers {
+    /// the modifiers that are held down right now, as far as the current message knows
+    pub fn current() -> Modifiers {
+        let held = |vk| unsafe { winuser::GetKeyState(vk) } < 0;
+        Modifiers {
+            ctrl: held(winuser::VK_CONTROL),
+            shift: held(winuser::VK_SHIFT),
+            alt: held(winuser::VK_MENU),
+        }
+    }
+}
+
+impl From<i32> for Key {
+    fn from(key: i32) -> Key {
+        match key {
+            0x41..=0x5A => Key::LETTERS[(key - 0x41) as usize],
+            0x30..=0x39 => Key::DIGITS[(key - 0x30) as usize],
+            winuser::VK_NUMPAD0..=winuser::VK_NUMPAD9 => {
+                Key::DIGITS[(key - winuser::VK_NUMPAD0) as usize]
+            }
+            winuser::VK_F1..=winuser::VK_F12 => Key::FUNCTION[(key - winuser::VK_F1) as usize],
+            winuser::VK_SPACE => Key::Space,
+            winuser::VK_RETURN => Key::Enter,
+            winuser::VK_ESCAPE => Key::Escape,
+            winuser::VK_TAB => Key::Tab,
+            winuser::VK_BACK => Key::Backspace,
+            winuser::VK_DELETE => Key::Delete,
+            winuser::VK_INSERT => Key::Insert,
+            winuser::VK_HOME => Key::Home,
+            winuser::VK_END => Key::End,
+            winuser::VK_PRIOR => Key::PageUp,
+            winuser::VK_NEXT => Key::PageDown,
+            winuser::VK_UP => Key::Up,
+            winuser::VK_DOWN => Key::Down,
+            winuser::VK_LEFT => Key::Left,
+            winuser::VK_RIGHT => Key::Right,
+            winuser::VK_OEM_MINUS | winuser::VK_SUBTRACT => Key::Minus,
+            winuser::VK_OEM_PLUS | winuser::VK_ADD => Key::Plus,
+            winuser::VK_OEM_COMMA => Key::Comma,
+            winuser::VK_OEM_PERIOD | winuser::VK_DECIMAL => Key::Period,
+            winuser::VK_OEM_2 | winuser::VK_DIVIDE => Key::Slash,
+            _ => Key::Other(key),
+        }
+    }
+}
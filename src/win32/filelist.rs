@@ -1,8 +1,8 @@
 use std::sync::{Arc, Mutex};
 use std::{mem, ptr, str};
 
-use common::*;
-use listview::ListView;
+use win32::common::*;
+use win32::listview::ListView;
 
 lazy_static! {
     static ref FILE_CLASS: () = {
@@ -0,0 +1,21 @@
+#![allow(dead_code)]
+use std::mem;
+
+use win32::common::*;
+
+pub struct ImageView {
+    hwnd: windef::HWND,
+}
+
+impl ImageView {
+    pub fn new(parent: windef::HWND) -> Self {
+        use winapi::um::commctrl::*;
+        use winapi::um::winuser::{WS_CHILD, WS_VISIBLE};
+        unsafe {
+            let mut rect = mem::zeroed::<windef::RECT>();
+            winuser::GetClientRect(parent, &mut rect);
+        }
+
+        unimplemented!()
+    }
+}
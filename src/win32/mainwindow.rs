@@ -0,0 +1,293 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{mem, ptr};
+
+use render::Framebuffer;
+use viewer::{Effect, Viewer, ANIMATION_TIMER, WATCH_INTERVAL, WATCH_TIMER};
+use win32::common::*;
+use win32::trackbar::Trackbar;
+
+lazy_static! {
+    static ref MAIN_CLASS: () = {
+        Class::create("PictMainWindowClass".to_wide());
+    };
+}
+
+#[derive(Debug)]
+pub struct MainWindow {
+    pub(crate) window: Window,
+    trackbar: Trackbar,
+    context: Arc<Mutex<Context>>,
+    viewer: Mutex<Viewer>,
+    framebuffer: Mutex<Framebuffer>,
+}
+
+impl MainWindow {
+    pub fn new(context: Arc<Mutex<Context>>) -> Self {
+        ::lazy_static::initialize(&MAIN_CLASS);
+
+        let conf = Config::get();
+
+        let params = Params::builder()
+            .class_name("PictMainWindowClass".to_wide())
+            .window_name("pict".to_wide())
+            .x(conf.position.x)
+            .y(conf.position.y)
+            .style(winuser::WS_TILEDWINDOW | winuser::WS_CLIPCHILDREN)
+            .ex_style(winuser::WS_EX_APPWINDOW | winuser::WS_EX_ACCEPTFILES)
+            .build();
+
+        let window = Window::new(&params);
+        MAIN_HWND.with(|hwnd| {
+            let mut this = hwnd.lock().unwrap();
+            if this.is_none() {
+                *this = Some(window.hwnd())
+            }
+        });
+
+        window.set_size(conf.size.w, conf.size.h);
+        let trackbar = Trackbar::new(window.hwnd());
+
+        let this = Self {
+            window,
+            trackbar,
+            viewer: Mutex::new(Viewer::new(Arc::clone(&context))),
+            framebuffer: Mutex::new(Framebuffer::default()),
+            context,
+        };
+
+        let (width, height) = this.window.get_size();
+        this.handle(&EventType::Resize { width, height });
+        this
+    }
+
+    /// opens what was asked for on the command line
+    pub fn start(&self, args: &Args) {
+        let effects = self.viewer.lock().unwrap().start(args);
+        self.apply(effects);
+
+        let ms = WATCH_INTERVAL.as_secs() as u32 * 1000 + WATCH_INTERVAL.subsec_millis();
+        unsafe { winuser::SetTimer(self.window.hwnd(), WATCH_TIMER, ms, None) };
+    }
+
+    pub fn hwnd(&self) -> HWND {
+        self.window.hwnd().into()
+    }
+
+    // hands the effects of an event to the windows. none of the viewer locks are held here
+    fn apply(&self, effects: Vec<Effect>) {
+        for effect in effects {
+            match effect {
+                Effect::Select(index) => {
+                    App::with_filelist(|f| f.select(index));
+                }
+                Effect::Populate { title, files } => {
+                    App::with_filelist(|f| f.populate(&title, &files));
+                }
+                Effect::ToggleFileList => self.toggle_filelist(),
+                Effect::AlignFileList => {
+                    App::with_filelist(|f| f.align_to(self.hwnd().into()));
+                }
+                Effect::SetFrames(Some(max)) => {
+                    self.trackbar.set_range(max);
+                    self.trackbar.show();
+                }
+                Effect::SetFrames(None) => self.trackbar.hide(),
+                Effect::SetFramePosition(pos) => self.trackbar.set_pos(pos),
+                Effect::Schedule(next) => self.schedule_frame(next),
+                Effect::Redraw => unsafe {
+                    winuser::InvalidateRect(self.window.hwnd(), ptr::null(), 0);
+                },
+            }
+        }
+    }
+
+    fn toggle_filelist(&self) {
+        debug!("toggling filelist");
+
+        let snap = {
+            let this = self.context.lock().unwrap();
+            this.get_snap()
+        };
+
+        App::with_filelist(|f| {
+            if f.is_visible() {
+                f.hide();
+            } else {
+                f.show();
+                if snap {
+                    f.align_to(self.hwnd().into());
+                }
+            }
+        });
+    }
+
+    // (re)arms the animation timer for the next frame, or stops it if we're paused
+    fn schedule_frame(&self, next: Option<Duration>) {
+        unsafe {
+            match next {
+                Some(delay) => {
+                    let ms = delay.as_secs() as u32 * 1000 + delay.subsec_millis();
+                    winuser::SetTimer(self.window.hwnd(), ANIMATION_TIMER, ms, None);
+                }
+                None => {
+                    winuser::KillTimer(self.window.hwnd(), ANIMATION_TIMER);
+                }
+            }
+        }
+    }
+
+    fn on_mouse_down(&self, button: &MouseButton) {
+        // keep getting the mouse while panning, even outside of the window
+        if *button == MouseButton::Middle {
+            unsafe { winuser::SetCapture(self.window.hwnd()) };
+        }
+    }
+
+    fn on_mouse_up(&self, button: &MouseButton) {
+        if *button == MouseButton::Middle {
+            unsafe { winuser::ReleaseCapture() };
+        }
+    }
+
+    fn on_mouse_wheel(&self, delta: i16, pos: (i32, i32)) -> Vec<Effect> {
+        // the wheel position is in screen coords
+        let mut point = windef::POINT { x: pos.0, y: pos.1 };
+        unsafe { winuser::ScreenToClient(self.window.hwnd(), &mut point) };
+
+        self.viewer.lock().unwrap().handle(&EventType::MouseWheel {
+            delta,
+            x: point.x,
+            y: point.y,
+        })
+    }
+
+    fn on_paint(&self) {
+        use winapi::um::wingdi;
+
+        let (w, h) = self.window.get_size();
+        let fb = &mut self.framebuffer.lock().unwrap();
+        if fb.width() != w as usize || fb.height() != h as usize {
+            fb.resize(w, h);
+        }
+        self.viewer.lock().unwrap().render(fb);
+
+        unsafe {
+            let mut ps = mem::zeroed::<winuser::PAINTSTRUCT>();
+            let hdc = winuser::BeginPaint(self.window.hwnd(), &mut ps);
+
+            let mut info = mem::zeroed::<wingdi::BITMAPINFO>();
+            info.bmiHeader.biSize = mem::size_of::<wingdi::BITMAPINFOHEADER>() as u32;
+            info.bmiHeader.biWidth = w;
+            info.bmiHeader.biHeight = -h; // top-down
+            info.bmiHeader.biPlanes = 1;
+            info.bmiHeader.biBitCount = 32;
+            info.bmiHeader.biCompression = wingdi::BI_RGB;
+
+            wingdi::SetDIBitsToDevice(
+                hdc,
+                0,
+                0,
+                w as u32,
+                h as u32,
+                0,
+                0,
+                0,
+                h as u32,
+                fb.pixels().as_ptr() as *const _,
+                &info,
+                wingdi::DIB_RGB_COLORS,
+            );
+            winuser::EndPaint(self.window.hwnd(), &ps);
+        }
+    }
+
+    fn on_moving(&self, _pos: (i32, i32)) {
+        self.reposition_trackbar();
+
+        let this = self.context.lock().unwrap();
+        if this.get_snap() {
+            App::with_filelist(|f| f.align_to(self.hwnd().into()));
+        }
+    }
+
+    fn on_hscroll(&self, wp: usize, _lp: isize) -> Vec<Effect> {
+        use winapi::um::commctrl::*;
+
+        match minwindef::LOWORD(wp as u32) as usize {
+            TB_THUMBTRACK => {
+                let pos = minwindef::HIWORD(wp as u32) as usize;
+                self.viewer.lock().unwrap().scrub_drag(pos)
+            }
+            TB_ENDTRACK => self.viewer.lock().unwrap().scrub_release(),
+            TB_LINEDOWN | TB_LINEUP | TB_PAGEDOWN | TB_PAGEUP | TB_TOP | TB_BOTTOM
+            | TB_THUMBPOSITION => {
+                let pos = self.trackbar.get_pos();
+                self.viewer.lock().unwrap().scrub_seek(pos)
+            }
+            _ => vec![],
+        }
+    }
+
+    fn on_color_static(&self, wp: usize, lp: isize) -> isize {
+        use std::mem;
+        use winapi::um::wingdi;
+
+        use winapi::ctypes;
+
+        unsafe {
+            mem::transmute::<*mut ctypes::c_void, minwindef::LRESULT>(wingdi::GetStockObject(
+                wingdi::WHITE_BRUSH as i32,
+            ))
+        }
+    }
+
+    fn reposition_trackbar(&self) {
+        unsafe {
+            let mut rect: windef::RECT = ::std::mem::zeroed();
+            winuser::GetClientRect(self.hwnd().into(), &mut rect);
+            winuser::SetWindowPos(
+                self.trackbar.hwnd().into(),
+                winuser::HWND_TOP,
+                10,
+                rect.bottom - 20,
+                rect.right - 20,
+                20,
+                winuser::SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    pub fn handle(&self, ev: &EventType) -> isize {
+        let effects = match *ev {
+            EventType::MouseDown { ref button, .. } => {
+                self.on_mouse_down(button);
+                self.viewer.lock().unwrap().handle(ev)
+            }
+            EventType::MouseUp { ref button, .. } => {
+                self.on_mouse_up(button);
+                self.viewer.lock().unwrap().handle(ev)
+            }
+            EventType::MouseWheel { delta, x, y } => self.on_mouse_wheel(delta, (x, y)),
+            //EventType::Moved { x, y } => {}
+            EventType::Moving { x, y } => {
+                self.on_moving((x, y));
+                vec![]
+            }
+            EventType::Resize { .. } => {
+                self.reposition_trackbar();
+                self.viewer.lock().unwrap().handle(ev)
+            }
+            EventType::HScroll { wp, lp } => self.on_hscroll(wp, lp),
+            EventType::Paint => {
+                self.on_paint();
+                return 1;
+            }
+            EventType::CtrlColorStatic { wp, lp } => return self.on_color_static(wp, lp),
+            _ => self.viewer.lock().unwrap().handle(ev),
+        };
+
+        self.apply(effects);
+        0
+    }
+}
@@ -0,0 +1,33 @@
+//! The Win32 frontend
+mod common;
+
+mod error;
+mod util;
+
+mod class;
+//mod control;
+mod window;
+
+mod imageview;
+mod listview;
+
+mod filelist;
+mod mainwindow;
+
+mod app;
+mod event;
+
+mod trackbar;
+
+pub use self::app::*;
+pub use self::class::*;
+pub use self::error::*;
+pub use self::event::*;
+pub use self::util::*;
+pub use self::window::*;
+
+pub use winapi::shared::{basetsd, minwindef, ntdef, windef, windowsx};
+pub use winapi::um::{
+    combaseapi, commctrl, errhandlingapi, libloaderapi, objbase, processthreadsapi, shellapi,
+    winbase, wingdi, winuser,
+};
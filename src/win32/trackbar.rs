@@ -1,6 +1,6 @@
 use std::{mem, ptr};
 
-use common::*;
+use win32::common::*;
 
 #[derive(Debug)]
 pub struct Trackbar {
@@ -12,9 +12,7 @@ impl Trackbar {
     pub fn new(parent: windef::HWND) -> Self {
         let hwnd = Trackbar::create_track_bar(parent);
 
-        let this = Self { parent, hwnd };
-        this.show();
-        this
+        Self { parent, hwnd }
     }
 
     fn create_track_bar(parent: windef::HWND) -> windef::HWND {
@@ -63,13 +61,27 @@ impl Trackbar {
         unsafe { winuser::ShowWindow(self.hwnd, winuser::SW_HIDE) };
     }
 
+    /// sets the range to `0..=max` and moves the thumb to the start
+    pub fn set_range(&self, max: usize) {
+        use winapi::um::commctrl::*;
+
+        unsafe {
+            winuser::SendMessageW(self.hwnd, TBM_SETPAGESIZE, 0, 1);
+            winuser::SendMessageW(self.hwnd, TBM_SETRANGEMIN, 0, 0);
+            winuser::SendMessageW(self.hwnd, TBM_SETRANGEMAX, 0, max as isize);
+            winuser::SendMessageW(self.hwnd, TBM_SETPOS, 1, 0);
+        }
+    }
+
+    pub fn get_pos(&self) -> usize {
+        unsafe { winuser::SendMessageW(self.hwnd, commctrl::TBM_GETPOS, 0, 0) as usize }
+    }
+
+    pub fn set_pos(&self, pos: usize) {
+        unsafe { winuser::SendMessageW(self.hwnd, commctrl::TBM_SETPOS, 1, pos as isize) };
+    }
+
     pub fn hwnd(&self) -> HWND {
         HWND::from(self.hwnd)
     }
 }
-
-/* need to think about what the trackbar will do
-I should probably impl a Index trait
-need a show and a hide
-need to set the number of steps
-*/
@@ -0,0 +1,52 @@
+use std::{mem, ptr};
+
+use win32::common::*;
+
+pub trait ToWide {
+    fn to_wide(&self) -> *const u16;
+    fn to_wide_mut(&self) -> *mut u16;
+}
+
+impl<T: AsRef<str>> ToWide for T {
+    fn to_wide(&self) -> *const u16 {
+        if self.as_ref().is_empty() {
+            let data = "\0".encode_utf16().collect::<Vec<_>>();
+            let res = data.as_ptr();
+            mem::forget(data);
+            return res;
+        }
+
+        let mut s = self.as_ref().to_owned();
+        let c = s.chars().rev().take(1).next().unwrap();
+        if c != '\0' {
+            s += "\0"
+        };
+        let data = s.encode_utf16().collect::<Vec<_>>();
+        let res = data.as_ptr();
+        mem::forget(data);
+        res
+    }
+
+    fn to_wide_mut(&self) -> *mut u16 {
+        if self.as_ref().is_empty() {
+            let mut data = "\0".encode_utf16().collect::<Vec<_>>();
+            let res = data.as_mut_ptr();
+            mem::forget(data);
+            return res;
+        }
+
+        let mut s = self.as_ref().to_owned();
+        let c = s.chars().rev().take(1).next().unwrap();
+        if c != '\0' {
+            s += "\0"
+        };
+        let mut data = s.encode_utf16().collect::<Vec<_>>();
+        let res = data.as_mut_ptr();
+        mem::forget(data);
+        res
+    }
+}
+
+pub fn hinstance() -> minwindef::HINSTANCE {
+    unsafe { libloaderapi::GetModuleHandleW(ptr::null_mut()) }
+}
@@ -1,6 +1,6 @@
 use std::{mem, ptr};
 
-use common::*;
+use win32::common::*;
 
 #[derive(Debug)]
 pub struct Window {
@@ -82,6 +82,32 @@ impl Window {
         }
     }
 
+    /// covers the monitor the window is on, without a border
+    pub fn set_fullscreen(&self) {
+        unsafe {
+            let monitor =
+                winuser::MonitorFromWindow(self.hwnd(), winuser::MONITOR_DEFAULTTONEAREST);
+            let mut info: winuser::MONITORINFO = mem::zeroed();
+            info.cbSize = mem::size_of::<winuser::MONITORINFO>() as u32;
+            winuser::GetMonitorInfoW(monitor, &mut info);
+
+            let style = winuser::GetWindowLongPtrW(self.hwnd(), winuser::GWL_STYLE);
+            let style = style & !(winuser::WS_TILEDWINDOW as isize) | winuser::WS_POPUP as isize;
+            winuser::SetWindowLongPtrW(self.hwnd(), winuser::GWL_STYLE, style);
+
+            let rect = info.rcMonitor;
+            winuser::SetWindowPos(
+                self.hwnd(),
+                winuser::HWND_TOP,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                winuser::SWP_FRAMECHANGED | winuser::SWP_NOOWNERZORDER,
+            );
+        }
+    }
+
     pub fn set_title(&self, title: &str) {
         unsafe { winuser::SetWindowTextW(self.hwnd(), title.to_wide()) };
     }
@@ -98,7 +124,7 @@ impl Window {
             params.height,
             params.parent,
             params.menu,
-            ::util::hinstance(),
+            ::win32::util::hinstance(),
             params.lp_param,
         );
         if hwnd.is_null() {
@@ -155,22 +181,89 @@ pub unsafe extern "system" fn callback(
             }
         }
 
-        WM_KEYDOWN => {
-            let key: Key = (wp as i32).into();
+        WM_SIZE => {
+            let width = i32::from(minwindef::LOWORD(lp as u32));
+            let height = i32::from(minwindef::HIWORD(lp as u32));
             App::handle(&Event {
-                event: EventType::KeyDown { key },
+                event: EventType::Resize { width, height },
                 hwnd: target,
             })
         }
 
-        WM_LBUTTONDOWN | WM_MBUTTONDOWN | WM_RBUTTONDOWN => {
+        // alt turns keys into system keys. they still go to DefSubclassProc, so alt+f4 works
+        WM_KEYDOWN | WM_SYSKEYDOWN => {
+            let key: Key = (wp as i32).into();
+            let event = EventType::KeyDown {
+                key,
+                modifiers: Modifiers::current(),
+                repeat: lp & (1 << 30) != 0, // the key was already down
+            };
+            let res = App::handle(&Event {
+                event,
+                hwnd: target,
+            });
+            if msg == WM_SYSKEYDOWN {
+                return commctrl::DefSubclassProc(hwnd, msg, wp, lp);
+            }
+            res
+        }
+
+        WM_KEYUP | WM_SYSKEYUP => {
+            let key: Key = (wp as i32).into();
+            let event = EventType::KeyUp {
+                key,
+                modifiers: Modifiers::current(),
+            };
+            let res = App::handle(&Event {
+                event,
+                hwnd: target,
+            });
+            if msg == WM_SYSKEYUP {
+                return commctrl::DefSubclassProc(hwnd, msg, wp, lp);
+            }
+            res
+        }
+
+        // a lone half of a surrogate pair isn't anything that can be typed
+        WM_CHAR => match ::std::char::from_u32(wp as u32) {
+            Some(c) => App::handle(&Event {
+                event: EventType::Char { c },
+                hwnd: target,
+            }),
+            None => 0,
+        },
+
+        WM_LBUTTONDOWN | WM_MBUTTONDOWN | WM_RBUTTONDOWN | WM_XBUTTONDOWN => {
             let x = windowsx::GET_X_LPARAM(lp);
             let y = windowsx::GET_Y_LPARAM(lp);
-            let button: MouseButton = wp.into();
-            App::handle(&Event {
-                event: EventType::MouseDown { button, x, y },
-                hwnd: target,
-            })
+            let res = match MouseButton::from_msg(msg, wp) {
+                Some(button) => App::handle(&Event {
+                    event: EventType::MouseDown { button, x, y },
+                    hwnd: target,
+                }),
+                None => 0,
+            };
+            // the x buttons want TRUE back
+            if msg == WM_XBUTTONDOWN {
+                return minwindef::TRUE as minwindef::LRESULT;
+            }
+            res
+        }
+
+        WM_LBUTTONUP | WM_MBUTTONUP | WM_RBUTTONUP | WM_XBUTTONUP => {
+            let x = windowsx::GET_X_LPARAM(lp);
+            let y = windowsx::GET_Y_LPARAM(lp);
+            let res = match MouseButton::from_msg(msg, wp) {
+                Some(button) => App::handle(&Event {
+                    event: EventType::MouseUp { button, x, y },
+                    hwnd: target,
+                }),
+                None => 0,
+            };
+            if msg == WM_XBUTTONUP {
+                return minwindef::TRUE as minwindef::LRESULT;
+            }
+            res
         }
 
         WM_MOUSEMOVE => {
@@ -197,6 +290,7 @@ pub unsafe extern "system" fn callback(
             let count = shellapi::DragQueryFileW(hdrop, 0xFFFF_FFFF, ptr::null_mut(), 0);
 
             let mut buf: [u16; minwindef::MAX_PATH] = mem::uninitialized();
+            let mut files = vec![];
             for i in 0..count {
                 let n = shellapi::DragQueryFileW(
                     hdrop,
@@ -205,16 +299,18 @@ pub unsafe extern "system" fn callback(
                     minwindef::MAX_PATH as u32,
                 ) as usize;
                 if n > 0 {
-                    App::handle(&Event {
-                        event: EventType::DropFile {
-                            file: String::from_utf16_lossy(&buf[0..n]),
-                        },
-                        hwnd: target,
-                    });
+                    files.push(String::from_utf16_lossy(&buf[0..n]));
                 }
             }
-
             shellapi::DragFinish(hdrop);
+
+            // all of them at once, so they end up in the same list
+            if !files.is_empty() {
+                App::handle(&Event {
+                    event: EventType::DropFiles { files },
+                    hwnd: target,
+                });
+            }
             0
         }
 
@@ -223,6 +319,20 @@ pub unsafe extern "system" fn callback(
             hwnd: target,
         }),
 
+        WM_TIMER => App::handle(&Event {
+            event: EventType::Timer { id: wp },
+            hwnd: target,
+        }),
+
+        // only the main window paints itself, everything else gets the default painting
+        WM_PAINT => match App::handle(&Event {
+            event: EventType::Paint,
+            hwnd: target,
+        }) {
+            0 => commctrl::DefSubclassProc(hwnd, msg, wp, lp),
+            _ => 0,
+        },
+
         WM_HSCROLL => App::handle(&Event {
             event: EventType::HScroll { wp, lp },
             hwnd: target,